@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+/// BMCLAPI镜像的地址
+pub const BMCLAPI_URL: &str = "https://bmclapi2.bangbang93.com";
+/// 官方下载源的名字
+pub const OFFICIAL_SOURCE: &str = "official";
+
+/// 下载源，把官方的下载地址改写为镜像的地址
+///
+/// 改写规则按顺序匹配地址前缀，没有匹配的规则时地址保持不变
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadSource {
+    pub name: String,
+    /// (官方地址前缀, 镜像地址前缀)
+    pub rewrites: Vec<(String, String)>,
+}
+
+impl DownloadSource {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_owned(), rewrites: vec![] }
+    }
+
+    /// 官方下载源，不改写任何地址
+    pub fn official() -> Self {
+        Self::new(OFFICIAL_SOURCE)
+    }
+
+    /// BMCLAPI镜像，覆盖Mojang、Forge、NeoForge、Fabric、Quilt以及authlib-injector
+    pub fn bmclapi() -> Self {
+        Self::mirror("bmclapi", BMCLAPI_URL)
+    }
+
+    /// 使用和BMCLAPI相同路径规则的镜像，例如自建的BMCLAPI节点
+    pub fn mirror(name: &str, base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        let maven = format!("{base_url}/maven");
+        let rules = [
+            ("https://piston-meta.mojang.com", base_url.to_owned()),
+            ("https://piston-data.mojang.com", base_url.to_owned()),
+            ("https://launchermeta.mojang.com", base_url.to_owned()),
+            ("https://launcher.mojang.com", base_url.to_owned()),
+            ("https://libraries.minecraft.net", maven.clone()),
+            ("https://resources.download.minecraft.net", format!("{base_url}/assets")),
+            ("https://maven.minecraftforge.net", maven.clone()),
+            ("https://files.minecraftforge.net/maven", maven.clone()),
+            ("https://maven.neoforged.net/releases", maven.clone()),
+            ("https://meta.fabricmc.net", format!("{base_url}/fabric-meta")),
+            ("https://maven.fabricmc.net", maven.clone()),
+            ("https://meta.quiltmc.org", format!("{base_url}/quilt-meta")),
+            ("https://maven.quiltmc.org/repository/release", maven),
+            ("https://authlib-injector.yushi.moe", format!("{base_url}/mirrors/authlib-injector")),
+        ];
+        rules.into_iter().fold(Self::new(name), |source, (from, to)| source.with_rewrite(from, &to))
+    }
+
+    /// 添加一条改写规则
+    pub fn with_rewrite(mut self, from: &str, to: &str) -> Self {
+        self.rewrites.push((from.trim_end_matches('/').to_owned(), to.trim_end_matches('/').to_owned()));
+        self
+    }
+
+    /// 改写下载地址，`http://`的官方地址也会被匹配
+    pub fn rewrite(&self, url: &str) -> String {
+        let https_url = url.strip_prefix("http://").map(|rest| format!("https://{rest}"));
+        for (from, to) in &self.rewrites {
+            let rest = url.strip_prefix(from.as_str())
+                .or_else(|| https_url.as_deref()?.strip_prefix(from.as_str()));
+            if let Some(rest) = rest.filter(|rest| rest.is_empty() || rest.starts_with('/') || rest.starts_with('?')) {
+                return format!("{to}{rest}");
+            }
+        }
+        url.to_owned()
+    }
+}
+
+/// 下载源的速度统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceStats {
+    /// 下载成功的字节数
+    pub bytes: u64,
+    /// 下载成功的文件花费的时间
+    pub duration: Duration,
+    pub successes: u32,
+    pub failures: u32,
+}
+
+impl SourceStats {
+    /// 平均速度（字节/秒），还没有成功下载过时返回`None`
+    pub fn speed(&self) -> Option<f64> {
+        let seconds = self.duration.as_secs_f64();
+        (self.successes > 0 && seconds > 0.0).then(|| self.bytes as f64 / seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_official_urls_to_bmclapi() {
+        let source = DownloadSource::bmclapi();
+        let cases = [
+            ("https://piston-meta.mojang.com/mc/game/version_manifest_v2.json", "https://bmclapi2.bangbang93.com/mc/game/version_manifest_v2.json"),
+            ("https://piston-data.mojang.com/v1/objects/84194a2f/client.jar", "https://bmclapi2.bangbang93.com/v1/objects/84194a2f/client.jar"),
+            ("https://launchermeta.mojang.com/v1/packages/3d8e/1.12.json", "https://bmclapi2.bangbang93.com/v1/packages/3d8e/1.12.json"),
+            ("https://libraries.minecraft.net/com/mojang/logging/1.1.1/logging-1.1.1.jar", "https://bmclapi2.bangbang93.com/maven/com/mojang/logging/1.1.1/logging-1.1.1.jar"),
+            ("https://resources.download.minecraft.net/bd/bdf48ef6b5d0d23bbb02e17d04865216179f510a", "https://bmclapi2.bangbang93.com/assets/bd/bdf48ef6b5d0d23bbb02e17d04865216179f510a"),
+            ("https://maven.minecraftforge.net/net/minecraftforge/forge/1.20.1-47.1.0/forge-1.20.1-47.1.0-installer.jar", "https://bmclapi2.bangbang93.com/maven/net/minecraftforge/forge/1.20.1-47.1.0/forge-1.20.1-47.1.0-installer.jar"),
+            ("http://files.minecraftforge.net/maven/net/minecraftforge/forge/1.7.10-10.13.4.1614-1.7.10/forge.jar", "https://bmclapi2.bangbang93.com/maven/net/minecraftforge/forge/1.7.10-10.13.4.1614-1.7.10/forge.jar"),
+            ("https://maven.neoforged.net/releases/net/neoforged/neoforge/20.4.80/neoforge-20.4.80-installer.jar", "https://bmclapi2.bangbang93.com/maven/net/neoforged/neoforge/20.4.80/neoforge-20.4.80-installer.jar"),
+            ("https://meta.fabricmc.net/v2/versions/loader?limit=1", "https://bmclapi2.bangbang93.com/fabric-meta/v2/versions/loader?limit=1"),
+            ("https://maven.fabricmc.net/net/fabricmc/fabric-loader/0.14.21/fabric-loader-0.14.21.jar", "https://bmclapi2.bangbang93.com/maven/net/fabricmc/fabric-loader/0.14.21/fabric-loader-0.14.21.jar"),
+            ("https://meta.quiltmc.org/v3/versions/loader", "https://bmclapi2.bangbang93.com/quilt-meta/v3/versions/loader"),
+            ("https://maven.quiltmc.org/repository/release/org/quiltmc/quilt-loader/0.19.2/quilt-loader-0.19.2.jar", "https://bmclapi2.bangbang93.com/maven/org/quiltmc/quilt-loader/0.19.2/quilt-loader-0.19.2.jar"),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(source.rewrite(url), expected);
+        }
+    }
+
+    #[test]
+    fn keeps_unmatched_urls() {
+        let source = DownloadSource::mirror("local", "http://127.0.0.1:8080/");
+        assert_eq!(source.rewrite("https://libraries.minecraft.net/a/b.jar"), "http://127.0.0.1:8080/maven/a/b.jar");
+        // 只匹配完整的域名
+        assert_eq!(source.rewrite("https://libraries.minecraft.net.example.com/a.jar"), "https://libraries.minecraft.net.example.com/a.jar");
+        assert_eq!(source.rewrite("https://example.com/libraries.minecraft.net/a.jar"), "https://example.com/libraries.minecraft.net/a.jar");
+        assert_eq!(DownloadSource::official().rewrite("https://libraries.minecraft.net/a/b.jar"), "https://libraries.minecraft.net/a/b.jar");
+
+        // 先添加的规则优先
+        let source = DownloadSource::new("custom").with_rewrite("https://example.com/a/", "https://first.example.com").with_rewrite("https://example.com", "https://second.example.com");
+        assert_eq!(source.rewrite("https://example.com/a/b.jar"), "https://first.example.com/b.jar");
+        assert_eq!(source.rewrite("https://example.com/c.jar"), "https://second.example.com/c.jar");
+    }
+
+    #[test]
+    fn computes_speed() {
+        assert_eq!(SourceStats::default().speed(), None);
+        let stats = SourceStats { bytes: 4096, duration: Duration::from_secs(2), successes: 1, failures: 3 };
+        assert_eq!(stats.speed(), Some(2048.0));
+        assert_eq!(SourceStats { bytes: 0, duration: Duration::from_secs(1), successes: 0, failures: 2 }.speed(), None);
+    }
+}
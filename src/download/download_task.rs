@@ -0,0 +1,88 @@
+use std::fs;
+
+use crate::error::{CoreError, Result};
+use crate::json::json_version::{JsonDownload, JsonLibrary};
+use crate::tools::file_tools::MavenCoordinate;
+use crate::tools::hash_tools::sha1_file;
+
+/// 一个下载任务
+#[derive(Debug, Clone)]
+pub struct DownloadTask {
+    pub url: String,
+    /// 保存到本地的路径
+    pub path: String,
+    /// 文件的SHA-1，为`None`时不校验
+    pub sha1: Option<String>,
+    /// 文件大小，为`None`时不校验
+    pub size: Option<u64>,
+}
+
+impl DownloadTask {
+    pub fn new(url: &str, path: &str) -> Self {
+        Self { url: url.to_owned(), path: path.to_owned(), sha1: None, size: None }
+    }
+
+    /// 从版本json中的下载信息创建任务，没有`url`时返回`None`
+    pub fn from_json(download: &JsonDownload, path: &str) -> Option<Self> {
+        let url = download.url.as_deref().filter(|url| !url.is_empty())?;
+        Some(Self {
+            url: url.to_owned(),
+            path: path.to_owned(),
+            sha1: download.sha1.clone(),
+            size: download.size.and_then(|size| u64::try_from(size).ok()),
+        })
+    }
+
+    /// 库文件本体的下载任务，保存到`libraries_path`中
+    ///
+    /// 有`downloads`时使用其中的`artifact`；旧版本的Forge等只提供了`name`和`url`，
+    /// 根据Maven坐标从`url`（没有时为`repository`）推导下载地址
+    pub fn from_library(lib: &JsonLibrary, libraries_path: &str, repository: &str) -> Option<Self> {
+        match &lib.downloads {
+            Some(downloads) => {
+                let artifact = downloads.artifact.as_ref()?;
+                Self::from_json(artifact, &format!("{libraries_path}/{}", artifact.path.as_ref()?))
+            }
+            None => {
+                let coordinate = lib.name.parse::<MavenCoordinate>().ok()?;
+                let url = coordinate.url(lib.url.as_deref().unwrap_or(repository));
+                let mut task = Self::new(&url, &format!("{libraries_path}/{}", coordinate.path()));
+                task.sha1 = lib.sha1.clone();
+                task.size = lib.size;
+                Some(task)
+            }
+        }
+    }
+
+    pub fn with_sha1(mut self, sha1: &str) -> Self {
+        self.sha1 = Some(sha1.to_owned());
+        self
+    }
+
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// 检查`file`的大小和SHA-1是否和任务一致
+    pub fn verify(&self, file: &str) -> Result<()> {
+        let metadata = fs::metadata(file).map_err(|err| CoreError::io(file, err))?;
+        if let Some(expected) = self.size {
+            if metadata.len() != expected {
+                return Err(CoreError::SizeMismatch { path: file.to_owned(), expected, actual: metadata.len() });
+            }
+        }
+        if let Some(expected) = &self.sha1 {
+            let actual = sha1_file(file).map_err(|err| CoreError::io(file, err))?;
+            if !actual.eq_ignore_ascii_case(expected) {
+                return Err(CoreError::HashMismatch { path: file.to_owned(), expected: expected.clone(), actual });
+            }
+        }
+        Ok(())
+    }
+
+    /// 本地文件是否已经存在并且完整
+    pub fn is_complete(&self) -> bool {
+        self.verify(&self.path).is_ok()
+    }
+}
@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+use serde::de::DeserializeOwned;
+
+use crate::error::{CoreError, Result};
+use crate::tools::http_tools;
+
+use super::download_source::{DownloadSource, SourceStats};
+use super::download_task::DownloadTask;
+
+/// 下载器，支持并发下载、失败重试、断点续传、SHA-1校验以及镜像下载源
+///
+/// 配置了镜像时会先使用镜像，镜像返回404、文件校验失败或者多次重试仍然失败时改用下一个下载源，最后使用官方源
+pub struct Downloader {
+    /// 同时下载的文件数量
+    pub concurrency: usize,
+    /// 每个文件失败后的重试次数
+    pub retries: u32,
+    /// 替换所有下载地址的协议和域名，例如`http://127.0.0.1:8080`
+    pub base_url: Option<String>,
+    /// 优先使用的下载源，官方源总是作为最后的备用
+    pub sources: Vec<DownloadSource>,
+    /// 按照统计的速度自动选择最快的下载源
+    pub auto_select: bool,
+    stats: Mutex<HashMap<String, SourceStats>>,
+    agent: ureq::Agent,
+}
+
+impl Default for Downloader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Downloader {
+    pub fn new() -> Self {
+        Self {
+            concurrency: 8,
+            retries: 3,
+            base_url: None,
+            sources: vec![],
+            auto_select: false,
+            stats: Mutex::default(),
+            agent: http_tools::agent(),
+        }
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// 把所有下载地址的协议和域名替换为`base_url`，只保留路径部分
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.trim_end_matches('/').to_owned());
+        self
+    }
+
+    /// 添加下载源，例如```DownloadSource::bmclapi()```
+    pub fn with_source(mut self, source: DownloadSource) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    pub fn with_auto_select(mut self, auto_select: bool) -> Self {
+        self.auto_select = auto_select;
+        self
+    }
+
+    /// 获取实际请求的地址（优先使用的下载源）
+    pub fn resolve_url(&self, url: &str) -> String {
+        self.candidate_urls(url).into_iter().next().map(|(_, url)| url).unwrap_or_else(|| url.to_owned())
+    }
+
+    /// 各个下载源的速度统计
+    pub fn source_stats(&self) -> HashMap<String, SourceStats> {
+        self.stats.lock().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+
+    /// 统计的速度最快的下载源
+    pub fn fastest_source(&self) -> Option<String> {
+        self.source_stats().into_iter()
+            .filter_map(|(name, stats)| Some((name, stats.speed()?)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(name, _)| name)
+    }
+
+    /// 下载单个文件，本地文件已经完整时直接跳过
+    pub fn download(&self, task: &DownloadTask) -> Result<()> {
+        if task.is_complete() {
+            return Ok(());
+        }
+        let mut last_error: Option<CoreError> = None;
+        for (source, url) in self.candidate_urls(&task.url) {
+            let mut attempt = 0;
+            loop {
+                let started_at = Instant::now();
+                match self.try_download(task, &url) {
+                    Ok(bytes) => {
+                        self.record(&source, |stats| {
+                            stats.bytes += bytes;
+                            stats.duration += started_at.elapsed();
+                            stats.successes += 1;
+                        });
+                        return Ok(());
+                    }
+                    Err(err) => {
+                        self.record(&source, |stats| stats.failures += 1);
+                        let fallback = is_fallback_error(&err);
+                        last_error = Some(err);
+                        if fallback || attempt >= self.retries {
+                            break;
+                        }
+                        attempt += 1;
+                    }
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| CoreError::Download { url: task.url.clone(), message: "No download source".to_owned() }))
+    }
+
+    /// GET请求并解析json，请求失败时依次尝试其他下载源
+    pub fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let mut last_error: Option<CoreError> = None;
+        for (source, url) in self.candidate_urls(url) {
+            match http_tools::get_json(&self.agent, &url) {
+                Ok(json) => return Ok(json),
+                Err(err) => {
+                    self.record(&source, |stats| stats.failures += 1);
+                    last_error = Some(err);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| CoreError::Download { url: url.to_owned(), message: "No download source".to_owned() }))
+    }
+
+    /// 并发下载多个文件
+    ///
+    /// 所有任务都会尝试下载，如果有失败的任务，返回遇到的第一个错误
+    pub fn download_all(&self, tasks: &[DownloadTask]) -> Result<()> {
+        let next = AtomicUsize::new(0);
+        let first_error: Mutex<Option<CoreError>> = Mutex::new(None);
+        thread::scope(|scope| {
+            for _ in 0..self.concurrency.min(tasks.len()) {
+                scope.spawn(|| {
+                    while let Some(task) = tasks.get(next.fetch_add(1, Ordering::SeqCst)) {
+                        if let Err(err) = self.download(task) {
+                            first_error.lock().unwrap_or_else(|err| err.into_inner()).get_or_insert(err);
+                        }
+                    }
+                });
+            }
+        });
+        match first_error.into_inner().unwrap_or_else(|err| err.into_inner()) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// 下载到`<path>.part`，校验通过后再重命名，`.part`已经存在时从断点继续
+    ///
+    /// 返回这次下载的字节数
+    fn try_download(&self, task: &DownloadTask, url: &str) -> Result<u64> {
+        let part_path = format!("{}.part", task.path);
+        if let Some(parent_dir) = Path::new(&task.path).parent() {
+            fs::create_dir_all(parent_dir).map_err(|err| CoreError::io(parent_dir.to_string_lossy(), err))?;
+        }
+
+        let resume_from = fs::metadata(&part_path).map(|metadata| metadata.len()).unwrap_or(0);
+        let mut request = self.agent.get(url);
+        if resume_from > 0 {
+            request = request.set("Range", &format!("bytes={resume_from}-"));
+        }
+        let response = match request.call() {
+            Ok(response) => response,
+            Err(err) => {
+                if let ureq::Error::Status(416, _) = err {
+                    // 断点位置不正确，删除后重新下载
+                    let _ = fs::remove_file(&part_path);
+                }
+                return Err(http_tools::http_error(url, err));
+            }
+        };
+
+        let append = resume_from > 0 && response.status() == 206;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&part_path)
+            .map_err(|err| CoreError::io(&part_path, err))?;
+        let bytes = std::io::copy(&mut response.into_reader(), &mut file)
+            .map_err(|err| CoreError::Download { url: url.to_owned(), message: err.to_string() })?;
+        drop(file);
+
+        if let Err(err) = task.verify(&part_path) {
+            let _ = fs::remove_file(&part_path);
+            return Err(err);
+        }
+        fs::rename(&part_path, &task.path).map_err(|err| CoreError::io(&task.path, err))?;
+        Ok(bytes)
+    }
+
+    /// 按照优先顺序获取每个下载源的地址，重复的地址只保留一个
+    fn candidate_urls(&self, url: &str) -> Vec<(String, String)> {
+        let mut sources: Vec<&DownloadSource> = self.sources.iter().collect();
+        if self.auto_select {
+            // 还没有统计的下载源排在前面，这样每个下载源都会被尝试
+            let stats = self.source_stats();
+            let speed = |source: &DownloadSource| stats.get(&source.name).and_then(SourceStats::speed).unwrap_or(f64::INFINITY);
+            sources.sort_by(|a, b| speed(b).total_cmp(&speed(a)));
+        }
+        let official = DownloadSource::official();
+        sources.push(&official);
+
+        let mut candidates: Vec<(String, String)> = vec![];
+        for source in sources {
+            let url = self.apply_base_url(&source.rewrite(url));
+            if !candidates.iter().any(|(_, candidate)| *candidate == url) {
+                candidates.push((source.name.clone(), url));
+            }
+        }
+        candidates
+    }
+
+    fn apply_base_url(&self, url: &str) -> String {
+        let Some(base_url) = &self.base_url else {
+            return url.to_owned();
+        };
+        let path = match url.find("://") {
+            Some(index) => url[index + 3..].find('/').map(|start| &url[index + 3 + start..]).unwrap_or("/"),
+            None => url,
+        };
+        format!("{base_url}{path}")
+    }
+
+    fn record(&self, source: &str, update: impl FnOnce(&mut SourceStats)) {
+        let mut stats = self.stats.lock().unwrap_or_else(|err| err.into_inner());
+        update(stats.entry(source.to_owned()).or_default());
+    }
+}
+
+/// 是否应该直接改用下一个下载源：文件不存在或者下载的文件不正确
+fn is_fallback_error(err: &CoreError) -> bool {
+    matches!(err, CoreError::HttpStatus { status: 404, .. } | CoreError::HashMismatch { .. } | CoreError::SizeMismatch { .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    use crate::tools::hash_tools::sha1_bytes;
+    use crate::download::download_source::OFFICIAL_SOURCE;
+    use crate::tools::test_tools::{temp_dir, write_file, MockRequest, MockResponse, MockServer};
+
+    use super::*;
+
+    const CONTENT: &[u8] = b"hello minecraft world";
+
+    /// 支持`Range`请求的服务器
+    fn range_server() -> MockServer {
+        MockServer::start(|request| match request.header("Range").and_then(|range| range.strip_prefix("bytes=")) {
+            Some(range) => {
+                let start: usize = range.trim_end_matches('-').parse().unwrap();
+                MockResponse::new(206, &CONTENT[start..])
+                    .with_header("Content-Range", &format!("bytes {start}-{}/{}", CONTENT.len() - 1, CONTENT.len()))
+            }
+            None => MockResponse::new(200, CONTENT),
+        })
+    }
+
+    fn task(server: &MockServer, dir: &str) -> DownloadTask {
+        DownloadTask::new(&format!("{}/file.bin", server.url), &format!("{dir}/file.bin"))
+            .with_sha1(&sha1_bytes(CONTENT))
+            .with_size(CONTENT.len() as u64)
+    }
+
+    #[test]
+    fn resumes_partial_download() {
+        let server = range_server();
+        let dir = temp_dir("resume");
+        let task = task(&server, &dir);
+        write_file(&format!("{}.part", task.path), &CONTENT[..6]);
+
+        Downloader::new().with_retries(0).download(&task).unwrap();
+        assert_eq!(fs::read(&task.path).unwrap(), CONTENT);
+        assert!(!Path::new(&format!("{}.part", task.path)).exists());
+        assert_eq!(server.requests()[0].header("Range"), Some("bytes=6-"));
+    }
+
+    #[test]
+    fn restarts_when_range_is_ignored() {
+        let server = MockServer::start(|_| MockResponse::new(200, CONTENT));
+        let dir = temp_dir("ignore-range");
+        let task = task(&server, &dir);
+        write_file(&format!("{}.part", task.path), "garbage");
+
+        Downloader::new().with_retries(0).download(&task).unwrap();
+        assert_eq!(fs::read(&task.path).unwrap(), CONTENT);
+        assert_eq!(server.requests()[0].header("Range"), Some("bytes=7-"));
+    }
+
+    #[test]
+    fn rejects_size_mismatch() {
+        let server = range_server();
+        let dir = temp_dir("size-mismatch");
+        let task = task(&server, &dir).with_size(CONTENT.len() as u64 + 1);
+
+        let err = Downloader::new().with_retries(0).download(&task).unwrap_err();
+        assert!(matches!(err, CoreError::SizeMismatch { .. }), "{err:?}");
+        assert!(!Path::new(&task.path).exists());
+        assert!(!Path::new(&format!("{}.part", task.path)).exists());
+    }
+
+    #[test]
+    fn rejects_hash_mismatch() {
+        let server = range_server();
+        let dir = temp_dir("hash-mismatch");
+        let task = task(&server, &dir).with_sha1(&sha1_bytes(b"something else"));
+
+        let err = Downloader::new().with_retries(0).download(&task).unwrap_err();
+        assert!(matches!(err, CoreError::HashMismatch { .. }), "{err:?}");
+        assert!(!Path::new(&task.path).exists());
+        assert!(!Path::new(&format!("{}.part", task.path)).exists());
+    }
+
+    #[test]
+    fn retries_server_errors() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let server = MockServer::start(move |_| match counter.fetch_add(1, Ordering::SeqCst) {
+            0 | 1 => MockResponse::new(503, "unavailable"),
+            _ => MockResponse::new(200, CONTENT),
+        });
+        let dir = temp_dir("retry");
+        let task = task(&server, &dir);
+
+        Downloader::new().with_retries(3).download(&task).unwrap();
+        assert_eq!(fs::read(&task.path).unwrap(), CONTENT);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn gives_up_after_retries() {
+        let server = MockServer::start(|_| MockResponse::new(500, "error"));
+        let dir = temp_dir("give-up");
+        let task = task(&server, &dir);
+
+        let err = Downloader::new().with_retries(2).download(&task).unwrap_err();
+        assert!(matches!(err, CoreError::HttpStatus { status: 500, .. }), "{err:?}");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    /// 官方源和镜像源，`mirror_handler`生成镜像的响应
+    fn mirror_servers(mirror_handler: impl Fn(&MockRequest) -> MockResponse + Send + 'static) -> (MockServer, MockServer, DownloadSource) {
+        let official = MockServer::start(|_| MockResponse::new(200, CONTENT));
+        let mirror = MockServer::start(mirror_handler);
+        let source = DownloadSource::new("mirror").with_rewrite(&official.url, &format!("{}/mirror", mirror.url));
+        (official, mirror, source)
+    }
+
+    #[test]
+    fn prefers_mirror() {
+        let (official, mirror, source) = mirror_servers(|_| MockResponse::new(200, CONTENT));
+        let dir = temp_dir("mirror");
+        let task = task(&official, &dir);
+        let downloader = Downloader::new().with_retries(0).with_source(source);
+
+        assert_eq!(downloader.resolve_url(&task.url), format!("{}/mirror/file.bin", mirror.url));
+        downloader.download(&task).unwrap();
+        assert_eq!(fs::read(&task.path).unwrap(), CONTENT);
+        assert_eq!(mirror.requests()[0].path, "/mirror/file.bin");
+        assert!(official.requests().is_empty());
+        let stats = downloader.source_stats();
+        assert_eq!(stats["mirror"].successes, 1);
+        assert_eq!(stats["mirror"].bytes, CONTENT.len() as u64);
+        assert!(!stats.contains_key(OFFICIAL_SOURCE));
+    }
+
+    #[test]
+    fn falls_back_to_official_when_missing() {
+        let (official, mirror, source) = mirror_servers(|_| MockResponse::new(404, "not found"));
+        let dir = temp_dir("mirror-missing");
+        let task = task(&official, &dir);
+        // 404不重试，直接换下一个下载源
+        let downloader = Downloader::new().with_retries(3).with_source(source);
+
+        downloader.download(&task).unwrap();
+        assert_eq!(fs::read(&task.path).unwrap(), CONTENT);
+        assert_eq!(mirror.requests().len(), 1);
+        assert_eq!(official.requests().len(), 1);
+        let stats = downloader.source_stats();
+        assert_eq!((stats["mirror"].successes, stats["mirror"].failures), (0, 1));
+        assert_eq!((stats[OFFICIAL_SOURCE].successes, stats[OFFICIAL_SOURCE].failures), (1, 0));
+        assert_eq!(downloader.fastest_source().as_deref(), Some(OFFICIAL_SOURCE));
+    }
+
+    #[test]
+    fn falls_back_to_official_on_hash_mismatch() {
+        let (official, mirror, source) = mirror_servers(|_| MockResponse::new(200, "stale mirror content!"));
+        let dir = temp_dir("mirror-hash");
+        let task = task(&official, &dir);
+
+        Downloader::new().with_retries(3).with_source(source).download(&task).unwrap();
+        assert_eq!(fs::read(&task.path).unwrap(), CONTENT);
+        assert_eq!(mirror.requests().len(), 1);
+        assert_eq!(official.requests().len(), 1);
+    }
+
+    #[test]
+    fn falls_back_for_json_requests() {
+        let official = MockServer::start(|_| MockResponse::json(200, &serde_json::json!({ "source": "official" })));
+        let mirror = MockServer::start(|_| MockResponse::new(502, "bad gateway"));
+        let source = DownloadSource::new("mirror").with_rewrite(&official.url, &mirror.url);
+        let downloader = Downloader::new().with_retries(0).with_source(source);
+
+        let json: serde_json::Value = downloader.get_json(&format!("{}/v2/versions/loader", official.url)).unwrap();
+        assert_eq!(json["source"], "official");
+        assert_eq!(mirror.requests()[0].path, "/v2/versions/loader");
+        assert_eq!(downloader.source_stats()["mirror"].failures, 1);
+    }
+
+    #[test]
+    fn auto_selects_fastest_source() {
+        let official = MockServer::start(|_| MockResponse::new(200, CONTENT));
+        let slow = MockServer::start(|_| {
+            thread::sleep(std::time::Duration::from_millis(300));
+            MockResponse::new(200, CONTENT)
+        });
+        let fast = MockServer::start(|_| MockResponse::new(200, CONTENT));
+        let downloader = Downloader::new()
+            .with_retries(0)
+            .with_source(DownloadSource::new("slow").with_rewrite(&official.url, &slow.url))
+            .with_source(DownloadSource::new("fast").with_rewrite(&official.url, &fast.url))
+            .with_auto_select(true);
+        let dir = temp_dir("auto-select");
+
+        // 先按顺序使用还没有统计的下载源，之后使用速度最快的
+        for name in ["a.bin", "b.bin", "c.bin"] {
+            let task = DownloadTask::new(&format!("{}/{name}", official.url), &format!("{dir}/{name}")).with_sha1(&sha1_bytes(CONTENT));
+            downloader.download(&task).unwrap();
+        }
+        let paths = |server: &MockServer| server.requests().into_iter().map(|request| request.path).collect::<Vec<String>>();
+        assert_eq!(paths(&slow), ["/a.bin"]);
+        assert_eq!(paths(&fast), ["/b.bin", "/c.bin"]);
+        assert!(official.requests().is_empty());
+        assert_eq!(downloader.fastest_source().as_deref(), Some("fast"));
+        assert!(downloader.source_stats()["fast"].speed().unwrap() > downloader.source_stats()["slow"].speed().unwrap());
+        assert_eq!(downloader.resolve_url(&format!("{}/d.bin", official.url)), format!("{}/d.bin", fast.url));
+    }
+}
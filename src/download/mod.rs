@@ -0,0 +1,3 @@
+pub mod download_task;
+pub mod downloader;
+pub mod download_source;
\ No newline at end of file
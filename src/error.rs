@@ -0,0 +1,142 @@
+use thiserror::Error;
+
+/// 启动核心的错误类型
+///
+/// 所有公开的方法都会返回`Result<T, CoreError>`，方便界面根据错误种类给出提示
+#[derive(Debug, Error)]
+pub enum CoreError {
+    /// 找不到版本json文件
+    #[error("Version json not found: {path}")]
+    MissingVersionJson { path: String },
+
+    /// json解析失败，包含文件路径以及出错的行列
+    #[error("Failed to parse {path} at line {line}, column {column}: {message}")]
+    ParseJson { path: String, line: usize, column: usize, message: String },
+
+    /// `inheritsFrom`指向的父版本不存在
+    #[error("Parent version {parent} of {version} not found")]
+    MissingParentVersion { version: String, parent: String },
+
+    /// `inheritsFrom`出现循环继承
+    #[error("Inheritance cycle detected: {}", chain.join(" -> "))]
+    InheritanceCycle { chain: Vec<String> },
+
+    /// 版本列表中没有这个版本
+    #[error("Version {0} not found in the version manifest")]
+    UnknownVersion(String),
+
+    /// 加载器不支持这个游戏版本，或者没有这个加载器版本
+    #[error("{loader} {loader_version} is not available for Minecraft {game_version}")]
+    UnsupportedLoader { loader: String, loader_version: String, game_version: String },
+
+    /// 安装器jar不是可以识别的安装器
+    #[error("Invalid installer {path}: {message}")]
+    InvalidInstaller { path: String, message: String },
+
+    /// 安装器的处理器运行失败
+    #[error("Processor {processor} failed: {message}")]
+    ProcessorFailed { processor: String, message: String },
+
+    /// 安装的组件和游戏版本不匹配
+    #[error("{name} requires Minecraft {required}, but the version is {actual}")]
+    IncompatibleGameVersion { name: String, required: String, actual: String },
+
+    /// 版本json缺少必须的字段
+    #[error("Version {version} is missing field {field}")]
+    MissingField { version: String, field: String },
+
+    /// 库的名字不是合法的Maven坐标
+    #[error("Invalid maven coordinate: {0}")]
+    InvalidMavenCoordinate(String),
+
+    /// 库文件不存在
+    #[error("Library {name} not found at {path}")]
+    MissingLibrary { name: String, path: String },
+
+    /// 启动需要的文件不存在，包含所有缺失的文件
+    #[error("{} required files are missing: {}", paths.len(), paths.join(", "))]
+    MissingFiles { paths: Vec<String> },
+
+    /// zip（jar）文件损坏或者无法读取
+    #[error("Corrupt zip file {path}: {source}")]
+    CorruptZip { path: String, source: zip::result::ZipError },
+
+    /// 找不到可用的Java
+    #[error("No Java runtime found")]
+    NoJavaFound,
+
+    /// 找到了Java，但是没有满足版本要求的
+    #[error("No Java {required} runtime found")]
+    NoSuitableJava { required: u32 },
+
+    /// Mojang没有为当前平台提供这个Java运行时
+    #[error("Java runtime {component} is not available for {platform}")]
+    RuntimeUnavailable { component: String, platform: String },
+
+    /// 暂不支持的账户类型
+    #[error("Unsupported account type: {0}")]
+    UnsupportedAuth(String),
+
+    /// 登录失败
+    #[error("Authentication failed: {0}")]
+    Auth(String),
+
+    /// 账户没有购买Minecraft
+    #[error("This account does not own Minecraft")]
+    GameNotOwned,
+
+    /// 账户还没有创建Minecraft角色
+    #[error("This account has no Minecraft profile")]
+    NoMinecraftProfile,
+
+    /// 账户文件无法解密或者内容不正确
+    #[error("Account store error: {0}")]
+    AccountStore(String),
+
+    /// 下载失败（网络错误等）
+    #[error("Failed to download {url}: {message}")]
+    Download { url: String, message: String },
+
+    /// 服务器返回了错误的状态码
+    #[error("{url} returned HTTP {status}")]
+    HttpStatus { url: String, status: u16 },
+
+    /// 下载的文件大小不正确
+    #[error("Size mismatch for {path}: expected {expected}, got {actual}")]
+    SizeMismatch { path: String, expected: u64, actual: u64 },
+
+    /// 下载的文件SHA-1不正确
+    #[error("SHA-1 mismatch for {path}: expected {expected}, got {actual}")]
+    HashMismatch { path: String, expected: String, actual: String },
+
+    /// 解压失败
+    #[error("Failed to decompress {path}: {message}")]
+    Decompress { path: String, message: String },
+
+    /// 索引或清单中的文件名会写到目标目录之外
+    #[error("Unsafe path {name} escapes {root}")]
+    UnsafePath { root: String, name: String },
+
+    /// 文件读写错误
+    #[error("I/O error on {path}: {source}")]
+    Io { path: String, source: std::io::Error },
+}
+
+pub type Result<T> = std::result::Result<T, CoreError>;
+
+impl CoreError {
+    /// 包装一个带有路径的I/O错误
+    pub fn io(path: impl Into<String>, source: std::io::Error) -> Self {
+        CoreError::Io { path: path.into(), source }
+    }
+
+    /// 包装一个带有路径的json解析错误
+    pub fn parse_json(path: impl Into<String>, source: serde_json::Error) -> Self {
+        CoreError::ParseJson {
+            path: path.into(),
+            line: source.line(),
+            column: source.column(),
+            message: source.to_string(),
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::fs;
+use std::path::Path;
+
+use crate::download::download_task::DownloadTask;
+use crate::download::downloader::Downloader;
+use crate::error::{CoreError, Result};
+use crate::game_version::GameVersion;
+use crate::json::json_assets::JsonAssets;
+use crate::json::json_version::JsonAssetsIndex;
+use crate::tools::file_tools::is_safe_relative_path;
+
+/// 资源文件的下载地址
+pub const RESOURCES_URL: &str = "https://resources.download.minecraft.net";
+
+/// 游戏的资源文件（`assets`目录）
+pub struct GameAssets {
+    /// 版本json中的`assetIndex`
+    pub index: JsonAssetsIndex,
+    /// 资源索引的内容
+    pub assets: JsonAssets,
+    /// `assets`目录
+    pub assets_root: String,
+    /// 游戏目录，`map_to_resources`的资源会复制到这里的`resources`
+    pub game_directory: String,
+}
+
+impl GameAssets {
+    /// 读取版本的资源索引，索引不存在或者已经损坏时先下载
+    pub fn load(version: &GameVersion, downloader: &Downloader) -> Result<Self> {
+        let index = Self::asset_index(version)?;
+        let task = Self::index_task(&version.assets_root(), &index);
+        downloader.download(&task)?;
+        Self::read(version, index)
+    }
+
+    /// 只读取本地已有的资源索引，不会下载
+    pub fn load_local(version: &GameVersion) -> Result<Self> {
+        let index = Self::asset_index(version)?;
+        Self::read(version, index)
+    }
+
+    fn asset_index(version: &GameVersion) -> Result<JsonAssetsIndex> {
+        version.version_json.asset_index.clone().ok_or_else(|| CoreError::MissingField {
+            version: version.id.clone(),
+            field: "assetIndex".to_owned(),
+        })
+    }
+
+    fn index_task(assets_root: &str, index: &JsonAssetsIndex) -> DownloadTask {
+        let mut task = DownloadTask::new(&index.url, &format!("{assets_root}/indexes/{}.json", index.id)).with_sha1(&index.sha1);
+        task.size = u64::try_from(index.size).ok().filter(|size| *size > 0);
+        task
+    }
+
+    fn read(version: &GameVersion, index: JsonAssetsIndex) -> Result<Self> {
+        let assets_root = version.assets_root();
+        let index_path = format!("{assets_root}/indexes/{}.json", index.id);
+        let contents = fs::read_to_string(&index_path).map_err(|err| CoreError::io(&index_path, err))?;
+        let assets: JsonAssets = serde_json::from_str(&contents).map_err(|err| CoreError::parse_json(&index_path, err))?;
+        Ok(Self { index, assets, assets_root, game_directory: version.game_directory() })
+    }
+
+    /// 资源文件在`assets/objects`中的路径
+    pub fn object_path(&self, hash: &str) -> String {
+        format!("{}/objects/{}/{hash}", self.assets_root, &hash[..2.min(hash.len())])
+    }
+
+    /// 获取所有资源文件的下载任务
+    pub fn get_download_tasks(&self) -> Vec<DownloadTask> {
+        self.assets.objects.values()
+            .map(|object| {
+                let prefix = &object.hash[..2.min(object.hash.len())];
+                DownloadTask::new(&format!("{RESOURCES_URL}/{prefix}/{}", object.hash), &self.object_path(&object.hash))
+                    .with_sha1(&object.hash)
+                    .with_size(object.size)
+            })
+            .collect()
+    }
+
+    /// 获取不存在或者SHA-1不正确的资源文件
+    pub fn get_missing_objects(&self) -> Vec<DownloadTask> {
+        self.get_download_tasks().into_iter().filter(|task| !task.is_complete()).collect()
+    }
+
+    /// 下载所有缺失的资源文件
+    pub fn download_missing_objects(&self, downloader: &Downloader) -> Result<()> {
+        downloader.download_all(&self.get_missing_objects())
+    }
+
+    /// 是否是需要按名字复制资源的旧版本
+    pub fn is_legacy(&self) -> bool {
+        self.assets.virtual_ || self.assets.map_to_resources
+    }
+
+    /// 启动参数`${game_assets}`对应的目录
+    ///
+    /// `map_to_resources`为游戏目录下的`resources`，`virtual`为`assets/virtual/<id>`，其他版本为`assets`
+    pub fn game_assets_path(&self) -> String {
+        if self.assets.map_to_resources {
+            format!("{}/resources", self.game_directory)
+        } else if self.assets.virtual_ {
+            format!("{}/virtual/{}", self.assets_root, self.index.id)
+        } else {
+            self.assets_root.clone()
+        }
+    }
+
+    /// 旧版本需要把`assets/objects`中的资源按名字复制到`game_assets_path`
+    ///
+    /// 目标文件已经存在并且大小一致时跳过，资源名会写到目标目录之外时返回错误
+    pub fn copy_legacy_assets(&self) -> Result<()> {
+        if !self.is_legacy() {
+            return Ok(());
+        }
+        let target_root = self.game_assets_path();
+        for (name, object) in &self.assets.objects {
+            if !is_safe_relative_path(name) {
+                return Err(CoreError::UnsafePath { root: target_root, name: name.clone() });
+            }
+            let target = Path::new(&target_root).join(name);
+            if fs::metadata(&target).is_ok_and(|metadata| metadata.len() == object.size) {
+                continue;
+            }
+            let source = self.object_path(&object.hash);
+            if !Path::new(&source).exists() {
+                continue;
+            }
+            if let Some(parent_dir) = target.parent() {
+                fs::create_dir_all(parent_dir).map_err(|err| CoreError::io(parent_dir.to_string_lossy(), err))?;
+            }
+            fs::copy(&source, &target).map_err(|err| CoreError::io(target.to_string_lossy(), err))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::tools::hash_tools::sha1_bytes;
+    use crate::tools::test_tools::{temp_dir, write_file};
+
+    use super::*;
+
+    fn legacy_assets(root: &str, objects: serde_json::Value) -> GameAssets {
+        GameAssets {
+            index: serde_json::from_value(json!({ "id": "legacy", "sha1": "", "url": "" })).unwrap(),
+            assets: serde_json::from_value(json!({ "virtual": true, "objects": objects })).unwrap(),
+            assets_root: format!("{root}/assets"),
+            game_directory: format!("{root}/game"),
+        }
+    }
+
+    fn write_object(assets: &GameAssets, contents: &[u8]) -> String {
+        let hash = sha1_bytes(contents);
+        write_file(&assets.object_path(&hash), contents);
+        hash
+    }
+
+    #[test]
+    fn copies_objects_by_name() {
+        let root = temp_dir("legacy-assets");
+        let hash = sha1_bytes(b"sound");
+        let assets = legacy_assets(&root, json!({ "sounds/cave1.ogg": { "hash": hash, "size": 5 } }));
+        write_object(&assets, b"sound");
+
+        assets.copy_legacy_assets().unwrap();
+        assert_eq!(fs::read(format!("{root}/assets/virtual/legacy/sounds/cave1.ogg")).unwrap(), b"sound");
+    }
+
+    #[test]
+    fn rejects_path_traversal() {
+        let root = temp_dir("legacy-assets-traversal");
+        let hash = sha1_bytes(b"evil");
+        for name in ["../../../evil.txt", "sounds/../../../../evil.txt", "/tmp/evil.txt"] {
+            let assets = legacy_assets(&root, json!({ name: { "hash": hash, "size": 4 } }));
+            write_object(&assets, b"evil");
+
+            let err = assets.copy_legacy_assets().unwrap_err();
+            assert!(matches!(err, CoreError::UnsafePath { .. }), "{err:?}");
+        }
+        assert!(!Path::new(&format!("{root}/evil.txt")).exists());
+        assert!(!Path::new(&format!("{root}/assets/evil.txt")).exists());
+    }
+}
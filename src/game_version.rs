@@ -1,25 +1,36 @@
-use std::collections::HashMap;
-use std::fmt::format;
-use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::process::Command;
-use std::ptr::null;
-use std::vec;
-use std::{fs::File, env::consts::OS};
+use std::fs::File;
 use std::io::Read;
-use serde_json::Value;
-use zip::read::ZipArchive;
+use serde_json::{json, Value};
 
-use crate::json::json_version::{JsonAdvanceArgument, JsonLibrary};
-use crate::tools::file_tools::lib_name_to_path;
+use crate::download::download_task::DownloadTask;
+use crate::download::downloader::Downloader;
+use crate::error::{CoreError, Result};
+use crate::game_assets::GameAssets;
+use crate::json::json_version::{JsonAdvanceArgument, JsonLibrary, JsonLogging};
+use crate::launch::classpath::Classpath;
+use crate::launch::game_process::GameProcess;
+use crate::launch::launch_options::LaunchOptions;
+use crate::launch::natives::{self, NativesManager};
 use crate::tools::string_tools::replace_variables;
-use crate::tools::system_tools;
+use crate::platform::java_discovery::{self, JavaRuntime};
+use crate::platform::java_installer::JavaRuntimeInstaller;
+use crate::platform::rule_evaluator::{self, RuleEnvironment};
 use crate::users::user_types::UserResult;
-use crate::{json::{json_version::JsonVersion, self}, launcher_core::LauncherCore};
+use crate::{json::json_version::JsonVersion, launcher_core::LauncherCore};
+
+/// Mojang的库文件仓库，库没有提供`url`时使用
+pub const MINECRAFT_LIBRARIES_URL: &str = "https://libraries.minecraft.net/";
 
 pub struct GameVersion {
     pub id: String,
     pub path: String,
+    /// 已经沿着`inheritsFrom`合并完成的版本json
     pub version_json: JsonVersion,
+    /// 版本的继承链，从当前版本开始，依次为它的父版本
+    pub inheritance_chain: Vec<String>,
     launcher_core: LauncherCore
 }
 
@@ -37,49 +48,128 @@ impl GameVersion {
     /// 
     /// `version`: String，版本名字
     /// 
-    /// 加载后将返回一个GameVersion类型，然后可以获取到版本的json文件信息
-    pub fn load(core: LauncherCore, version: String) -> Self {
+    /// 加载后将返回一个GameVersion类型，然后可以获取到版本的json文件信息。
+    /// 如果版本json含有`inheritsFrom`，会递归加载父版本并合并
+    pub fn load(core: LauncherCore, version: String) -> Result<Self> {
         let path = format!("{}/versions/{version}", core.base_path);
-        let json_file = format!("{}/versions/{version}/{version}.json", core.base_path.clone());
-        let mut file = File::open(json_file.clone()).expect("Failed to open file");
+        let mut inheritance_chain: Vec<String> = vec![];
+        let minecraft_json = Self::resolve_json(&core.base_path, &version, &mut inheritance_chain)?;
+        Ok(Self { version_json: minecraft_json, launcher_core: core, path, id: version, inheritance_chain })
+    }
+
+    /// 读取`versions/<version>/<version>.json`，不处理继承
+    pub(crate) fn read_json(base_path: &str, version: &str) -> Result<JsonVersion> {
+        let json_file = format!("{base_path}/versions/{version}/{version}.json");
+        let mut file = File::open(&json_file).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => CoreError::MissingVersionJson { path: json_file.clone() },
+            _ => CoreError::io(&json_file, err),
+        })?;
         let mut contents = String::new();
-        file.read_to_string(&mut contents).expect("Failed to read file");
-        
-        let minecraft_json: JsonVersion = serde_json::from_str(&contents).expect("Failed to parse JSON");
-        Self { version_json: minecraft_json, launcher_core: core, path, id: version }
+        file.read_to_string(&mut contents).map_err(|err| CoreError::io(&json_file, err))?;
+        serde_json::from_str(&contents).map_err(|err| CoreError::parse_json(&json_file, err))
     }
 
+    /// 沿着`inheritsFrom`递归加载父版本，并把子版本合并进去
+    /// 
+    /// `chain`会记录已经加载过的版本，用于检测循环继承
+    pub(crate) fn resolve_json(base_path: &str, version: &str, chain: &mut Vec<String>) -> Result<JsonVersion> {
+        if chain.iter().any(|id| id == version) {
+            let mut cycle = chain.clone();
+            cycle.push(version.to_owned());
+            return Err(CoreError::InheritanceCycle { chain: cycle });
+        }
+        chain.push(version.to_owned());
+        let json = Self::read_json(base_path, version)?;
+        Self::merge_parent(base_path, version, json, chain)
+    }
+
+    /// 加载还没有写入`versions`的版本json，父版本从`versions`中读取并合并
+    pub(crate) fn from_json(core: LauncherCore, version: String, json: JsonVersion) -> Result<Self> {
+        let path = format!("{}/versions/{version}", core.base_path);
+        let mut inheritance_chain = vec![version.clone()];
+        let version_json = Self::merge_parent(&core.base_path, &version, json, &mut inheritance_chain)?;
+        Ok(Self { version_json, launcher_core: core, path, id: version, inheritance_chain })
+    }
+
+    /// 加载`json`的父版本并把`json`合并进去，没有父版本时直接返回`json`
+    fn merge_parent(base_path: &str, version: &str, json: JsonVersion, chain: &mut Vec<String>) -> Result<JsonVersion> {
+        match &json.override_ {
+            Some(parent_id) => {
+                let mut parent = Self::resolve_json(base_path, parent_id, chain).map_err(|err| match err {
+                    CoreError::MissingVersionJson { .. } if chain.last() == Some(parent_id) => {
+                        CoreError::MissingParentVersion { version: version.to_owned(), parent: parent_id.clone() }
+                    }
+                    err => err,
+                })?;
+                json.set_to_json(&mut parent);
+                Ok(parent)
+            }
+            None => Ok(json)
+        }
+    }
+
+    /// 获取游戏本体jar的路径
+    /// 
+    /// 继承的版本没有自己的jar时，会使用父版本的jar
+    pub fn jar_path(&self) -> String {
+        let jar = self.version_json.jar.clone().unwrap_or_else(|| self.id.clone());
+        format!("{}/versions/{jar}/{jar}.jar", self.launcher_core.base_path)
+    }
 
-    pub fn get_libraries_and_natives(&self) -> Libraries {
+    /// 资源文件目录，即`<assets_path>/assets`
+    pub fn assets_root(&self) -> String {
+        format!("{}/assets", self.launcher_core.assets_path)
+    }
+
+    /// 游戏目录
+    pub fn game_directory(&self) -> String {
+        self.launcher_core.base_path.clone()
+    }
+
+    /// 查找满足版本json中`javaVersion`要求的Java
+    ///
+    /// 优先使用启动器安装的对应组件的运行时，没有时搜索系统中的Java和启动器的`runtime`目录
+    pub fn find_java(&self) -> Result<JavaRuntime> {
+        let runtime_dir = self.runtime_dir();
+        if let Some(runtime) = self.java_component().and_then(|component| JavaRuntimeInstaller::new(&runtime_dir).installed_runtime(component)) {
+            return Ok(runtime);
+        }
+        let runtimes = java_discovery::discover_java(&[&runtime_dir]);
+        let required = self.version_json.java_version.as_ref().map(|java| java.major_version);
+        java_discovery::select_java(&runtimes, required).cloned()
+    }
+
+    /// 安装版本json中`javaVersion`要求的Mojang运行时，没有要求时安装`jre-legacy`
+    pub fn install_java(&self, downloader: &Downloader) -> Result<JavaRuntime> {
+        let component = self.java_component().unwrap_or("jre-legacy");
+        JavaRuntimeInstaller::new(&self.runtime_dir()).install(component, downloader)
+    }
+
+    fn runtime_dir(&self) -> String {
+        format!("{}/runtime", self.launcher_core.base_path)
+    }
+
+    fn java_component(&self) -> Option<&str> {
+        self.version_json.java_version.as_ref()
+            .map(|java| java.component.as_str())
+            .filter(|component| !component.is_empty())
+    }
+
+    /// 获取当前环境需要的库文件和natives，库文件的`rules`不允许的会被过滤掉
+    pub fn get_libraries_and_natives(&self, env: &RuleEnvironment) -> Libraries {
         let mut libs: Vec<JsonLibrary> = vec![];
         let mut natives: Vec<JsonLibrary> = vec![];
-        let libraries_json = self.version_json.clone().libraries.unwrap();
+        let libraries_json = self.version_json.libraries.clone().unwrap_or_default();
         for lib in libraries_json {
-            if lib.name.contains("natives") || lib.natives.is_some() {
-                if lib.rules.is_some() {
-                    let mut lib_support_system = false;
-                    for rule in lib.clone().rules.unwrap() {
-                        if rule.os.is_none() && rule.action == "allow" {
-                            // 可能支持所有系统，但是有例外系统
-                            lib_support_system = true;
-                            continue;
-                        }
-                        if rule.os.is_some() {
-                            if rule.os.clone().unwrap().name.unwrap() == OS {
-                                if rule.action != "allow" {
-                                    if lib_support_system == true && rule.os.clone().unwrap().name.unwrap() == OS {
-                                        lib_support_system = false;
-                                    }
-                                }                                
-                            }
-                        }
-                    }
-                    if lib_support_system == true {
-                        natives.push(lib.clone());
-                    }
-                } else {
-                    natives.push(lib.clone());
-                }
+            if !rule_evaluator::is_allowed(lib.rules.as_deref(), env) {
+                continue;
+            }
+            if lib.natives.is_some() {
+                natives.push(lib);
+            } else if natives::is_classified_native(&lib) {
+                // 1.19+的natives库同时需要放到classpath中
+                natives.push(lib.clone());
+                libs.push(lib);
             } else {
                 libs.push(lib);
             }
@@ -87,220 +177,479 @@ impl GameVersion {
         Libraries { libs, natives }
     }
 
+    /// 获取启动使用的classpath，游戏本体在最后
+    pub fn get_classpath(&self, env: &RuleEnvironment) -> Classpath {
+        let libraries_path = format!("{}/libraries", self.launcher_core.assets_path);
+        let mut classpath = Classpath::build(&self.get_libraries_and_natives(env).libs, &libraries_path);
+        classpath.push(&self.id, &self.jar_path());
+        classpath
+    }
 
-    /// 获取未替换变量的启动参数，兼容以前版本的json参数`minecraftArguments`
-    pub fn get_arguments(&self) -> Vec<String> {
-        let version_json = self.version_json.clone();
-        let argument_old = version_json.arguments_old.unwrap_or_default();
-        if !argument_old.is_empty() {
-            vec![argument_old]
-        } else {
-            let mut argument_str: Vec<String> = vec![];
-            for jvm_argument in version_json.arguments.clone().unwrap().jvm.unwrap() {
-                match jvm_argument {
-                    Value::String(s) => {
-                        argument_str.push(s)
-                    }
-                    Value::Object(object) => {
-                        let advance_argument: JsonAdvanceArgument = serde_json::from_value(serde_json::Value::Object(object)).unwrap();
-                        for rule in advance_argument.rules.unwrap() {
-                            if rule.action == "allow" && rule.os.is_some() {
-                                if rule.os.clone().unwrap().name.is_some() {
-                                    if OS.to_lowercase() != rule.os.clone().unwrap().name.unwrap_or_default().to_lowercase() {
-                                        continue;
-                                    } else if rule.os.clone().unwrap().version.is_some() /* 暂时还没想好怎么判断系统版本号 */ {
-                                        continue;
-                                    }
-                                } 
-                                if rule.os.clone().unwrap().arch.is_some() && rule.os.clone().unwrap().arch.unwrap() != system_tools::arch() {
-                                    continue;
-                                }
-                                match &advance_argument.value {
-                                    Value::String(s) => {
-                                        argument_str.push(s.to_string())
-                                    }
-                                    Value::Array(arr) => {
-                                        for arg in arr {
-                                            argument_str.push(arg.to_string())
-                                        }
-                                    }
-                                    _ => { }
-                                }
-                            }
-                        }
-                    }
-                    _ => { }
-                    
+    /// 获取启动需要的所有文件的下载任务，包括游戏本体、库文件、natives和日志配置
+    pub fn get_download_tasks(&self, env: &RuleEnvironment) -> Vec<DownloadTask> {
+        let assets_path = &self.launcher_core.assets_path;
+        let mut tasks: Vec<DownloadTask> = vec![];
+        if let Some(client) = self.version_json.downloads.as_ref().and_then(|downloads| downloads.get("client")) {
+            tasks.extend(DownloadTask::from_json(client, &self.jar_path()));
+        }
+
+        let libraries_path = format!("{assets_path}/libraries");
+        let libs_and_natives = self.get_libraries_and_natives(env);
+        for lib in libs_and_natives.libs.iter().chain(&libs_and_natives.natives) {
+            tasks.extend(DownloadTask::from_library(lib, &libraries_path, MINECRAFT_LIBRARIES_URL));
+            let classifier = lib.downloads.as_ref()
+                .zip(natives::native_classifier(lib, env))
+                .and_then(|(downloads, classifier)| downloads.classifiers.as_ref()?.get(&classifier));
+            if let Some(download) = classifier {
+                if let Some(path) = &download.path {
+                    tasks.extend(DownloadTask::from_json(download, &format!("{libraries_path}/{path}")));
                 }
             }
+        }
 
-            // argument_str.push(format!("{}/{}.jar", &self.path, &self.id));
-            argument_str.push(self.version_json.main_class.to_string());
+        if let Some((logging, path)) = self.log_config() {
+            let file = &logging.file;
+            let mut task = DownloadTask::new(&file.url, &path).with_sha1(&file.sha1);
+            task.size = u64::try_from(file.size).ok();
+            tasks.push(task);
+        }
 
-            for game_argument in version_json.arguments.clone().unwrap().game.unwrap() {
-                match game_argument {
-                    Value::String(s) => {
-                        argument_str.push(s)
-                    }
-                    Value::Object(object) => {
-                        let advance_argument: JsonAdvanceArgument = serde_json::from_value(serde_json::Value::Object(object)).unwrap();
-                        for rule in advance_argument.rules.unwrap() {
-                            if rule.action == "allow" {
-                                match &advance_argument.value {
-                                    Value::String(s) => {
-                                        argument_str.push(s.to_string())
-                                    }
-                                    Value::Array(arr) => {
-                                        for arg in arr {
-                                            argument_str.push(arg.to_string())
-                                        }
-                                    }
-                                    _ => { }
-                                }
-                            }
-                        }
+        let mut paths: HashSet<String> = HashSet::new();
+        tasks.retain(|task| paths.insert(task.path.clone()));
+        tasks
+    }
+
+    /// 版本json中`logging.client`的日志配置，以及配置文件的路径
+    pub fn log_config(&self) -> Option<(&JsonLogging, String)> {
+        let logging = self.version_json.logging.as_ref()?.get("client")?;
+        let path = format!("{}/log_configs/{}", self.assets_root(), logging.file.id);
+        Some((logging, path))
+    }
+
+    /// 获取本地不存在或者已经损坏的文件
+    pub fn get_missing_files(&self, env: &RuleEnvironment) -> Vec<DownloadTask> {
+        self.get_download_tasks(env).into_iter().filter(|task| !task.is_complete()).collect()
+    }
+
+    /// 下载所有缺失或者损坏的文件
+    pub fn download_missing_files(&self, downloader: &Downloader, env: &RuleEnvironment) -> Result<()> {
+        downloader.download_all(&self.get_missing_files(env))
+    }
+
+    /// 获取未替换变量的启动参数，兼容以前版本的json参数`minecraftArguments`
+    /// 
+    /// 参数中的`rules`会按照`env`判断，不满足的参数不会加入
+    pub fn get_arguments(&self, env: &RuleEnvironment) -> Result<Vec<String>> {
+        let version_json = &self.version_json;
+        let mut argument_str: Vec<String> = vec![];
+        let argument_old = version_json.arguments_old.clone().unwrap_or_default();
+        if !argument_old.is_empty() {
+            // 旧版本的json没有jvm参数，使用官方启动器的默认参数
+            self.evaluate_arguments(&default_jvm_arguments(), env, &mut argument_str)?;
+            argument_str.push(version_json.main_class.to_string());
+            argument_str.extend(argument_old.split_whitespace().map(str::to_owned));
+            return Ok(argument_str);
+        }
+        let arguments = version_json.arguments.as_ref().ok_or_else(|| self.missing_field("arguments"))?;
+        self.evaluate_arguments(arguments.jvm.as_deref().unwrap_or_default(), env, &mut argument_str)?;
+        argument_str.push(version_json.main_class.to_string());
+        self.evaluate_arguments(arguments.game.as_deref().unwrap_or_default(), env, &mut argument_str)?;
+        Ok(argument_str)
+    }
+
+    /// 依次处理参数列表，字符串直接加入，带规则的参数满足规则时才加入
+    fn evaluate_arguments(&self, arguments: &[Value], env: &RuleEnvironment, argument_str: &mut Vec<String>) -> Result<()> {
+        for argument in arguments {
+            match argument {
+                Value::String(s) => {
+                    argument_str.push(s.to_string())
+                }
+                Value::Object(object) => {
+                    let advance_argument = self.parse_advance_argument(object.clone())?;
+                    if rule_evaluator::is_allowed(advance_argument.rules.as_deref(), env) {
+                        push_argument_value(argument_str, &advance_argument.value);
                     }
-                    _ => { }
-                    
                 }
+                _ => { }
             }
-            argument_str
         }
+        Ok(())
     }
 
-    pub fn launch(&self, user: UserResult) {
-        let mut cp_str: String = String::default();
-        let system_os = OS.clone();
-        let assets_path = self.launcher_core.assets_path.clone();
-        let natives_path = format!("{}/natives-{}", self.path.clone(), system_os);
-        println!("{}", natives_path);
-        let libs_and_natives: Libraries = self.get_libraries_and_natives();
-        for lib in libs_and_natives.libs {
-            if lib.clone().downloads.is_some() && lib.clone().downloads.unwrap().artifact.path.is_some() {
-                cp_str += &format!("{assets_path}/libraries/{};", lib.clone().downloads.unwrap().artifact.path.unwrap());
-            }
-        }
+    fn parse_advance_argument(&self, object: serde_json::Map<String, Value>) -> Result<JsonAdvanceArgument> {
+        serde_json::from_value(Value::Object(object)).map_err(|err| CoreError::parse_json(format!("{}/{}.json", self.path, self.id), err))
+    }
 
-        cp_str += &format!("{}/{}.jar", self.path, self.id);
+    fn missing_field(&self, field: &str) -> CoreError {
+        CoreError::MissingField { version: self.id.clone(), field: field.to_owned() }
+    }
 
-        for native in libs_and_natives.natives {
-            let mut file_path = String::default();
-            
-            if native.natives.is_none() {
-                file_path = format!("{}/libraries/{}", assets_path, lib_name_to_path(native.name));
-            } else {
-                let natives_system = native.natives.unwrap();
-                let mut native_download_info = native.downloads.clone().unwrap().classifiers.unwrap();
-                let system_natives_class = &native_download_info.get(&natives_system[system_os]).unwrap().path;
-                file_path = format!("{}/libraries/{}", assets_path, system_natives_class.clone().unwrap())
+    /// natives解压到的目录，不同系统和架构使用不同的目录
+    pub fn natives_directory(&self, env: &RuleEnvironment) -> String {
+        format!("{}/natives-{}-{}", self.path, env.os_name, env.os_arch)
+    }
+
+    /// 解压natives并清理旧的natives目录，返回解压到的目录
+    pub fn extract_natives(&self, env: &RuleEnvironment) -> Result<String> {
+        let libraries_path = format!("{}/libraries", self.launcher_core.assets_path);
+        let natives = natives::collect_natives(&self.get_libraries_and_natives(env).natives, &libraries_path, env)?;
+        let manager = NativesManager::new(&self.natives_directory(env));
+        manager.extract(&natives)?;
+        manager.cleanup_old()?;
+        Ok(manager.directory)
+    }
+
+    /// 是否支持快速游戏（1.20+的`--quickPlayMultiplayer`等参数）
+    pub fn supports_quick_play(&self) -> bool {
+        self.version_json.arguments.as_ref()
+            .and_then(|arguments| arguments.game.as_ref())
+            .is_some_and(|game| game.iter().any(|argument| argument.to_string().contains("${quickPlayMultiplayer}")))
+    }
+
+    /// 启动游戏
+    ///
+    /// # 参数
+    /// `user`: 登录得到的账户
+    ///
+    /// `options`: 启动选项，例如内存、窗口大小、额外参数
+    ///
+    /// 启动后立即返回游戏进程，不会等待游戏退出
+    pub fn launch(&self, user: UserResult, options: &LaunchOptions) -> Result<GameProcess> {
+        let env = options.apply_features(RuleEnvironment::current());
+        let classpath = self.get_classpath(&env);
+        classpath.verify()?;
+        let natives_path = self.extract_natives(&env)?;
+        let game_directory = options.game_directory.clone().unwrap_or_else(|| self.game_directory());
+        std::fs::create_dir_all(&game_directory).map_err(|err| CoreError::io(&game_directory, err))?;
+
+        // 外置登录需要的authlib-injector参数，放在所有jvm参数的最前面
+        let mut agent_arguments: Vec<String> = vec![];
+        let (username, uuid, access_token, xuid, user_type) = match &user {
+            UserResult::Developer { username, uuid } => (username.to_string(), uuid.to_string(), String::new(), String::new(), "Legacy"),
+            UserResult::Microsoft { username, uuid, access_token, xuid, .. } => {
+                (username.to_string(), uuid.to_string(), access_token.to_string(), xuid.to_string(), "msa")
             }
-            
-            println!("{}", file_path);
-            let file = File::open(file_path).expect("Failed open file");
-            let mut archive = ZipArchive::new(file).expect("Failed open Zip file");
-            for i in 0..archive.len() {
-                let mut file = archive.by_index(i).expect("Failed to get file from ZIP archive");
-                let file_path = PathBuf::from(file.name());
-                let output_path = Path::new(&natives_path).join(&file_path);
-        
-                if (&*file.name()).ends_with('/') {
-                    std::fs::create_dir_all(&output_path).expect("Failed to create directory");
-                } else {
-                    if let Some(parent_dir) = output_path.parent() {
-                        std::fs::create_dir_all(&parent_dir).expect("Failed to create parent directory");
-                    }
-        
-                    let mut output_file = File::create(&output_path).expect("Failed to create file");
-                    std::io::copy(&mut file, &mut output_file).expect("Failed to extract file");
+            UserResult::CustomAuth { username, uuid, access_token, server_url, prefetched_metadata, .. } => {
+                let injector_path = &self.launcher_core.authlib_injector_path;
+                if !Path::new(injector_path).exists() {
+                    return Err(CoreError::MissingLibrary { name: "authlib-injector".to_owned(), path: injector_path.clone() });
                 }
+                agent_arguments.push(format!("-javaagent:{injector_path}={server_url}"));
+                if !prefetched_metadata.is_empty() {
+                    agent_arguments.push(format!("-Dauthlibinjector.yggdrasil.prefetched={prefetched_metadata}"));
+                }
+                (username.to_string(), uuid.to_string(), access_token.to_string(), String::new(), "Mojang")
             }
-        }
+        };
+        let asset_index = self.version_json.asset_index.as_ref().ok_or_else(|| self.missing_field("assetIndex"))?;
+        let game_assets = match GameAssets::load_local(self) {
+            Ok(mut assets) => {
+                assets.game_directory = game_directory.clone();
+                assets.copy_legacy_assets()?;
+                assets.game_assets_path()
+            }
+            // 资源索引还没有下载，不影响启动
+            Err(CoreError::Io { .. }) => self.assets_root(),
+            Err(err) => return Err(err),
+        };
 
         let mut variables: HashMap<&str, String> = HashMap::new();
-        // variables.insert("classpath", format!("\"{cp_str}\""));
-        variables.insert("classpath", cp_str.to_string());
+        variables.insert("classpath", classpath.to_string());
         variables.insert("natives_directory", natives_path);
-        variables.insert("launcher_name", "BakaXL".to_owned());
-        variables.insert("launcher_version", "4.0".to_owned());
+        variables.insert("launcher_name", options.launcher_name.clone());
+        variables.insert("launcher_version", options.launcher_version.clone());
         variables.insert("version_name", self.id.clone());
-        variables.insert("game_directory", self.launcher_core.base_path.clone());
-        variables.insert("assets_root", format!("{}/assets", self.launcher_core.assets_path));
-        variables.insert("assets_index_name", self.version_json.clone().asset_index.unwrap().id);
-        variables.insert("resolution_width", "1000".to_owned());
-        variables.insert("resolution_height", "900".to_owned());
-        variables.insert(
-            "auth_player_name", 
-            match &user {
-                UserResult::Developer { username, .. } => username.to_string(),
-                UserResult::Microsoft {  } => todo!(),
-                UserResult::CustomAuth {  } => todo!(),
-            }
-        );
-        variables.insert(
-            "auth_uuid", 
-            match &user {
-                UserResult::Developer { uuid, .. } => uuid.to_string(),
-                UserResult::Microsoft {  } => todo!(),
-                UserResult::CustomAuth {  } => todo!(),
+        variables.insert("version_type", self.version_json.type_.clone().unwrap_or_else(|| "release".to_owned()));
+        variables.insert("game_directory", game_directory.clone());
+        variables.insert("assets_root", self.assets_root());
+        variables.insert("game_assets", game_assets);
+        variables.insert("assets_index_name", asset_index.id.clone());
+        variables.insert("auth_player_name", username);
+        variables.insert("auth_uuid", uuid);
+        variables.insert("auth_access_token", access_token);
+        variables.insert("auth_xuid", xuid);
+        variables.insert("user_type", user_type.to_owned());
+
+        let java = self.find_java()?;
+
+        let mut args = self.build_arguments(&env, options, variables)?;
+        args.splice(0..0, agent_arguments);
+
+        let mut command = match options.wrapper_command.split_first() {
+            Some((wrapper, wrapper_args)) => {
+                let mut command = Command::new(wrapper);
+                command.args(wrapper_args).arg(&java.path);
+                command
             }
-        );
-        variables.insert(
-            "auth_access_token", 
-            match &user {
-                UserResult::Developer { .. } => "".to_owned(),
-                UserResult::Microsoft {  } => todo!(),
-                UserResult::CustomAuth {  } => todo!(),
+            None => Command::new(&java.path),
+        };
+        command.current_dir(&game_directory).envs(&options.environment).args(&args);
+        GameProcess::spawn(command, &game_directory).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound if options.wrapper_command.is_empty() => CoreError::NoJavaFound,
+            _ => CoreError::io(options.wrapper_command.first().unwrap_or(&java.path), err),
+        })
+    }
+
+    /// 替换变量并加入启动选项中的参数，得到java之后的完整参数（不包括authlib-injector的参数）
+    ///
+    /// # 参数
+    /// `env`: 已经应用了启动选项特性的规则环境
+    ///
+    /// `variables`: 账户、路径等变量，窗口大小和快速游戏的变量由`options`提供
+    fn build_arguments(&self, env: &RuleEnvironment, options: &LaunchOptions, mut variables: HashMap<&str, String>) -> Result<Vec<String>> {
+        let (width, height) = options.resolution.unwrap_or((854, 480));
+        variables.insert("resolution_width", width.to_string());
+        variables.insert("resolution_height", height.to_string());
+        variables.extend(options.quick_play_variables());
+
+        let mut args: Vec<_> = self.get_arguments(env)?.iter().map(|x| replace_variables(x, &variables)).collect();
+        let main_class_index = args.iter().position(|arg| *arg == self.version_json.main_class).unwrap_or(args.len());
+        args.splice(main_class_index..main_class_index, options.jvm_arguments.iter().cloned());
+        // 日志配置让游戏输出log4j的XML格式日志，配置文件还没有下载时使用游戏默认的配置
+        if let Some((logging, path)) = self.log_config().filter(|(_, path)| Path::new(path).is_file()) {
+            args.insert(main_class_index, logging.argument.replace("${path}", &path));
+        }
+        args.splice(0..0, options.memory_arguments());
+        args.insert(0, format!("-Dminecraft.client.jar={}", self.jar_path()));
+        if options.fullscreen {
+            args.push("--fullscreen".to_owned());
+        }
+        if !self.supports_quick_play() {
+            args.extend(options.legacy_server_arguments());
+        }
+        args.extend(options.game_arguments.iter().cloned());
+        Ok(args)
+    }
+}
+
+/// 把带规则的参数值（字符串或者数组）加入到参数列表
+fn push_argument_value(argument_str: &mut Vec<String>, value: &Value) {
+    match value {
+        Value::String(s) => {
+            argument_str.push(s.to_string())
+        }
+        Value::Array(arr) => {
+            for arg in arr {
+                if let Some(arg) = arg.as_str() {
+                    argument_str.push(arg.to_owned())
+                }
             }
-        );
-        variables.insert(
-            "auth_xuid", 
-            match &user {
-                UserResult::Developer { .. } => "".to_owned(),
-                UserResult::Microsoft {  } => todo!(),
-                UserResult::CustomAuth {  } => todo!(),
+        }
+        _ => { }
+    }
+}
+
+/// 旧版本（`minecraftArguments`）使用的默认jvm参数，和官方启动器保持一致
+fn default_jvm_arguments() -> Vec<Value> {
+    vec![
+        json!({
+            "rules": [{ "action": "allow", "os": { "name": "osx" } }],
+            "value": ["-XstartOnFirstThread"]
+        }),
+        json!({
+            "rules": [{ "action": "allow", "os": { "name": "windows" } }],
+            "value": "-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump"
+        }),
+        json!({
+            "rules": [{ "action": "allow", "os": { "arch": "x86" } }],
+            "value": "-Xss1M"
+        }),
+        json!("-Djava.library.path=${natives_directory}"),
+        json!("-Dminecraft.launcher.brand=${launcher_name}"),
+        json!("-Dminecraft.launcher.version=${launcher_version}"),
+        json!("-cp"),
+        json!("${classpath}"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::launch::launch_options::QuickPlay;
+    use crate::tools::test_tools::{temp_dir, vanilla_version_json, write_version_json};
+
+    /// 写入`versions/<id>/<id>.json`，`extra`中的字段会合并到最小的版本json中
+    fn write_version(base_path: &str, id: &str, extra: Value) {
+        let mut json = vanilla_version_json(id);
+        for (key, value) in extra.as_object().unwrap() {
+            json[key] = value.clone();
+        }
+        write_version_json(base_path, id, &json);
+    }
+
+    #[test]
+    fn merges_child_into_parent() {
+        let base_path = temp_dir("merge");
+        write_version(&base_path, "1.20.1", json!({
+            "type": "release",
+            "assets": "5",
+            "arguments": { "game": ["--parent"], "jvm": ["-Dparent"] },
+            "libraries": [{ "name": "com.example:parent:1.0" }],
+        }));
+        write_version(&base_path, "fabric", json!({
+            "inheritsFrom": "1.20.1",
+            "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+            "arguments": { "game": ["--child"] },
+            "libraries": [{ "name": "com.example:child:1.0" }],
+        }));
+
+        let version = GameVersion::load(LauncherCore::new(base_path.clone()), "fabric".to_owned()).unwrap();
+        let json = &version.version_json;
+        assert_eq!(version.inheritance_chain, ["fabric", "1.20.1"]);
+        assert_eq!(json.id, "fabric");
+        assert_eq!(json.main_class, "net.fabricmc.loader.impl.launch.knot.KnotClient");
+        assert_eq!(json.assets.as_deref(), Some("5"));
+        assert_eq!(json.type_.as_deref(), Some("release"));
+        let libraries: Vec<&str> = json.libraries.iter().flatten().map(|lib| lib.name.as_str()).collect();
+        assert_eq!(libraries, ["com.example:child:1.0", "com.example:parent:1.0"]);
+        let arguments = json.arguments.as_ref().unwrap();
+        assert_eq!(arguments.game.as_ref().unwrap(), &vec![json!("--parent"), json!("--child")]);
+        assert_eq!(arguments.jvm.as_ref().unwrap(), &vec![json!("-Dparent")]);
+        assert_eq!(version.jar_path(), format!("{base_path}/versions/1.20.1/1.20.1.jar"));
+    }
+
+    #[test]
+    fn uses_child_jar_when_present() {
+        let base_path = temp_dir("jar");
+        write_version(&base_path, "1.12.2", json!({}));
+        write_version(&base_path, "forge", json!({ "inheritsFrom": "1.12.2", "jar": "custom" }));
+        let version = GameVersion::load(LauncherCore::new(base_path.clone()), "forge".to_owned()).unwrap();
+        assert_eq!(version.jar_path(), format!("{base_path}/versions/custom/custom.jar"));
+    }
+
+    #[test]
+    fn detects_inheritance_cycle() {
+        let base_path = temp_dir("cycle");
+        write_version(&base_path, "a", json!({ "inheritsFrom": "b" }));
+        write_version(&base_path, "b", json!({ "inheritsFrom": "a" }));
+        match GameVersion::load(LauncherCore::new(base_path), "a".to_owned()) {
+            Err(CoreError::InheritanceCycle { chain }) => assert_eq!(chain, ["a", "b", "a"]),
+            other => panic!("unexpected result: {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn reports_missing_parent() {
+        let base_path = temp_dir("missing-parent");
+        write_version(&base_path, "forge", json!({ "inheritsFrom": "1.99" }));
+        match GameVersion::load(LauncherCore::new(base_path.clone()), "forge".to_owned()) {
+            Err(CoreError::MissingParentVersion { version, parent }) => {
+                assert_eq!(version, "forge");
+                assert_eq!(parent, "1.99");
             }
-        );
-        variables.insert(
-            "user_type", 
-            match user {
-                UserResult::Developer { .. } => "Legacy".to_owned(),
-                UserResult::Microsoft {  } => "msa".to_owned(),
-                UserResult::CustomAuth {  } => "Mojang".to_owned(),
+            other => panic!("unexpected result: {:?}", other.err()),
+        }
+        assert!(matches!(
+            GameVersion::load(LauncherCore::new(base_path), "none".to_owned()),
+            Err(CoreError::MissingVersionJson { .. })
+        ));
+    }
+
+    /// 1.20.1风格的参数，窗口大小和快速游戏参数带有特性规则
+    fn write_modern_version(base_path: &str) -> GameVersion {
+        write_version(base_path, "1.20.1", json!({
+            "arguments": {
+                "jvm": ["-Djava.library.path=${natives_directory}", "-cp", "${classpath}"],
+                "game": [
+                    "--username", "${auth_player_name}", "--version", "${version_name}",
+                    { "rules": [{ "action": "allow", "features": { "has_custom_resolution": true } }], "value": ["--width", "${resolution_width}", "--height", "${resolution_height}"] },
+                    { "rules": [{ "action": "allow", "features": { "is_quick_play_multiplayer": true } }], "value": ["--quickPlayMultiplayer", "${quickPlayMultiplayer}"] },
+                    { "rules": [{ "action": "allow", "features": { "is_quick_play_singleplayer": true } }], "value": ["--quickPlaySingleplayer", "${quickPlaySingleplayer}"] }
+                ]
             }
-        );
-        
-        // let replace_arg = replace_variables(&argument.join(" "), &variables);
-        
-        let java_command = match std::env::consts::OS {
-            "linux" => "java",
-            "macos" => "/usr/bin/java",
-            "windows" => "java.exe",
-            _ => ""
-        };
+        }));
+        GameVersion::load(LauncherCore::new(base_path.to_owned()), "1.20.1".to_owned()).unwrap()
+    }
 
-        // let args: [&str; 2] = [&format!("-Dminecraft.client.jar={}/{}.jar", self.path.clone(), self.id.clone()) ,&replace_arg];
+    fn build_arguments(version: &GameVersion, options: &LaunchOptions) -> Vec<String> {
+        let env = options.apply_features(RuleEnvironment::new("linux", "6.1", "x86_64"));
+        let variables = HashMap::from([
+            ("natives_directory", "/natives".to_owned()),
+            ("classpath", "a.jar:b.jar".to_owned()),
+            ("auth_player_name", "Steve".to_owned()),
+            ("version_name", version.id.clone()),
+            ("launcher_name", "BakaXL".to_owned()),
+            ("launcher_version", "4.0".to_owned()),
+        ]);
+        version.build_arguments(&env, options, variables).unwrap()
+    }
 
-        let mut args: Vec<_> = self.get_arguments().iter().map(|x| replace_variables(&x, &variables)).collect();
-        args.insert(0, format!("-Dminecraft.client.jar={}/{}.jar", &self.path, &self.id));
+    #[test]
+    fn builds_default_arguments() {
+        let version = write_modern_version(&temp_dir("default-arguments"));
+        assert_eq!(build_arguments(&version, &LaunchOptions::new()), [
+            format!("-Dminecraft.client.jar={}", version.jar_path()),
+            "-Djava.library.path=/natives".to_owned(),
+            "-cp".to_owned(),
+            "a.jar:b.jar".to_owned(),
+            "net.minecraft.client.main.Main".to_owned(),
+            "--username".to_owned(),
+            "Steve".to_owned(),
+            "--version".to_owned(),
+            "1.20.1".to_owned(),
+        ]);
+    }
 
-        println!("### Debug: {:?}\n{:?}\n{:?}\n{:?}", &java_command, &args, &variables, self.get_arguments());
-        let output = Command::new(java_command)
-            .current_dir(&self.path)
-            .args(&args)
-            .output();
+    #[test]
+    fn builds_arguments_with_options() {
+        let version = write_modern_version(&temp_dir("option-arguments"));
+        assert!(version.supports_quick_play());
+        let options = LaunchOptions::new()
+            .with_min_memory(1024)
+            .with_max_memory(4096)
+            .with_resolution(1280, 720)
+            .with_fullscreen(true)
+            .with_jvm_argument("-XX:+UseG1GC")
+            .with_game_argument("--demo")
+            .with_server("mc.example.com", Some(25566));
+        assert_eq!(build_arguments(&version, &options), [
+            format!("-Dminecraft.client.jar={}", version.jar_path()),
+            "-Xms1024m".to_owned(),
+            "-Xmx4096m".to_owned(),
+            "-Djava.library.path=/natives".to_owned(),
+            "-cp".to_owned(),
+            "a.jar:b.jar".to_owned(),
+            "-XX:+UseG1GC".to_owned(),
+            "net.minecraft.client.main.Main".to_owned(),
+            "--username".to_owned(),
+            "Steve".to_owned(),
+            "--version".to_owned(),
+            "1.20.1".to_owned(),
+            "--width".to_owned(),
+            "1280".to_owned(),
+            "--height".to_owned(),
+            "720".to_owned(),
+            "--quickPlayMultiplayer".to_owned(),
+            "mc.example.com:25566".to_owned(),
+            "--fullscreen".to_owned(),
+            "--demo".to_owned(),
+        ]);
 
-            match output {
-                Ok(output) => {
-                    if output.status.success() {
-                        println!("Java program executed successfully");
-                    } else {
-                        
-                        eprintln!("Java program execution failed: {}\n{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
-                    }
-                }
-                Err(err) => {
-                    eprintln!("Failed to execute Java program: {}", err);
-                }
-            }
+        let arguments = build_arguments(&version, &LaunchOptions::new().with_quick_play(QuickPlay::Singleplayer("New World".to_owned())));
+        assert!(arguments.ends_with(&["--quickPlaySingleplayer".to_owned(), "New World".to_owned()]));
+    }
+
+    #[test]
+    fn uses_legacy_server_arguments() {
+        let base_path = temp_dir("legacy-arguments");
+        write_version(&base_path, "1.12.2", json!({
+            "arguments": null,
+            "minecraftArguments": "--username ${auth_player_name} --version ${version_name}"
+        }));
+        let version = GameVersion::load(LauncherCore::new(base_path), "1.12.2".to_owned()).unwrap();
+        assert!(!version.supports_quick_play());
+
+        let options = LaunchOptions::new().with_jvm_argument("-Dextra").with_server("mc.example.com", Some(25566)).with_game_argument("--demo");
+        let arguments = build_arguments(&version, &options);
+        assert!(arguments.contains(&"-Dminecraft.launcher.brand=BakaXL".to_owned()));
+        let main_class = arguments.iter().position(|argument| argument == "net.minecraft.client.main.Main").unwrap();
+        assert_eq!(arguments[main_class - 1], "-Dextra");
+        assert_eq!(&arguments[main_class + 1..], [
+            "--username", "Steve", "--version", "1.12.2", "--server", "mc.example.com", "--port", "25566", "--demo",
+        ]);
+
+        // 旧版本不支持进入单人世界
+        let options = LaunchOptions::new().with_quick_play(QuickPlay::Singleplayer("New World".to_owned()));
+        assert!(build_arguments(&version, &options).ends_with(&["--version".to_owned(), "1.12.2".to_owned()]));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,143 @@
+use serde_json::Value;
+
+use crate::download::downloader::Downloader;
+use crate::error::{CoreError, Result};
+use crate::game_version::GameVersion;
+use crate::json::json_loader_meta::{JsonIntermediaryVersion, JsonLoaderGameVersion, JsonLoaderVersion};
+use crate::launcher_core::LauncherCore;
+
+use super::version_profile;
+
+/// Fabric meta的地址
+pub const FABRIC_META_URL: &str = "https://meta.fabricmc.net";
+
+/// 安装Fabric加载器
+///
+/// 版本json由Fabric meta生成，通过`inheritsFrom`继承原版，库文件从Fabric的Maven仓库下载
+pub struct FabricInstaller {
+    /// Fabric meta的地址，可以改为本地的测试服务器或者镜像
+    pub meta_url: String,
+}
+
+impl Default for FabricInstaller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FabricInstaller {
+    pub fn new() -> Self {
+        Self { meta_url: FABRIC_META_URL.to_owned() }
+    }
+
+    pub fn with_meta_url(mut self, meta_url: &str) -> Self {
+        self.meta_url = meta_url.trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// Fabric支持的游戏版本，从新到旧
+    pub fn game_versions(&self, downloader: &Downloader) -> Result<Vec<JsonLoaderGameVersion>> {
+        downloader.get_json(&format!("{}/v2/versions/game", self.meta_url))
+    }
+
+    /// 所有的加载器版本，从新到旧
+    pub fn loader_versions(&self, downloader: &Downloader) -> Result<Vec<JsonLoaderVersion>> {
+        downloader.get_json(&format!("{}/v2/versions/loader", self.meta_url))
+    }
+
+    /// 所有的intermediary版本，没有intermediary的游戏版本无法安装Fabric
+    pub fn intermediary_versions(&self, downloader: &Downloader) -> Result<Vec<JsonIntermediaryVersion>> {
+        downloader.get_json(&format!("{}/v2/versions/intermediary", self.meta_url))
+    }
+
+    /// 安装后的默认版本名字，和官方安装器一致
+    pub fn version_id(game_version: &str, loader_version: &str) -> String {
+        format!("fabric-loader-{loader_version}-{game_version}")
+    }
+
+    /// 安装Fabric
+    ///
+    /// # 参数
+    /// `core`: 安装到的启动核心，需要已经安装了`game_version`对应的原版
+    ///
+    /// `game_version`: 游戏版本，例如`1.20.1`
+    ///
+    /// `loader_version`: 加载器版本，例如`0.14.21`
+    ///
+    /// 版本json会写入`versions/<id>/<id>.json`，然后下载缺失的库文件。安装完成后返回加载好的版本
+    pub fn install(&self, core: LauncherCore, game_version: &str, loader_version: &str, downloader: &Downloader) -> Result<GameVersion> {
+        let id = Self::version_id(game_version, loader_version);
+        version_profile::ensure_parent_installed(&core, &id, game_version)?;
+
+        let has_intermediary = self.intermediary_versions(downloader)?.iter().any(|version| version.version == game_version);
+        let has_loader = self.loader_versions(downloader)?.iter().any(|version| version.version == loader_version);
+        if !has_intermediary || !has_loader {
+            return Err(CoreError::UnsupportedLoader {
+                loader: "Fabric".to_owned(),
+                loader_version: loader_version.to_owned(),
+                game_version: game_version.to_owned(),
+            });
+        }
+
+        let profile_url = format!("{}/v2/versions/loader/{game_version}/{loader_version}/profile/json", self.meta_url);
+        let profile: Value = downloader.get_json(&profile_url)?;
+        version_profile::install_version_json(core, &id, profile, downloader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::tools::test_tools::{assert_libraries_downloaded, fixture, loader_meta_server, temp_dir, write_vanilla_version, MockServer};
+
+    use super::*;
+
+    const PROFILE: &str = "fabric/profile-0.14.21-1.20.1.json";
+
+    /// 提供Fabric meta和Maven仓库的服务器，库文件的内容为它的名字
+    fn fabric_server() -> MockServer {
+        loader_meta_server(&[
+            ("/v2/versions/loader", "fabric/loader-versions.json"),
+            ("/v2/versions/intermediary", "fabric/intermediary-versions.json"),
+            ("/v2/versions/loader/1.20.1/0.14.21/profile/json", PROFILE),
+        ], PROFILE, "https://maven.fabricmc.net/")
+    }
+
+    #[test]
+    fn installs_captured_profile() {
+        let server = fabric_server();
+        let downloader = Downloader::new().with_retries(0).with_base_url(&server.url);
+        let base_path = temp_dir("fabric");
+        write_vanilla_version(&base_path, "1.20.1");
+
+        let installer = FabricInstaller::new().with_meta_url(&server.url);
+        let version = installer.install(LauncherCore::new(base_path.clone()), "1.20.1", "0.14.21", &downloader).unwrap();
+        assert_eq!(version.id, "fabric-loader-0.14.21-1.20.1");
+        assert_eq!(version.inheritance_chain, ["fabric-loader-0.14.21-1.20.1", "1.20.1"]);
+        assert_eq!(version.version_json.main_class, "net.fabricmc.loader.impl.launch.knot.KnotClient");
+
+        let json_path = format!("{base_path}/versions/{0}/{0}.json", version.id);
+        let written: Value = serde_json::from_str(&fs::read_to_string(json_path).unwrap()).unwrap();
+        let profile: Value = serde_json::from_str(&fixture(PROFILE)).unwrap();
+        assert_eq!(written["id"], "fabric-loader-0.14.21-1.20.1");
+        assert_eq!(written["inheritsFrom"], "1.20.1");
+        assert_eq!(written["mainClass"], "net.fabricmc.loader.impl.launch.knot.KnotClient");
+        assert_eq!(written["libraries"], profile["libraries"]);
+        assert_libraries_downloaded(&base_path, profile["libraries"].as_array().unwrap());
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let server = fabric_server();
+        let downloader = Downloader::new().with_retries(0).with_base_url(&server.url);
+        let base_path = temp_dir("fabric-unsupported");
+        write_vanilla_version(&base_path, "1.20.1");
+
+        let installer = FabricInstaller::new().with_meta_url(&server.url);
+        let result = installer.install(LauncherCore::new(base_path.clone()), "1.20.1", "0.1.0", &downloader);
+        assert!(matches!(result, Err(CoreError::UnsupportedLoader { .. })));
+        let result = installer.install(LauncherCore::new(base_path.clone()), "1.19.4", "0.14.21", &downloader);
+        assert!(matches!(result, Err(CoreError::MissingParentVersion { .. })));
+    }
+}
@@ -0,0 +1,676 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+
+use regex::{Captures, Regex};
+use serde::de::DeserializeOwned;
+use zip::read::ZipArchive;
+use zip::result::ZipError;
+
+use crate::download::download_task::DownloadTask;
+use crate::download::downloader::Downloader;
+use crate::error::{CoreError, Result};
+use crate::game_version::GameVersion;
+use crate::json::json_forge::{JsonForgeInstallProfile, JsonForgeProcessor, JsonLegacyForgeInstallProfile};
+use crate::launch::classpath::CLASSPATH_SEPARATOR;
+use crate::launcher_core::LauncherCore;
+use crate::platform::rule_evaluator::RuleEnvironment;
+use crate::tools::file_tools::MavenCoordinate;
+
+use super::version_profile;
+
+/// Forge的Maven仓库
+pub const FORGE_MAVEN_URL: &str = "https://maven.minecraftforge.net/";
+/// NeoForge的Maven仓库
+pub const NEOFORGE_MAVEN_URL: &str = "https://maven.neoforged.net/releases/";
+
+/// 安装器中内嵌的Maven仓库，里面的文件会直接复制到`libraries`
+const INSTALLER_MAVEN_DIR: &str = "maven/";
+
+/// 处理器失败时错误信息中保留的输出行数
+const PROCESSOR_OUTPUT_LINES: usize = 20;
+
+/// 打开的安装器jar
+pub(crate) struct InstallerJar {
+    pub path: String,
+    archive: ZipArchive<File>,
+}
+
+impl InstallerJar {
+    pub fn open(path: &str) -> Result<Self> {
+        let file = File::open(path).map_err(|err| CoreError::io(path, err))?;
+        let archive = ZipArchive::new(file).map_err(|source| CoreError::CorruptZip { path: path.to_owned(), source })?;
+        Ok(Self { path: path.to_owned(), archive })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        let name = name.trim_start_matches('/');
+        self.archive.file_names().any(|file_name| file_name == name)
+    }
+
+    pub fn read_bytes(&mut self, name: &str) -> Result<Vec<u8>> {
+        let name = name.trim_start_matches('/');
+        let mut file = self.archive.by_name(name).map_err(|err| match err {
+            ZipError::FileNotFound => CoreError::InvalidInstaller { path: self.path.clone(), message: format!("{name} not found") },
+            source => CoreError::CorruptZip { path: self.path.clone(), source },
+        })?;
+        let mut contents = vec![];
+        file.read_to_end(&mut contents).map_err(|err| CoreError::io(&self.path, err))?;
+        Ok(contents)
+    }
+
+    pub fn read_string(&mut self, name: &str) -> Result<String> {
+        let contents = self.read_bytes(name)?;
+        String::from_utf8(contents).map_err(|_| CoreError::InvalidInstaller {
+            path: self.path.clone(),
+            message: format!("{} is not valid UTF-8", name.trim_start_matches('/')),
+        })
+    }
+
+    pub fn read_json<T: DeserializeOwned>(&mut self, name: &str) -> Result<T> {
+        let contents = self.read_string(name)?;
+        serde_json::from_str(&contents).map_err(|err| CoreError::parse_json(format!("{}!/{}", self.path, name.trim_start_matches('/')), err))
+    }
+
+    /// 把安装器中的文件`name`解压到`target`
+    pub fn extract(&mut self, name: &str, target: &str) -> Result<()> {
+        let name = name.trim_start_matches('/');
+        let mut file = self.archive.by_name(name).map_err(|err| match err {
+            ZipError::FileNotFound => CoreError::InvalidInstaller { path: self.path.clone(), message: format!("{name} not found") },
+            source => CoreError::CorruptZip { path: self.path.clone(), source },
+        })?;
+        if let Some(parent_dir) = Path::new(target).parent() {
+            fs::create_dir_all(parent_dir).map_err(|err| CoreError::io(parent_dir.to_string_lossy(), err))?;
+        }
+        let mut output = File::create(target).map_err(|err| CoreError::io(target, err))?;
+        std::io::copy(&mut file, &mut output).map_err(|err| CoreError::io(target, err))?;
+        Ok(())
+    }
+
+    /// 把内嵌的Maven仓库复制到`libraries_path`，已经存在的文件会被覆盖
+    pub fn extract_maven(&mut self, libraries_path: &str) -> Result<()> {
+        let names: Vec<String> = self.archive.file_names()
+            .filter(|name| name.starts_with(INSTALLER_MAVEN_DIR) && !name.ends_with('/'))
+            .map(str::to_owned)
+            .collect();
+        for name in names {
+            let relative_path = &name[INSTALLER_MAVEN_DIR.len()..];
+            if relative_path.split('/').any(|part| part == "..") {
+                let source = ZipError::InvalidArchive("Entry path escapes the extraction directory");
+                return Err(CoreError::CorruptZip { path: self.path.clone(), source });
+            }
+            self.extract(&name, &format!("{libraries_path}/{relative_path}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// 安装Forge和NeoForge
+///
+/// 1.13及以后的安装器按照`install_profile.json`下载处理器需要的库和原版的jar，
+/// 依次运行处理器（拆分jar、应用二进制补丁、反混淆等）生成启动需要的文件，全部成功后才写入`version.json`。
+/// 1.12.2及以前的安装器直接使用`install_profile.json`中的`versionInfo`，并把Forge本体复制到`libraries`。
+/// NeoForge的安装器和新版Forge格式相同
+pub struct ForgeInstaller {
+    /// 安装器jar的路径
+    pub installer_path: String,
+    /// 运行处理器使用的Java，为`None`时使用版本要求的Java
+    pub java_path: Option<String>,
+}
+
+impl ForgeInstaller {
+    pub fn new(installer_path: &str) -> Self {
+        Self { installer_path: installer_path.to_owned(), java_path: None }
+    }
+
+    /// 指定运行处理器使用的Java
+    pub fn with_java(mut self, java_path: &str) -> Self {
+        self.java_path = Some(java_path.to_owned());
+        self
+    }
+
+    /// 安装器的Maven坐标，`forge_version`为完整的版本，例如`1.20.1-47.1.0`
+    pub fn installer_coordinate(forge_version: &str) -> MavenCoordinate {
+        MavenCoordinate::new("net.minecraftforge", "forge", forge_version).with_classifier("installer")
+    }
+
+    /// NeoForge安装器的Maven坐标，例如`20.4.80`；1.20.1的NeoForge沿用了Forge的命名，例如`1.20.1-47.1.79`
+    pub fn neoforge_installer_coordinate(neoforge_version: &str) -> MavenCoordinate {
+        let artifact_id = if neoforge_version.starts_with("1.20.1-") { "forge" } else { "neoforge" };
+        MavenCoordinate::new("net.neoforged", artifact_id, neoforge_version).with_classifier("installer")
+    }
+
+    /// 从Forge的Maven仓库下载安装器到`installer_path`
+    pub fn download(forge_version: &str, installer_path: &str, downloader: &Downloader) -> Result<Self> {
+        let url = Self::installer_coordinate(forge_version).url(FORGE_MAVEN_URL);
+        downloader.download(&DownloadTask::new(&url, installer_path))?;
+        Ok(Self::new(installer_path))
+    }
+
+    /// 从NeoForge的Maven仓库下载安装器到`installer_path`
+    pub fn download_neoforge(neoforge_version: &str, installer_path: &str, downloader: &Downloader) -> Result<Self> {
+        let url = Self::neoforge_installer_coordinate(neoforge_version).url(NEOFORGE_MAVEN_URL);
+        downloader.download(&DownloadTask::new(&url, installer_path))?;
+        Ok(Self::new(installer_path))
+    }
+
+    /// 安装Forge或者NeoForge，安装器的格式会自动识别
+    ///
+    /// # 参数
+    /// `core`: 安装到的启动核心，需要已经安装了对应的原版
+    ///
+    /// 处理器使用`java_path`或者版本要求的Java运行，输出文件的SHA-1和`install_profile.json`中的不一致时安装失败。
+    /// 输出文件已经存在并且正确的处理器会被跳过。版本json在所有文件准备好之后才写入，
+    /// 安装失败时不会留下无法启动的版本。安装完成后返回加载好的版本
+    pub fn install(&self, core: LauncherCore, downloader: &Downloader) -> Result<GameVersion> {
+        let mut jar = InstallerJar::open(&self.installer_path)?;
+        if !jar.contains("install_profile.json") {
+            return Err(CoreError::InvalidInstaller { path: self.installer_path.clone(), message: "install_profile.json not found".to_owned() });
+        }
+        let profile: serde_json::Value = jar.read_json("install_profile.json")?;
+        let profile_path = format!("{}!/install_profile.json", self.installer_path);
+        if profile.get("versionInfo").is_some() {
+            let profile = serde_json::from_value(profile).map_err(|err| CoreError::parse_json(&profile_path, err))?;
+            return Self::install_legacy(&mut jar, profile, core, downloader);
+        }
+        let profile: JsonForgeInstallProfile = serde_json::from_value(profile).map_err(|err| CoreError::parse_json(&profile_path, err))?;
+        let is_neoforge = profile.profile.as_deref().is_some_and(|name| name.eq_ignore_ascii_case("neoforge"));
+        let repository = if is_neoforge { NEOFORGE_MAVEN_URL } else { FORGE_MAVEN_URL };
+        let mut version_json: serde_json::Value = jar.read_json(profile.json.as_deref().unwrap_or("/version.json"))?;
+        let id = version_json.get("id").and_then(|id| id.as_str()).unwrap_or(&profile.version).to_owned();
+        version_profile::ensure_parent_installed(&core, &id, &profile.minecraft)?;
+        if let Some(object) = version_json.as_object_mut() {
+            object.entry("inheritsFrom").or_insert_with(|| profile.minecraft.clone().into());
+        }
+        let version = version_profile::load_version_json(core.clone(), &id, &mut version_json)?;
+
+        let libraries_path = format!("{}/libraries", core.assets_path);
+        jar.extract_maven(&libraries_path)?;
+        let mut tasks: Vec<DownloadTask> = profile.libraries.iter()
+            .filter_map(|lib| DownloadTask::from_library(lib, &libraries_path, repository))
+            .filter(|task| !task.is_complete())
+            .collect();
+        tasks.extend(version.get_missing_files(&RuleEnvironment::current()));
+        downloader.download_all(&tasks)?;
+
+        let result = self.run_processors(&mut jar, &profile, &core, &version, downloader);
+        let _ = fs::remove_dir_all(format!("{}/installer-data", version.path));
+        if result.is_err() {
+            // 只删除空的版本文件夹，不影响已经安装的同名版本
+            let _ = fs::remove_dir(&version.path);
+        }
+        result?;
+        version_profile::write_version_json(&core, &id, &mut version_json)?;
+        GameVersion::load(core, id)
+    }
+
+    /// 准备原版的jar和处理器使用的变量，然后依次运行客户端的处理器
+    ///
+    /// 安装器中的数据文件解压到版本文件夹中的`installer-data`
+    fn run_processors(&self, jar: &mut InstallerJar, profile: &JsonForgeInstallProfile, core: &LauncherCore, version: &GameVersion, downloader: &Downloader) -> Result<()> {
+        let processors: Vec<&JsonForgeProcessor> = profile.processors.iter()
+            .filter(|processor| processor.sides.as_ref().is_none_or(|sides| sides.iter().any(|side| side == "client")))
+            .collect();
+        if processors.is_empty() {
+            return Ok(());
+        }
+        let data_dir = format!("{}/installer-data", version.path);
+        // 处理器需要原版的jar，版本json指定了其他jar时不会随Forge一起下载
+        let parent = GameVersion::load(core.clone(), profile.minecraft.clone())?;
+        let minecraft_jar = parent.jar_path();
+        if !Path::new(&minecraft_jar).is_file() {
+            parent.download_missing_files(downloader, &RuleEnvironment::current())?;
+        }
+        if !Path::new(&minecraft_jar).is_file() {
+            return Err(CoreError::MissingFiles { paths: vec![minecraft_jar] });
+        }
+        let java_path = match &self.java_path {
+            Some(java_path) => java_path.clone(),
+            None => version.find_java()?.path,
+        };
+        let mut runner = ProcessorRunner {
+            java_path,
+            libraries_path: format!("{}/libraries", core.assets_path),
+            data: HashMap::new(),
+            variable_regex: Regex::new(r"\{(\w+)\}").unwrap(),
+        };
+        runner.data.insert("SIDE".to_owned(), "client".to_owned());
+        runner.data.insert("MINECRAFT_JAR".to_owned(), minecraft_jar);
+        runner.data.insert("MINECRAFT_VERSION".to_owned(), profile.minecraft.clone());
+        runner.data.insert("ROOT".to_owned(), core.base_path.clone());
+        runner.data.insert("INSTALLER".to_owned(), self.installer_path.clone());
+        runner.data.insert("LIBRARY_DIR".to_owned(), runner.libraries_path.clone());
+        for (key, value) in &profile.data {
+            let value = match value.client.strip_prefix('/') {
+                // 安装器中的文件，例如`/data/client.lzma`
+                Some(name) => {
+                    let target = format!("{data_dir}/{name}");
+                    jar.extract(name, &target)?;
+                    target
+                }
+                None => runner.expand(&value.client)?,
+            };
+            runner.data.insert(key.clone(), value);
+        }
+
+        for processor in processors {
+            runner.run(processor)?;
+        }
+        Ok(())
+    }
+
+    /// 安装1.12.2及以前的Forge
+    fn install_legacy(jar: &mut InstallerJar, profile: JsonLegacyForgeInstallProfile, core: LauncherCore, downloader: &Downloader) -> Result<GameVersion> {
+        let install = &profile.install;
+        let mut version_json = profile.version_info;
+        let id = version_json.get("id").and_then(|id| id.as_str())
+            .or(install.target.as_deref())
+            .unwrap_or(&install.path)
+            .to_owned();
+        version_profile::ensure_parent_installed(&core, &id, &install.minecraft)?;
+
+        let coordinate: MavenCoordinate = install.path.parse()?;
+        jar.extract(&install.file_path, &format!("{}/libraries/{}", core.assets_path, coordinate.path()))?;
+        // 1.7.10等旧版本的versionInfo是完整的版本json，改为继承原版，使用原版的jar，
+        // 并去掉只有服务端需要的库以及和原版完全相同的库，后者在versionInfo中没有natives的下载信息
+        let parent = GameVersion::load(core.clone(), install.minecraft.clone())?;
+        let parent_libraries: HashSet<&str> = parent.version_json.libraries.iter().flatten().map(|lib| lib.name.as_str()).collect();
+        if let Some(object) = version_json.as_object_mut() {
+            object.entry("inheritsFrom").or_insert_with(|| install.minecraft.clone().into());
+            object.entry("jar").or_insert_with(|| install.minecraft.clone().into());
+            if let Some(libraries) = object.get_mut("libraries").and_then(|libraries| libraries.as_array_mut()) {
+                // `clientreq`为false的库只有服务端需要
+                libraries.retain(|lib| lib.get("clientreq").and_then(|clientreq| clientreq.as_bool()) != Some(false));
+                libraries.retain(|lib| lib.get("name").and_then(|name| name.as_str()).is_none_or(|name| !parent_libraries.contains(name)));
+            }
+        }
+        version_profile::install_version_json(core, &id, version_json, downloader)
+    }
+}
+
+/// 运行安装器的处理器
+pub(crate) struct ProcessorRunner {
+    pub java_path: String,
+    pub libraries_path: String,
+    /// `install_profile.json`中的`data`以及内置的变量
+    pub data: HashMap<String, String>,
+    variable_regex: Regex,
+}
+
+impl ProcessorRunner {
+    /// 库文件的路径
+    fn library_path(&self, name: &str) -> Result<String> {
+        let coordinate: MavenCoordinate = name.parse()?;
+        Ok(format!("{}/{}", self.libraries_path, coordinate.path()))
+    }
+
+    /// 展开参数：`[maven坐标]`为库文件的路径，`'文本'`为文本，其余替换其中的`{变量}`
+    fn expand(&self, value: &str) -> Result<String> {
+        if let Some(name) = value.strip_prefix('[').and_then(|value| value.strip_suffix(']')) {
+            return self.library_path(name);
+        }
+        if let Some(literal) = value.strip_prefix('\'').and_then(|value| value.strip_suffix('\'')) {
+            return Ok(literal.to_owned());
+        }
+        let mut missing: Option<String> = None;
+        let expanded = self.variable_regex.replace_all(value, |captures: &Captures| match self.data.get(&captures[1]) {
+            Some(value) => value.clone(),
+            None => {
+                missing.get_or_insert_with(|| captures[1].to_owned());
+                captures[0].to_owned()
+            }
+        });
+        match missing {
+            Some(key) => Err(CoreError::ProcessorFailed { processor: value.to_owned(), message: format!("Unknown variable {key}") }),
+            None => Ok(expanded.into_owned()),
+        }
+    }
+
+    /// 处理器的输出文件和它们的SHA-1
+    fn outputs(&self, processor: &JsonForgeProcessor) -> Result<Vec<DownloadTask>> {
+        processor.outputs.iter()
+            .map(|(path, sha1)| Ok(DownloadTask::new("", &self.expand(path)?).with_sha1(&self.expand(sha1)?)))
+            .collect()
+    }
+
+    pub fn run(&self, processor: &JsonForgeProcessor) -> Result<()> {
+        let failed = |message: String| CoreError::ProcessorFailed { processor: processor.jar.clone(), message };
+        let outputs = self.outputs(processor)?;
+        if !outputs.is_empty() && outputs.iter().all(DownloadTask::is_complete) {
+            return Ok(());
+        }
+
+        let jar_path = self.library_path(&processor.jar)?;
+        let main_class = read_main_class(&jar_path)?.ok_or_else(|| failed("No Main-Class in the jar manifest".to_owned()))?;
+        let mut classpath = vec![jar_path];
+        for name in &processor.classpath {
+            classpath.push(self.library_path(name)?);
+        }
+        let args = processor.args.iter().map(|arg| self.expand(arg)).collect::<Result<Vec<String>>>()?;
+
+        let output = Command::new(&self.java_path)
+            .arg("-cp")
+            .arg(classpath.join(CLASSPATH_SEPARATOR))
+            .arg(&main_class)
+            .args(&args)
+            .output()
+            .map_err(|err| CoreError::io(&self.java_path, err))?;
+        if !output.status.success() {
+            let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+            let lines: Vec<&str> = text.lines().collect();
+            let tail = lines[lines.len().saturating_sub(PROCESSOR_OUTPUT_LINES)..].join("\n");
+            return Err(failed(format!("{main_class} exited with {}\n{tail}", output.status)));
+        }
+        for output in &outputs {
+            output.verify(&output.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// 读取jar中`META-INF/MANIFEST.MF`的`Main-Class`
+fn read_main_class(jar_path: &str) -> Result<Option<String>> {
+    let file = File::open(jar_path).map_err(|err| CoreError::io(jar_path, err))?;
+    let mut archive = ZipArchive::new(file).map_err(|source| CoreError::CorruptZip { path: jar_path.to_owned(), source })?;
+    let mut manifest = String::new();
+    match archive.by_name("META-INF/MANIFEST.MF") {
+        Ok(mut file) => file.read_to_string(&mut manifest).map_err(|err| CoreError::io(jar_path, err))?,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(source) => return Err(CoreError::CorruptZip { path: jar_path.to_owned(), source }),
+    };
+    Ok(manifest.lines()
+        .find_map(|line| line.strip_prefix("Main-Class:"))
+        .map(|main_class| main_class.trim().to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::{json, Value};
+
+    use crate::tools::hash_tools::sha1_bytes;
+    use crate::tools::test_tools::{temp_dir, vanilla_version_json, write_file, write_version_json, write_zip, MockServer};
+
+    use super::*;
+
+    const FORGE_ID: &str = "1.20.1-forge-47.1.0";
+    const PATCHED: &[u8] = b"patched";
+
+    /// 只有`Main-Class`的处理器jar
+    fn processor_jar(dir: &str) -> Vec<u8> {
+        let path = format!("{dir}/processor.jar");
+        write_zip(&path, &[("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\nMain-Class: net.minecraftforge.binarypatcher.ConsoleTool\n")]);
+        fs::read(path).unwrap()
+    }
+
+    /// 写入安装器，客户端处理器会用二进制补丁生成`forge-1.20.1-47.1.0-client.jar`，它的SHA-1应为`patched_sha1`
+    fn write_installer(path: &str, patched_sha1: &str) {
+        let profile = json!({
+            "spec": 1,
+            "profile": "forge",
+            "version": FORGE_ID,
+            "minecraft": "1.20.1",
+            "json": "/version.json",
+            "data": {
+                "MAPPINGS": { "client": "[de.oceanlabs.mcp:mcp_config:1.20.1-20230612.114412:mappings@txt]", "server": "" },
+                "BINPATCH": { "client": "/data/client.lzma", "server": "/data/server.lzma" },
+                "PATCHED": { "client": "[net.minecraftforge:forge:1.20.1-47.1.0:client]", "server": "" },
+                "PATCHED_SHA": { "client": format!("'{patched_sha1}'"), "server": "" }
+            },
+            "processors": [
+                { "sides": ["server"], "jar": "net.minecraftforge:installertools:1.3.0", "args": ["--task", "SERVER_ONLY"] },
+                {
+                    "sides": ["client"],
+                    "jar": "net.minecraftforge:binarypatcher:1.1.1",
+                    "classpath": ["commons-io:commons-io:2.4"],
+                    "args": ["--clean", "{MINECRAFT_JAR}", "--output", "{PATCHED}", "--apply", "{BINPATCH}", "--mappings", "{MAPPINGS}", "--side", "{SIDE}"],
+                    "outputs": { "{PATCHED}": "{PATCHED_SHA}" }
+                }
+            ],
+            "libraries": []
+        });
+        let mut version_json = vanilla_version_json(FORGE_ID);
+        version_json["inheritsFrom"] = json!("1.20.1");
+        version_json["mainClass"] = json!("cpw.mods.bootstraplauncher.BootstrapLauncher");
+        version_json["libraries"] = json!([{ "name": "cpw.mods:bootstraplauncher:1.1.2", "url": FORGE_MAVEN_URL }]);
+        let dir = Path::new(path).parent().unwrap().to_string_lossy().into_owned();
+        write_zip(path, &[
+            ("install_profile.json", profile.to_string().as_bytes()),
+            ("version.json", version_json.to_string().as_bytes()),
+            ("data/client.lzma", b"binary patches"),
+            ("maven/net/minecraftforge/binarypatcher/1.1.1/binarypatcher-1.1.1.jar", &processor_jar(&dir)),
+            ("maven/commons-io/commons-io/2.4/commons-io-2.4.jar", b"commons-io"),
+        ]);
+    }
+
+    /// 写入没有游戏本体jar的原版，`client`为`downloads`中的`client`
+    fn write_vanilla(base_path: &str, client: Option<Value>) {
+        let mut json = vanilla_version_json("1.20.1");
+        if let Some(client) = client {
+            json["downloads"] = json!({ "client": client });
+        }
+        write_version_json(base_path, "1.20.1", &json);
+    }
+
+    /// 提供原版jar和Forge库文件的服务器
+    fn forge_server(client: &[u8]) -> (MockServer, Value) {
+        let server = MockServer::serve_files(HashMap::from([
+            ("/v1/objects/client.jar".to_owned(), client.to_vec()),
+            ("/cpw/mods/bootstraplauncher/1.1.2/bootstraplauncher-1.1.2.jar".to_owned(), b"bootstraplauncher".to_vec()),
+        ]));
+        let download = json!({ "sha1": sha1_bytes(client), "size": client.len(), "url": "https://piston-data.mojang.com/v1/objects/client.jar" });
+        (server, download)
+    }
+
+    /// 代替Java的脚本：记录参数，复制安装器中解压出的文件，并把`--output`写为`patched`
+    #[cfg(unix)]
+    fn fake_java(dir: &str) -> String {
+        crate::tools::test_tools::write_script(&format!("{dir}/java"), &format!(r#"printf '%s\n' "$@" >> "{dir}/java-args.txt"
+for arg in "$@"; do
+    case "$arg" in */installer-data/*) cp "$arg" "{dir}/installer-data-copy" ;; esac
+done
+while [ $# -gt 0 ]; do
+    if [ "$1" = "--output" ]; then mkdir -p "$(dirname "$2")"; printf patched > "$2"; fi
+    shift
+done"#))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_processors_before_writing_version_json() {
+        let client = b"vanilla client".to_vec();
+        let (server, download) = forge_server(&client);
+        let downloader = Downloader::new().with_retries(0).with_base_url(&server.url);
+        let base_path = temp_dir("forge");
+        write_vanilla(&base_path, Some(download));
+        let installer_path = format!("{base_path}/forge-installer.jar");
+        write_installer(&installer_path, &sha1_bytes(PATCHED));
+        let tools_dir = temp_dir("forge-tools");
+
+        let installer = ForgeInstaller::new(&installer_path).with_java(&fake_java(&tools_dir));
+        let version = installer.install(LauncherCore::new(base_path.clone()), &downloader).unwrap();
+        assert_eq!(version.id, FORGE_ID);
+        assert_eq!(version.inheritance_chain, [FORGE_ID, "1.20.1"]);
+        assert!(Path::new(&format!("{base_path}/versions/{FORGE_ID}/{FORGE_ID}.json")).is_file());
+        assert!(!Path::new(&format!("{base_path}/versions/{FORGE_ID}/installer-data")).exists());
+
+        // 原版jar、版本json中的库和内嵌仓库中的库都已经准备好
+        let libraries = format!("{base_path}/libraries");
+        assert_eq!(fs::read(format!("{base_path}/versions/1.20.1/1.20.1.jar")).unwrap(), client);
+        assert_eq!(fs::read(format!("{libraries}/cpw/mods/bootstraplauncher/1.1.2/bootstraplauncher-1.1.2.jar")).unwrap(), b"bootstraplauncher");
+        let patched_path = format!("{libraries}/net/minecraftforge/forge/1.20.1-47.1.0/forge-1.20.1-47.1.0-client.jar");
+        assert_eq!(fs::read(&patched_path).unwrap(), PATCHED);
+
+        // 只运行了客户端的处理器，参数中的变量都已经展开
+        let args = fs::read_to_string(format!("{tools_dir}/java-args.txt")).unwrap();
+        let classpath = [
+            format!("{libraries}/net/minecraftforge/binarypatcher/1.1.1/binarypatcher-1.1.1.jar"),
+            format!("{libraries}/commons-io/commons-io/2.4/commons-io-2.4.jar"),
+        ].join(CLASSPATH_SEPARATOR);
+        let expected = [
+            "-cp".to_owned(),
+            classpath,
+            "net.minecraftforge.binarypatcher.ConsoleTool".to_owned(),
+            "--clean".to_owned(),
+            format!("{base_path}/versions/1.20.1/1.20.1.jar"),
+            "--output".to_owned(),
+            patched_path,
+            "--apply".to_owned(),
+            format!("{base_path}/versions/{FORGE_ID}/installer-data/data/client.lzma"),
+            "--mappings".to_owned(),
+            format!("{libraries}/de/oceanlabs/mcp/mcp_config/1.20.1-20230612.114412/mcp_config-1.20.1-20230612.114412-mappings.txt"),
+            "--side".to_owned(),
+            "client".to_owned(),
+        ];
+        assert_eq!(args.lines().collect::<Vec<&str>>(), expected);
+        assert_eq!(fs::read(format!("{tools_dir}/installer-data-copy")).unwrap(), b"binary patches");
+
+        // 输出已经正确时跳过处理器
+        fs::remove_file(format!("{tools_dir}/java-args.txt")).unwrap();
+        installer.install(LauncherCore::new(base_path), &downloader).unwrap();
+        assert!(!Path::new(&format!("{tools_dir}/java-args.txt")).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_output_hash_mismatch() {
+        let (server, download) = forge_server(b"vanilla client");
+        let downloader = Downloader::new().with_retries(0).with_base_url(&server.url);
+        let base_path = temp_dir("forge-hash-mismatch");
+        write_vanilla(&base_path, Some(download));
+        let installer_path = format!("{base_path}/forge-installer.jar");
+        write_installer(&installer_path, &sha1_bytes(b"expected"));
+        let tools_dir = temp_dir("forge-hash-mismatch-tools");
+
+        let result = ForgeInstaller::new(&installer_path).with_java(&fake_java(&tools_dir)).install(LauncherCore::new(base_path.clone()), &downloader);
+        match result {
+            Err(CoreError::HashMismatch { expected, actual, .. }) => {
+                assert_eq!(expected, sha1_bytes(b"expected"));
+                assert_eq!(actual, sha1_bytes(PATCHED));
+            }
+            Err(err) => panic!("unexpected error: {err:?}"),
+            Ok(_) => panic!("installed with a mismatched processor output"),
+        }
+        // 不留下无法启动的版本
+        assert!(!Path::new(&format!("{base_path}/versions/{FORGE_ID}")).exists());
+    }
+
+    #[test]
+    fn reports_missing_vanilla_jar() {
+        let (server, _) = forge_server(b"vanilla client");
+        let downloader = Downloader::new().with_retries(0).with_base_url(&server.url);
+        let base_path = temp_dir("forge-missing-jar");
+        write_vanilla(&base_path, None);
+        let installer_path = format!("{base_path}/forge-installer.jar");
+        write_installer(&installer_path, &sha1_bytes(PATCHED));
+
+        let result = ForgeInstaller::new(&installer_path).with_java("java").install(LauncherCore::new(base_path.clone()), &downloader);
+        match result {
+            Err(CoreError::MissingFiles { paths }) => assert_eq!(paths, [format!("{base_path}/versions/1.20.1/1.20.1.jar")]),
+            Err(err) => panic!("unexpected error: {err:?}"),
+            Ok(_) => panic!("installed without the vanilla jar"),
+        }
+        assert!(!Path::new(&format!("{base_path}/versions/{FORGE_ID}")).exists());
+    }
+
+    #[test]
+    fn installs_legacy_version_info() {
+        let server = MockServer::serve_files(HashMap::from([
+            ("/maven/org/scala-lang/scala-library/2.11.1/scala-library-2.11.1.jar".to_owned(), b"scala".to_vec()),
+        ]));
+        let downloader = Downloader::new().with_retries(0).with_base_url(&server.url);
+        let base_path = temp_dir("forge-legacy");
+        let mut vanilla = vanilla_version_json("1.7.10");
+        vanilla["minecraftArguments"] = json!("--username ${auth_player_name}");
+        vanilla["libraries"] = json!([{ "name": "com.google.guava:guava:15.0" }]);
+        write_version_json(&base_path, "1.7.10", &vanilla);
+        write_file(&format!("{base_path}/libraries/com/google/guava/guava/15.0/guava-15.0.jar"), "guava");
+
+        let id = "1.7.10-Forge10.13.4.1614-1.7.10";
+        let forge = "net.minecraftforge:forge:1.7.10-10.13.4.1614-1.7.10";
+        let mut version_info = vanilla_version_json(id);
+        version_info["minecraftArguments"] = json!("--username ${auth_player_name} --tweakClass cpw.mods.fml.common.launcher.FMLTweaker");
+        version_info["mainClass"] = json!("net.minecraft.launchwrapper.Launch");
+        version_info["libraries"] = json!([
+            { "name": forge, "url": "http://files.minecraftforge.net/maven/" },
+            { "name": "org.scala-lang:scala-library:2.11.1", "url": "http://files.minecraftforge.net/maven/", "clientreq": true, "serverreq": true },
+            { "name": "lzma:lzma:0.0.1", "clientreq": false, "serverreq": true },
+            { "name": "com.google.guava:guava:15.0" }
+        ]);
+        let profile = json!({
+            "install": {
+                "profileName": "Forge",
+                "target": id,
+                "path": forge,
+                "filePath": "forge-1.7.10-10.13.4.1614-1.7.10-universal.jar",
+                "minecraft": "1.7.10"
+            },
+            "versionInfo": version_info
+        });
+        let installer_path = format!("{base_path}/forge-installer.jar");
+        write_zip(&installer_path, &[
+            ("install_profile.json", profile.to_string().as_bytes()),
+            ("forge-1.7.10-10.13.4.1614-1.7.10-universal.jar", b"universal"),
+        ]);
+
+        let version = ForgeInstaller::new(&installer_path).install(LauncherCore::new(base_path.clone()), &downloader).unwrap();
+        assert_eq!(version.id, id);
+        assert_eq!(version.inheritance_chain, [id, "1.7.10"]);
+        assert_eq!(version.jar_path(), format!("{base_path}/versions/1.7.10/1.7.10.jar"));
+
+        let written: Value = serde_json::from_str(&fs::read_to_string(format!("{base_path}/versions/{id}/{id}.json")).unwrap()).unwrap();
+        assert_eq!(written["inheritsFrom"], "1.7.10");
+        assert_eq!(written["jar"], "1.7.10");
+        let libraries: Vec<&str> = written["libraries"].as_array().unwrap().iter().map(|lib| lib["name"].as_str().unwrap()).collect();
+        assert_eq!(libraries, [forge, "org.scala-lang:scala-library:2.11.1"]);
+
+        let libraries_path = format!("{base_path}/libraries");
+        assert_eq!(fs::read(format!("{libraries_path}/net/minecraftforge/forge/1.7.10-10.13.4.1614-1.7.10/forge-1.7.10-10.13.4.1614-1.7.10.jar")).unwrap(), b"universal");
+        assert_eq!(fs::read(format!("{libraries_path}/org/scala-lang/scala-library/2.11.1/scala-library-2.11.1.jar")).unwrap(), b"scala");
+        assert!(!Path::new(&format!("{libraries_path}/lzma/lzma/0.0.1/lzma-0.0.1.jar")).exists());
+        let classpath = version.get_classpath(&RuleEnvironment::current());
+        assert!(!classpath.to_string().contains("lzma"));
+    }
+
+    #[test]
+    fn names_neoforge_installers() {
+        // 1.20.1的NeoForge沿用Forge的名字
+        let legacy = ForgeInstaller::neoforge_installer_coordinate("1.20.1-47.1.79");
+        assert_eq!(legacy.to_string(), "net.neoforged:forge:1.20.1-47.1.79:installer");
+        assert_eq!(legacy.url(NEOFORGE_MAVEN_URL), "https://maven.neoforged.net/releases/net/neoforged/forge/1.20.1-47.1.79/forge-1.20.1-47.1.79-installer.jar");
+        let neoforge = ForgeInstaller::neoforge_installer_coordinate("20.4.80");
+        assert_eq!(neoforge.to_string(), "net.neoforged:neoforge:20.4.80:installer");
+        assert_eq!(neoforge.url(NEOFORGE_MAVEN_URL), "https://maven.neoforged.net/releases/net/neoforged/neoforge/20.4.80/neoforge-20.4.80-installer.jar");
+        assert_eq!(ForgeInstaller::installer_coordinate("1.20.1-47.1.0").to_string(), "net.minecraftforge:forge:1.20.1-47.1.0:installer");
+
+        let server = MockServer::serve_files(HashMap::from([
+            ("/releases/net/neoforged/neoforge/20.4.80/neoforge-20.4.80-installer.jar".to_owned(), b"neoforge".to_vec()),
+            ("/net/minecraftforge/forge/1.20.1-47.1.0/forge-1.20.1-47.1.0-installer.jar".to_owned(), b"forge".to_vec()),
+        ]));
+        let downloader = Downloader::new().with_retries(0).with_base_url(&server.url);
+        let dir = temp_dir("forge-download");
+        let installer = ForgeInstaller::download_neoforge("20.4.80", &format!("{dir}/neoforge.jar"), &downloader).unwrap();
+        assert_eq!(fs::read(&installer.installer_path).unwrap(), b"neoforge");
+        let installer = ForgeInstaller::download("1.20.1-47.1.0", &format!("{dir}/forge.jar"), &downloader).unwrap();
+        assert_eq!(fs::read(&installer.installer_path).unwrap(), b"forge");
+    }
+
+    #[test]
+    fn expands_processor_data() {
+        let mut runner = ProcessorRunner {
+            java_path: "java".to_owned(),
+            libraries_path: "/libraries".to_owned(),
+            data: HashMap::from([("SIDE".to_owned(), "client".to_owned()), ("ROOT".to_owned(), "/game".to_owned())]),
+            variable_regex: Regex::new(r"\{(\w+)\}").unwrap(),
+        };
+        assert_eq!(runner.expand("[net.minecraft:client:1.20.1-20230612.114412:slim]").unwrap(), "/libraries/net/minecraft/client/1.20.1-20230612.114412/client-1.20.1-20230612.114412-slim.jar");
+        assert_eq!(runner.expand("[de.oceanlabs.mcp:mcp_config:1.20.1@zip]").unwrap(), "/libraries/de/oceanlabs/mcp/mcp_config/1.20.1/mcp_config-1.20.1.zip");
+        assert_eq!(runner.expand("'{ROOT}'").unwrap(), "{ROOT}");
+        assert_eq!(runner.expand("{ROOT}/versions/{SIDE}").unwrap(), "/game/versions/client");
+        runner.data.remove("SIDE");
+        assert!(matches!(runner.expand("{SIDE}"), Err(CoreError::ProcessorFailed { message, .. }) if message == "Unknown variable SIDE"));
+    }
+}
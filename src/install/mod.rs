@@ -0,0 +1,6 @@
+pub mod version_manifest;
+pub mod version_profile;
+pub mod fabric_installer;
+pub mod quilt_installer;
+pub mod forge_installer;
+pub mod optifine_installer;
\ No newline at end of file
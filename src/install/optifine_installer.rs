@@ -0,0 +1,458 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use regex::Regex;
+use serde_json::{json, Value};
+
+use crate::download::downloader::Downloader;
+use crate::error::{CoreError, Result};
+use crate::game_version::GameVersion;
+use crate::launcher_core::LauncherCore;
+use crate::tools::file_tools::MavenCoordinate;
+
+use super::forge_installer::InstallerJar;
+use super::version_profile;
+
+/// OptiFine的Tweaker，通过launchwrapper加载
+const OPTIFINE_TWEAKER: &str = "optifine.OptiFineTweaker";
+const LAUNCHWRAPPER_MAIN_CLASS: &str = "net.minecraft.launchwrapper.Launch";
+/// OptiFine没有自带launchwrapper时使用Mojang的版本
+const LAUNCHWRAPPER_LIBRARY: &str = "net.minecraft:launchwrapper:1.12";
+/// 不同版本的OptiFine中保存版本信息的类
+const CONFIG_CLASSES: [&str; 3] = ["net/optifine/Config.class", "notch/net/optifine/Config.class", "Config.class"];
+
+/// 安装OptiFine
+///
+/// 可以作为独立的版本安装（运行OptiFine的`Patcher`生成库文件，通过launchwrapper启动），
+/// 也可以作为模组复制到Forge版本的`mods`文件夹
+pub struct OptiFineInstaller {
+    /// OptiFine jar的路径
+    pub jar_path: String,
+    /// OptiFine对应的游戏版本，例如`1.20.1`
+    pub minecraft_version: String,
+    /// OptiFine的版本，例如`HD_U_I5`
+    pub edition: String,
+    /// 运行`Patcher`使用的Java，为`None`时使用原版要求的Java
+    pub java_path: Option<String>,
+}
+
+impl OptiFineInstaller {
+    /// 打开OptiFine jar并识别它对应的游戏版本
+    ///
+    /// 依次读取`changelog.txt`、`Config`类中的常量以及文件名，都无法识别时返回错误
+    pub fn open(jar_path: &str) -> Result<Self> {
+        let mut jar = InstallerJar::open(jar_path)?;
+        let detected = jar.read_string("changelog.txt").ok().and_then(|changelog| parse_changelog(&changelog))
+            .or_else(|| {
+                CONFIG_CLASSES.iter()
+                    .filter_map(|name| jar.read_bytes(name).ok())
+                    .find_map(|class| parse_config_class(&class))
+            })
+            .or_else(|| Path::new(jar_path).file_name().and_then(|name| parse_changelog(&name.to_string_lossy())));
+        let (minecraft_version, edition) = detected.ok_or_else(|| CoreError::InvalidInstaller {
+            path: jar_path.to_owned(),
+            message: "Unable to detect the OptiFine version".to_owned(),
+        })?;
+        Ok(Self { jar_path: jar_path.to_owned(), minecraft_version, edition, java_path: None })
+    }
+
+    /// 指定运行`Patcher`使用的Java
+    pub fn with_java(mut self, java_path: &str) -> Self {
+        self.java_path = Some(java_path.to_owned());
+        self
+    }
+
+    /// 安装后的版本名字，例如`1.20.1-OptiFine_HD_U_I5`
+    pub fn version_id(&self) -> String {
+        format!("{}-OptiFine_{}", self.minecraft_version, self.edition)
+    }
+
+    /// OptiFine库的Maven坐标，例如`optifine:OptiFine:1.20.1_HD_U_I5`
+    pub fn library_coordinate(&self) -> MavenCoordinate {
+        MavenCoordinate::new("optifine", "OptiFine", &format!("{}_{}", self.minecraft_version, self.edition))
+    }
+
+    /// 检查OptiFine和`game_version`是否匹配
+    pub fn check_game_version(&self, game_version: &str) -> Result<()> {
+        if game_version != self.minecraft_version {
+            return Err(CoreError::IncompatibleGameVersion {
+                name: format!("OptiFine {}", self.edition),
+                required: self.minecraft_version.clone(),
+                actual: game_version.to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    /// 作为独立的版本安装
+    ///
+    /// # 参数
+    /// `core`: 安装到的启动核心，需要已经安装了OptiFine对应的原版
+    ///
+    /// OptiFine带有`Patcher`时使用`java_path`或者原版要求的Java运行它生成库文件，否则直接复制OptiFine jar。
+    /// 版本json继承原版，通过launchwrapper加载OptiFine的Tweaker。安装完成后返回加载好的版本
+    pub fn install(&self, core: LauncherCore, downloader: &Downloader) -> Result<GameVersion> {
+        let id = self.version_id();
+        version_profile::ensure_parent_installed(&core, &id, &self.minecraft_version)?;
+        let parent = GameVersion::load(core.clone(), self.minecraft_version.clone())?;
+        let mut jar = InstallerJar::open(&self.jar_path)?;
+
+        let libraries_path = format!("{}/libraries", core.assets_path);
+        let coordinate = self.library_coordinate();
+        let library_path = format!("{libraries_path}/{}", coordinate.path());
+        if let Some(parent_dir) = Path::new(&library_path).parent() {
+            fs::create_dir_all(parent_dir).map_err(|err| CoreError::io(parent_dir.to_string_lossy(), err))?;
+        }
+        if jar.contains("optifine/Patcher.class") {
+            self.run_patcher(&parent, &library_path)?;
+        } else {
+            fs::copy(&self.jar_path, &library_path).map_err(|err| CoreError::io(&library_path, err))?;
+        }
+
+        // 新版OptiFine自带了支持Java 9+的launchwrapper
+        let launchwrapper = match jar.read_string("launchwrapper-of.txt") {
+            Ok(version) => {
+                let launchwrapper = MavenCoordinate::new("optifine", "launchwrapper-of", version.trim());
+                jar.extract(&launchwrapper.file_name(), &format!("{libraries_path}/{}", launchwrapper.path()))?;
+                launchwrapper.to_string()
+            }
+            Err(_) => LAUNCHWRAPPER_LIBRARY.to_owned(),
+        };
+
+        let mut version_json = json!({
+            "id": id,
+            "inheritsFrom": self.minecraft_version,
+            "time": parent.version_json.time.map(|time| time.to_rfc3339()),
+            "releaseTime": parent.version_json.release_time.map(|time| time.to_rfc3339()),
+            "type": parent.version_json.type_.as_deref().unwrap_or("release"),
+            "mainClass": LAUNCHWRAPPER_MAIN_CLASS,
+            "libraries": [{ "name": coordinate.to_string() }, { "name": launchwrapper }],
+        });
+        // 旧版本的`minecraftArguments`会覆盖原版的参数，需要带上原版的参数
+        match &parent.version_json.arguments_old {
+            Some(arguments) if parent.version_json.arguments.is_none() => {
+                version_json["minecraftArguments"] = Value::String(format!("{arguments} --tweakClass {OPTIFINE_TWEAKER}"));
+            }
+            _ => version_json["arguments"] = json!({ "game": ["--tweakClass", OPTIFINE_TWEAKER] }),
+        }
+        version_profile::install_version_json(core, &id, version_json, downloader)
+    }
+
+    /// 作为模组安装到Forge版本的`mods`文件夹，返回复制后的路径
+    ///
+    /// `version`对应的游戏版本和OptiFine不一致时返回错误
+    pub fn install_as_mod(&self, version: &GameVersion) -> Result<String> {
+        let game_version = version.version_json.jar.clone()
+            .or_else(|| version.inheritance_chain.last().cloned())
+            .unwrap_or_else(|| version.id.clone());
+        self.check_game_version(&game_version)?;
+
+        let mods_dir = format!("{}/mods", version.game_directory());
+        fs::create_dir_all(&mods_dir).map_err(|err| CoreError::io(&mods_dir, err))?;
+        let file_name = Path::new(&self.jar_path).file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| format!("OptiFine_{}_{}.jar", self.minecraft_version, self.edition));
+        let target = format!("{mods_dir}/{file_name}");
+        fs::copy(&self.jar_path, &target).map_err(|err| CoreError::io(&target, err))?;
+        Ok(target)
+    }
+
+    /// 运行`optifine.Patcher`，用原版的jar和OptiFine生成库文件
+    fn run_patcher(&self, parent: &GameVersion, library_path: &str) -> Result<()> {
+        let java_path = match &self.java_path {
+            Some(java_path) => java_path.clone(),
+            None => parent.find_java()?.path,
+        };
+        let output = Command::new(&java_path)
+            .arg("-cp")
+            .arg(&self.jar_path)
+            .arg("optifine.Patcher")
+            .arg(parent.jar_path())
+            .arg(&self.jar_path)
+            .arg(library_path)
+            .output()
+            .map_err(|err| CoreError::io(&java_path, err))?;
+        if !output.status.success() || !Path::new(library_path).is_file() {
+            let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+            return Err(CoreError::ProcessorFailed { processor: "optifine.Patcher".to_owned(), message: format!("exited with {}\n{}", output.status, text.trim()) });
+        }
+        Ok(())
+    }
+}
+
+/// 从`changelog.txt`或者文件名中识别版本，例如`OptiFine 1.20.1_HD_U_I5`、`OptiFine_1.12.2_HD_U_G5.jar`
+fn parse_changelog(text: &str) -> Option<(String, String)> {
+    let regex = Regex::new(r"OptiFine[ _](\d+\.\d+(?:\.\d+)?)[ _](HD[ _]U[ _][A-Za-z0-9]+(?:[ _]pre\d*)?)").unwrap();
+    let captures = regex.captures(text)?;
+    Some((captures[1].to_owned(), captures[2].replace(' ', "_")))
+}
+
+/// 从`Config`类的常量`MC_VERSION`、`OF_EDITION`、`OF_RELEASE`中识别版本
+fn parse_config_class(class: &[u8]) -> Option<(String, String)> {
+    let strings = class_constant_strings(class)?;
+    let version_regex = Regex::new(r"^1\.\d+(?:\.\d+)?$").unwrap();
+    let release_regex = Regex::new(r"^[A-Z]\d+(?:_pre\d*)?$").unwrap();
+    let minecraft_version = strings.iter().find(|string| version_regex.is_match(string))?;
+    let edition = strings.iter().find(|string| string.starts_with("HD_U") || *string == "HD")?;
+    let release = strings.iter().find(|string| release_regex.is_match(string))?;
+    Some((minecraft_version.clone(), format!("{edition}_{release}")))
+}
+
+/// 读取class文件常量池中的所有字符串
+fn class_constant_strings(class: &[u8]) -> Option<Vec<String>> {
+    let u2 = |offset: usize| -> Option<usize> { Some(u16::from_be_bytes([*class.get(offset)?, *class.get(offset + 1)?]) as usize) };
+    if class.get(0..4)? != [0xCA, 0xFE, 0xBA, 0xBE] {
+        return None;
+    }
+    let count = u2(8)?;
+    let mut offset = 10;
+    let mut strings: Vec<String> = vec![];
+    let mut index = 1;
+    while index < count {
+        let tag = *class.get(offset)?;
+        offset += 1;
+        match tag {
+            1 => {
+                let length = u2(offset)?;
+                let bytes = class.get(offset + 2..offset + 2 + length)?;
+                strings.push(String::from_utf8_lossy(bytes).into_owned());
+                offset += 2 + length;
+            }
+            3 | 4 | 9 | 10 | 11 | 12 | 17 | 18 => offset += 4,
+            // long和double占用两个位置
+            5 | 6 => {
+                offset += 8;
+                index += 1;
+            }
+            7 | 8 | 16 | 19 | 20 => offset += 2,
+            15 => offset += 3,
+            _ => return None,
+        }
+        index += 1;
+    }
+    Some(strings)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::tools::test_tools::{temp_dir, vanilla_version_json, write_file, write_vanilla_version, write_version_json, write_zip, MockServer};
+
+    use super::*;
+
+    /// 常量池中的一项
+    enum Constant<'a> {
+        Utf8(&'a str),
+        /// 其他类型的标记和内容
+        Other(u8, &'a [u8]),
+    }
+
+    /// 只有常量池的class文件，long和double占用两个位置
+    fn class_file(constants: &[Constant]) -> Vec<u8> {
+        let mut pool: Vec<u8> = vec![];
+        let mut count = 1;
+        for constant in constants {
+            match constant {
+                Constant::Utf8(text) => {
+                    pool.push(1);
+                    pool.extend_from_slice(&(text.len() as u16).to_be_bytes());
+                    pool.extend_from_slice(text.as_bytes());
+                    count += 1;
+                }
+                Constant::Other(tag, bytes) => {
+                    pool.push(*tag);
+                    pool.extend_from_slice(bytes);
+                    count += if matches!(tag, 5 | 6) { 2 } else { 1 };
+                }
+            }
+        }
+        let mut class = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52];
+        class.extend_from_slice(&(count as u16).to_be_bytes());
+        class.extend_from_slice(&pool);
+        // access_flags、this_class等内容不会被读取
+        class.extend_from_slice(&[0, 0x21, 0, 2]);
+        class
+    }
+
+    /// 和OptiFine的`Config`类相似的常量池，版本信息夹在各种类型的常量之间
+    fn config_class() -> Vec<u8> {
+        class_file(&[
+            Constant::Other(7, &[0, 2]),
+            Constant::Utf8("net/optifine/Config"),
+            Constant::Other(3, &[0, 0, 0, 42]),
+            Constant::Other(5, &[0, 0, 0, 0, 0, 0, 0, 1]),
+            Constant::Utf8("OptiFine"),
+            Constant::Other(8, &[0, 5]),
+            Constant::Other(6, &[0x3F, 0xF0, 0, 0, 0, 0, 0, 0]),
+            Constant::Utf8("1.20.1"),
+            Constant::Other(10, &[0, 1, 0, 12]),
+            Constant::Other(12, &[0, 2, 0, 2]),
+            Constant::Other(15, &[6, 0, 11]),
+            Constant::Utf8("HD_U"),
+            Constant::Other(16, &[0, 2]),
+            Constant::Other(18, &[0, 0, 0, 12]),
+            Constant::Utf8("I5"),
+            Constant::Other(4, &[0x3F, 0x80, 0, 0]),
+            Constant::Other(19, &[0, 2]),
+        ])
+    }
+
+    #[test]
+    fn reads_class_constant_pool() {
+        let class = config_class();
+        let strings = class_constant_strings(&class).unwrap();
+        assert_eq!(strings, ["net/optifine/Config", "OptiFine", "1.20.1", "HD_U", "I5"]);
+        assert_eq!(parse_config_class(&class), Some(("1.20.1".to_owned(), "HD_U_I5".to_owned())));
+
+        // 截断、魔数错误以及未知的标记
+        assert_eq!(class_constant_strings(&class[..class.len() - 10]), None);
+        let mut bad_magic = class.clone();
+        bad_magic[0] = 0;
+        assert_eq!(class_constant_strings(&bad_magic), None);
+        assert_eq!(class_constant_strings(&class_file(&[Constant::Utf8("1.20.1"), Constant::Other(2, &[0, 0])])), None);
+        assert_eq!(parse_config_class(&class_file(&[Constant::Utf8("1.20.1"), Constant::Utf8("HD_U")])), None);
+    }
+
+    #[test]
+    fn parses_changelog_and_file_names() {
+        assert_eq!(parse_changelog("OptiFine 1.20.1_HD_U_I5\n\nChangelog"), Some(("1.20.1".to_owned(), "HD_U_I5".to_owned())));
+        assert_eq!(parse_changelog("OptiFine_1.12.2_HD_U_G5.jar"), Some(("1.12.2".to_owned(), "HD_U_G5".to_owned())));
+        assert_eq!(parse_changelog("preview_OptiFine_1.20.1_HD_U_I6_pre6.jar"), Some(("1.20.1".to_owned(), "HD_U_I6_pre6".to_owned())));
+        assert_eq!(parse_changelog("OptiFine 1.8.9 HD U M5"), Some(("1.8.9".to_owned(), "HD_U_M5".to_owned())));
+        assert_eq!(parse_changelog("sodium-fabric-0.5.3.jar"), None);
+    }
+
+    #[test]
+    fn detects_version_from_jar() {
+        let dir = temp_dir("optifine-detect");
+        let changelog = format!("{dir}/changelog.jar");
+        write_zip(&changelog, &[("changelog.txt", b"OptiFine 1.20.1_HD_U_I5\n")]);
+        let config = format!("{dir}/config.jar");
+        write_zip(&config, &[("net/optifine/Config.class", &config_class())]);
+        let file_name = format!("{dir}/OptiFine_1.12.2_HD_U_G5.jar");
+        write_zip(&file_name, &[("optifine/Installer.class", b"")]);
+        let unknown = format!("{dir}/unknown.jar");
+        write_zip(&unknown, &[("optifine/Installer.class", b"")]);
+
+        let installer = OptiFineInstaller::open(&changelog).unwrap();
+        assert_eq!((installer.minecraft_version.as_str(), installer.edition.as_str()), ("1.20.1", "HD_U_I5"));
+        assert_eq!(installer.version_id(), "1.20.1-OptiFine_HD_U_I5");
+        assert_eq!(installer.library_coordinate().to_string(), "optifine:OptiFine:1.20.1_HD_U_I5");
+        let installer = OptiFineInstaller::open(&config).unwrap();
+        assert_eq!((installer.minecraft_version.as_str(), installer.edition.as_str()), ("1.20.1", "HD_U_I5"));
+        let installer = OptiFineInstaller::open(&file_name).unwrap();
+        assert_eq!((installer.minecraft_version.as_str(), installer.edition.as_str()), ("1.12.2", "HD_U_G5"));
+        assert!(matches!(OptiFineInstaller::open(&unknown), Err(CoreError::InvalidInstaller { .. })));
+    }
+
+    #[test]
+    fn installs_as_mod_only_for_matching_version() {
+        let base_path = temp_dir("optifine-mod");
+        let jar_path = format!("{base_path}/OptiFine_1.20.1_HD_U_I5.jar");
+        write_zip(&jar_path, &[("changelog.txt", b"OptiFine 1.20.1_HD_U_I5\n")]);
+        write_vanilla_version(&base_path, "1.20.1");
+        write_vanilla_version(&base_path, "1.19.4");
+        for (id, parent) in [("1.20.1-forge-47.1.0", "1.20.1"), ("1.19.4-forge-45.1.0", "1.19.4")] {
+            let mut json = vanilla_version_json(id);
+            json["inheritsFrom"] = serde_json::json!(parent);
+            write_version_json(&base_path, id, &json);
+        }
+        let installer = OptiFineInstaller::open(&jar_path).unwrap();
+
+        let forge = GameVersion::load(LauncherCore::new(base_path.clone()), "1.19.4-forge-45.1.0".to_owned()).unwrap();
+        match installer.install_as_mod(&forge) {
+            Err(CoreError::IncompatibleGameVersion { required, actual, .. }) => assert_eq!((required.as_str(), actual.as_str()), ("1.20.1", "1.19.4")),
+            Err(err) => panic!("unexpected error: {err:?}"),
+            Ok(path) => panic!("installed into an incompatible version: {path}"),
+        }
+        assert!(!Path::new(&format!("{base_path}/mods")).exists());
+
+        let forge = GameVersion::load(LauncherCore::new(base_path.clone()), "1.20.1-forge-47.1.0".to_owned()).unwrap();
+        let target = installer.install_as_mod(&forge).unwrap();
+        assert_eq!(target, format!("{base_path}/mods/OptiFine_1.20.1_HD_U_I5.jar"));
+        assert_eq!(fs::read(target).unwrap(), fs::read(&jar_path).unwrap());
+    }
+
+    #[test]
+    fn installs_standalone_with_new_arguments() {
+        let base_path = temp_dir("optifine-standalone");
+        write_vanilla_version(&base_path, "1.20.1");
+        let jar_path = format!("{base_path}/OptiFine_1.20.1_HD_U_I5.jar");
+        write_zip(&jar_path, &[
+            ("changelog.txt", b"OptiFine 1.20.1_HD_U_I5\n"),
+            ("launchwrapper-of.txt", b"2.3\n"),
+            ("launchwrapper-of-2.3.jar", b"launchwrapper-of"),
+        ]);
+
+        let installer = OptiFineInstaller::open(&jar_path).unwrap();
+        let version = installer.install(LauncherCore::new(base_path.clone()), &Downloader::new().with_retries(0)).unwrap();
+        assert_eq!(version.id, "1.20.1-OptiFine_HD_U_I5");
+        assert_eq!(version.inheritance_chain, ["1.20.1-OptiFine_HD_U_I5", "1.20.1"]);
+
+        let written: Value = serde_json::from_str(&fs::read_to_string(format!("{0}/versions/{1}/{1}.json", base_path, version.id)).unwrap()).unwrap();
+        assert_eq!(written["mainClass"], LAUNCHWRAPPER_MAIN_CLASS);
+        assert_eq!(written["arguments"], json!({ "game": ["--tweakClass", OPTIFINE_TWEAKER] }));
+        assert_eq!(written.get("minecraftArguments"), None);
+        assert_eq!(written["libraries"], json!([{ "name": "optifine:OptiFine:1.20.1_HD_U_I5" }, { "name": "optifine:launchwrapper-of:2.3" }]));
+        // 没有Patcher时直接复制OptiFine
+        assert_eq!(fs::read(format!("{base_path}/libraries/optifine/OptiFine/1.20.1_HD_U_I5/OptiFine-1.20.1_HD_U_I5.jar")).unwrap(), fs::read(&jar_path).unwrap());
+        assert_eq!(fs::read(format!("{base_path}/libraries/optifine/launchwrapper-of/2.3/launchwrapper-of-2.3.jar")).unwrap(), b"launchwrapper-of");
+    }
+
+    #[test]
+    fn installs_standalone_with_legacy_arguments() {
+        let server = MockServer::serve_files(HashMap::from([
+            ("/net/minecraft/launchwrapper/1.12/launchwrapper-1.12.jar".to_owned(), b"launchwrapper".to_vec()),
+        ]));
+        let downloader = Downloader::new().with_retries(0).with_base_url(&server.url);
+        let base_path = temp_dir("optifine-legacy");
+        let mut vanilla = vanilla_version_json("1.12.2");
+        vanilla.as_object_mut().unwrap().remove("arguments");
+        vanilla["minecraftArguments"] = json!("--username ${auth_player_name} --version ${version_name}");
+        write_version_json(&base_path, "1.12.2", &vanilla);
+        write_file(&format!("{base_path}/versions/1.12.2/1.12.2.jar"), "client");
+        let jar_path = format!("{base_path}/OptiFine_1.12.2_HD_U_G5.jar");
+        write_zip(&jar_path, &[("changelog.txt", b"OptiFine 1.12.2_HD_U_G5\n")]);
+
+        let installer = OptiFineInstaller::open(&jar_path).unwrap();
+        let version = installer.install(LauncherCore::new(base_path.clone()), &downloader).unwrap();
+        let written: Value = serde_json::from_str(&fs::read_to_string(format!("{0}/versions/{1}/{1}.json", base_path, version.id)).unwrap()).unwrap();
+        // `minecraftArguments`会覆盖原版的参数，需要带上原版的参数
+        assert_eq!(written["minecraftArguments"], "--username ${auth_player_name} --version ${version_name} --tweakClass optifine.OptiFineTweaker");
+        assert_eq!(written.get("arguments"), None);
+        assert_eq!(written["libraries"][1]["name"], LAUNCHWRAPPER_LIBRARY);
+        assert_eq!(fs::read(format!("{base_path}/libraries/net/minecraft/launchwrapper/1.12/launchwrapper-1.12.jar")).unwrap(), b"launchwrapper");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_patcher() {
+        let base_path = temp_dir("optifine-patcher");
+        write_vanilla_version(&base_path, "1.20.1");
+        let jar_path = format!("{base_path}/OptiFine_1.20.1_HD_U_I5.jar");
+        write_zip(&jar_path, &[("changelog.txt", b"OptiFine 1.20.1_HD_U_I5\n"), ("optifine/Patcher.class", b""), ("launchwrapper-of.txt", b"2.3"), ("launchwrapper-of-2.3.jar", b"")]);
+        let tools_dir = temp_dir("optifine-patcher-tools");
+        // 记录参数，并把最后一个参数（输出的库文件）写为`patched`
+        let java = crate::tools::test_tools::write_script(&format!("{tools_dir}/java"), &format!(r#"printf '%s\n' "$@" > "{tools_dir}/java-args.txt"
+for arg in "$@"; do output="$arg"; done
+printf patched > "$output""#));
+
+        let installer = OptiFineInstaller::open(&jar_path).unwrap().with_java(&java);
+        installer.install(LauncherCore::new(base_path.clone()), &Downloader::new().with_retries(0)).unwrap();
+        let library_path = format!("{base_path}/libraries/optifine/OptiFine/1.20.1_HD_U_I5/OptiFine-1.20.1_HD_U_I5.jar");
+        assert_eq!(fs::read(&library_path).unwrap(), b"patched");
+        let args = fs::read_to_string(format!("{tools_dir}/java-args.txt")).unwrap();
+        let client_jar = format!("{base_path}/versions/1.20.1/1.20.1.jar");
+        assert_eq!(args.lines().collect::<Vec<&str>>(), ["-cp", jar_path.as_str(), "optifine.Patcher", client_jar.as_str(), jar_path.as_str(), library_path.as_str()]);
+
+        // Patcher没有生成库文件时安装失败，不写入版本json
+        let base_path = temp_dir("optifine-patcher-failed");
+        write_vanilla_version(&base_path, "1.20.1");
+        let java = crate::tools::test_tools::write_script(&format!("{tools_dir}/failing-java"), "exit 0");
+        let result = OptiFineInstaller::open(&jar_path).unwrap().with_java(&java).install(LauncherCore::new(base_path.clone()), &Downloader::new().with_retries(0));
+        assert!(matches!(result, Err(CoreError::ProcessorFailed { .. })));
+        assert!(!Path::new(&format!("{base_path}/versions/1.20.1-OptiFine_HD_U_I5/1.20.1-OptiFine_HD_U_I5.json")).exists());
+    }
+}
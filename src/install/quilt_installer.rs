@@ -0,0 +1,153 @@
+use serde_json::Value;
+
+use crate::download::downloader::Downloader;
+use crate::error::{CoreError, Result};
+use crate::game_version::GameVersion;
+use crate::json::json_loader_meta::{JsonIntermediaryVersion, JsonLoaderGameVersion, JsonLoaderVersion};
+use crate::launcher_core::LauncherCore;
+
+use super::version_profile;
+
+/// Quilt meta的地址
+pub const QUILT_META_URL: &str = "https://meta.quiltmc.org";
+/// Quilt的Maven仓库
+pub const QUILT_MAVEN_URL: &str = "https://maven.quiltmc.org/repository/release/";
+
+/// 安装Quilt加载器
+///
+/// 和Fabric相同，版本json由Quilt meta生成并继承原版，hashed映射和加载器从Quilt的Maven仓库下载
+pub struct QuiltInstaller {
+    /// Quilt meta的地址，可以改为本地的测试服务器或者镜像
+    pub meta_url: String,
+}
+
+impl Default for QuiltInstaller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuiltInstaller {
+    pub fn new() -> Self {
+        Self { meta_url: QUILT_META_URL.to_owned() }
+    }
+
+    pub fn with_meta_url(mut self, meta_url: &str) -> Self {
+        self.meta_url = meta_url.trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// Quilt支持的游戏版本，从新到旧
+    pub fn game_versions(&self, downloader: &Downloader) -> Result<Vec<JsonLoaderGameVersion>> {
+        downloader.get_json(&format!("{}/v3/versions/game", self.meta_url))
+    }
+
+    /// 所有的加载器版本，从新到旧
+    pub fn loader_versions(&self, downloader: &Downloader) -> Result<Vec<JsonLoaderVersion>> {
+        downloader.get_json(&format!("{}/v3/versions/loader", self.meta_url))
+    }
+
+    /// 所有的hashed映射版本，没有hashed映射的游戏版本无法安装Quilt
+    pub fn hashed_versions(&self, downloader: &Downloader) -> Result<Vec<JsonIntermediaryVersion>> {
+        downloader.get_json(&format!("{}/v3/versions/hashed", self.meta_url))
+    }
+
+    /// 安装后的默认版本名字，和官方安装器一致
+    pub fn version_id(game_version: &str, loader_version: &str) -> String {
+        format!("quilt-loader-{loader_version}-{game_version}")
+    }
+
+    /// 安装Quilt
+    ///
+    /// # 参数
+    /// `core`: 安装到的启动核心，需要已经安装了`game_version`对应的原版
+    ///
+    /// `game_version`: 游戏版本，例如`1.20.1`
+    ///
+    /// `loader_version`: 加载器版本，例如`0.19.2`
+    ///
+    /// 版本json会写入`versions/<id>/<id>.json`，然后下载缺失的库文件。安装完成后返回加载好的版本
+    pub fn install(&self, core: LauncherCore, game_version: &str, loader_version: &str, downloader: &Downloader) -> Result<GameVersion> {
+        let id = Self::version_id(game_version, loader_version);
+        version_profile::ensure_parent_installed(&core, &id, game_version)?;
+
+        let has_hashed = self.hashed_versions(downloader)?.iter().any(|version| version.version == game_version);
+        let has_loader = self.loader_versions(downloader)?.iter().any(|version| version.version == loader_version);
+        if !has_hashed || !has_loader {
+            return Err(CoreError::UnsupportedLoader {
+                loader: "Quilt".to_owned(),
+                loader_version: loader_version.to_owned(),
+                game_version: game_version.to_owned(),
+            });
+        }
+
+        let profile_url = format!("{}/v3/versions/loader/{game_version}/{loader_version}/profile/json", self.meta_url);
+        let mut profile: Value = downloader.get_json(&profile_url)?;
+        // 没有给出`url`的库在Quilt的仓库中，避免按照Mojang的仓库下载
+        let libraries = profile.get_mut("libraries").and_then(Value::as_array_mut).into_iter().flatten();
+        for library in libraries.filter_map(Value::as_object_mut) {
+            library.entry("url").or_insert_with(|| Value::String(QUILT_MAVEN_URL.to_owned()));
+        }
+        version_profile::install_version_json(core, &id, profile, downloader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::tools::test_tools::{assert_libraries_downloaded, fixture, loader_meta_server, temp_dir, write_vanilla_version, MockServer};
+
+    use super::*;
+
+    const PROFILE: &str = "quilt/profile-0.19.2-1.20.1.json";
+
+    /// 提供Quilt meta和Maven仓库的服务器，库文件的内容为它的名字
+    fn quilt_server() -> MockServer {
+        loader_meta_server(&[
+            ("/v3/versions/loader", "quilt/loader-versions.json"),
+            ("/v3/versions/hashed", "quilt/hashed-versions.json"),
+            ("/v3/versions/loader/1.20.1/0.19.2/profile/json", PROFILE),
+        ], PROFILE, QUILT_MAVEN_URL)
+    }
+
+    #[test]
+    fn installs_captured_profile() {
+        let server = quilt_server();
+        let downloader = Downloader::new().with_retries(0).with_base_url(&server.url);
+        let base_path = temp_dir("quilt");
+        write_vanilla_version(&base_path, "1.20.1");
+
+        let installer = QuiltInstaller::new().with_meta_url(&server.url);
+        let version = installer.install(LauncherCore::new(base_path.clone()), "1.20.1", "0.19.2", &downloader).unwrap();
+        assert_eq!(version.id, "quilt-loader-0.19.2-1.20.1");
+        assert_eq!(version.inheritance_chain, ["quilt-loader-0.19.2-1.20.1", "1.20.1"]);
+
+        let json_path = format!("{base_path}/versions/{0}/{0}.json", version.id);
+        let written: Value = serde_json::from_str(&fs::read_to_string(json_path).unwrap()).unwrap();
+        let profile: Value = serde_json::from_str(&fixture(PROFILE)).unwrap();
+        assert_eq!(written["id"], "quilt-loader-0.19.2-1.20.1");
+        assert_eq!(written["inheritsFrom"], "1.20.1");
+        assert_eq!(written["mainClass"], "org.quiltmc.loader.impl.launch.knot.KnotClient");
+        let libraries = written["libraries"].as_array().unwrap();
+        assert_eq!(libraries.len(), profile["libraries"].as_array().unwrap().len());
+        // 没有`url`的库使用Quilt的仓库
+        let json5 = libraries.iter().find(|lib| lib["name"] == "org.quiltmc:quilt-json5:1.0.3").unwrap();
+        assert_eq!(json5["url"], QUILT_MAVEN_URL);
+        assert_libraries_downloaded(&base_path, libraries);
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let server = quilt_server();
+        let downloader = Downloader::new().with_retries(0).with_base_url(&server.url);
+        let base_path = temp_dir("quilt-unsupported");
+        write_vanilla_version(&base_path, "1.20.1");
+
+        let installer = QuiltInstaller::new().with_meta_url(&server.url);
+        let result = installer.install(LauncherCore::new(base_path.clone()), "1.20.1", "0.18.0", &downloader);
+        assert!(matches!(result, Err(CoreError::UnsupportedLoader { .. })));
+        let result = installer.install(LauncherCore::new(base_path.clone()), "1.19.4", "0.19.2", &downloader);
+        assert!(matches!(result, Err(CoreError::MissingParentVersion { .. })));
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt;
+use std::fs;
+
+use crate::download::download_task::DownloadTask;
+use crate::download::downloader::Downloader;
+use crate::error::{CoreError, Result};
+use crate::game_assets::GameAssets;
+use crate::game_version::GameVersion;
+use crate::json::json_version_manifest::{JsonManifestVersion, JsonVersionManifest};
+use crate::launcher_core::LauncherCore;
+use crate::platform::rule_evaluator::RuleEnvironment;
+
+/// Mojang的版本列表
+pub const VERSION_MANIFEST_URL: &str = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+/// 版本类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VersionType {
+    Release,
+    Snapshot,
+    OldBeta,
+    OldAlpha,
+}
+
+impl VersionType {
+    pub const ALL: [VersionType; 4] = [VersionType::Release, VersionType::Snapshot, VersionType::OldBeta, VersionType::OldAlpha];
+
+    pub fn parse(type_: &str) -> Option<Self> {
+        match type_ {
+            "release" => Some(VersionType::Release),
+            "snapshot" => Some(VersionType::Snapshot),
+            "old_beta" => Some(VersionType::OldBeta),
+            "old_alpha" => Some(VersionType::OldAlpha),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VersionType::Release => "release",
+            VersionType::Snapshot => "snapshot",
+            VersionType::OldBeta => "old_beta",
+            VersionType::OldAlpha => "old_alpha",
+        }
+    }
+}
+
+impl fmt::Display for VersionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// 版本列表，用于获取可以安装的版本以及安装原版
+pub struct VersionManifest {
+    pub manifest: JsonVersionManifest,
+}
+
+impl VersionManifest {
+    /// 获取Mojang的版本列表
+    pub fn fetch(downloader: &Downloader) -> Result<Self> {
+        Self::fetch_from(VERSION_MANIFEST_URL, downloader)
+    }
+
+    /// 从指定的地址获取版本列表
+    ///
+    /// `manifest_url`也可以是本地文件的路径（或者`file://`地址），方便使用准备好的文件
+    pub fn fetch_from(manifest_url: &str, downloader: &Downloader) -> Result<Self> {
+        let local_path = match manifest_url.strip_prefix("file://") {
+            Some(path) => Some(path),
+            None if !manifest_url.contains("://") => Some(manifest_url),
+            None => None,
+        };
+        let manifest: JsonVersionManifest = match local_path {
+            Some(path) => {
+                let contents = fs::read_to_string(path).map_err(|err| CoreError::io(path, err))?;
+                serde_json::from_str(&contents).map_err(|err| CoreError::parse_json(path, err))?
+            }
+            None => downloader.get_json(manifest_url)?,
+        };
+        Ok(Self { manifest })
+    }
+
+    /// 获取指定类型的版本，`types`为空时返回所有版本，顺序和版本列表一致（从新到旧）
+    pub fn versions(&self, types: &[VersionType]) -> Vec<&JsonManifestVersion> {
+        self.manifest.versions.iter()
+            .filter(|version| types.is_empty() || VersionType::parse(&version.type_).is_some_and(|type_| types.contains(&type_)))
+            .collect()
+    }
+
+    pub fn find(&self, id: &str) -> Option<&JsonManifestVersion> {
+        self.manifest.versions.iter().find(|version| version.id == id)
+    }
+
+    pub fn latest_release(&self) -> Option<&JsonManifestVersion> {
+        self.find(&self.manifest.latest.release)
+    }
+
+    pub fn latest_snapshot(&self) -> Option<&JsonManifestVersion> {
+        self.find(&self.manifest.latest.snapshot)
+    }
+
+    /// 安装原版
+    ///
+    /// # 参数
+    /// `core`: 安装到的启动核心
+    ///
+    /// `id`: 版本名字，例如`1.20.1`
+    ///
+    /// 会下载版本json（校验版本列表中的sha1）到`versions/<id>/`，然后下载游戏本体、库文件和资源文件，
+    /// 已经存在并且完整的文件会跳过。安装完成后返回加载好的版本
+    pub fn install(&self, core: LauncherCore, id: &str, downloader: &Downloader) -> Result<GameVersion> {
+        let entry = self.find(id).ok_or_else(|| CoreError::UnknownVersion(id.to_owned()))?;
+        let json_path = format!("{}/versions/{id}/{id}.json", core.base_path);
+        let mut task = DownloadTask::new(&entry.url, &json_path);
+        if let Some(sha1) = &entry.sha1 {
+            task = task.with_sha1(sha1);
+        }
+        downloader.download(&task)?;
+
+        let version = GameVersion::load(core, id.to_owned())?;
+        version.download_missing_files(downloader, &RuleEnvironment::current())?;
+        GameAssets::load(&version, downloader)?.download_missing_objects(downloader)?;
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::{json, Value};
+
+    use crate::tools::hash_tools::sha1_bytes;
+    use crate::tools::test_tools::{fixture, temp_dir, vanilla_version_json, MockServer};
+
+    use super::*;
+
+    fn manifest() -> VersionManifest {
+        VersionManifest { manifest: serde_json::from_str(&fixture("version_manifest_v2.json")).unwrap() }
+    }
+
+    fn ids(versions: Vec<&JsonManifestVersion>) -> Vec<&str> {
+        versions.into_iter().map(|version| version.id.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_captured_manifest() {
+        let path = format!("{}/tests/fixtures/version_manifest_v2.json", env!("CARGO_MANIFEST_DIR"));
+        let manifest = VersionManifest::fetch_from(&path, &Downloader::new()).unwrap();
+        assert_eq!(manifest.latest_release().unwrap().id, "1.20.1");
+        assert_eq!(manifest.latest_snapshot().unwrap().id, "23w31a");
+        assert_eq!(ids(manifest.versions(&[])), ["23w31a", "1.20.1", "1.20", "b1.7.3", "rd-132211"]);
+        assert_eq!(ids(manifest.versions(&[VersionType::Release])), ["1.20.1", "1.20"]);
+        assert_eq!(ids(manifest.versions(&[VersionType::OldBeta, VersionType::OldAlpha])), ["b1.7.3", "rd-132211"]);
+
+        let entry = manifest.find("1.20.1").unwrap();
+        assert_eq!(entry.sha1.as_deref(), Some("715ccf3330885e75b205124f09f8712542cbe7e0"));
+        assert_eq!(entry.release_time.to_rfc3339(), "2023-06-12T13:25:51+00:00");
+        assert!(manifest.find("1.21").is_none());
+    }
+
+    #[test]
+    fn installs_vanilla_version() {
+        let client = b"client jar".to_vec();
+        let library = b"logging library".to_vec();
+        let asset = b"icon".to_vec();
+        let asset_hash = sha1_bytes(&asset);
+        let index = json!({ "objects": { "icons/icon_16x16.png": { "hash": asset_hash, "size": asset.len() } } }).to_string();
+        let mut version_json = vanilla_version_json("1.20.1");
+        version_json["arguments"] = json!({ "game": ["--username", "${auth_player_name}"], "jvm": ["-cp", "${classpath}"] });
+        version_json["assets"] = json!("5");
+        version_json["assetIndex"] = json!({
+            "id": "5",
+            "sha1": sha1_bytes(index.as_bytes()),
+            "size": index.len(),
+            "totalSize": asset.len(),
+            "url": "https://piston-meta.mojang.com/v1/packages/5.json"
+        });
+        version_json["downloads"] = json!({
+            "client": { "sha1": sha1_bytes(&client), "size": client.len(), "url": "https://piston-data.mojang.com/v1/objects/client.jar" }
+        });
+        version_json["libraries"] = json!([{
+            "name": "com.mojang:logging:1.1.1",
+            "downloads": {
+                "artifact": {
+                    "path": "com/mojang/logging/1.1.1/logging-1.1.1.jar",
+                    "sha1": sha1_bytes(&library),
+                    "size": library.len(),
+                    "url": "https://libraries.minecraft.net/com/mojang/logging/1.1.1/logging-1.1.1.jar"
+                }
+            }
+        }]);
+        let version_json = version_json.to_string();
+
+        let mut manifest = manifest();
+        let entry = manifest.manifest.versions.iter_mut().find(|version| version.id == "1.20.1").unwrap();
+        entry.sha1 = Some(sha1_bytes(version_json.as_bytes()));
+        let server = MockServer::serve_files(HashMap::from([
+            ("/v1/packages/715ccf3330885e75b205124f09f8712542cbe7e0/1.20.1.json".to_owned(), version_json.into_bytes()),
+            ("/v1/packages/5.json".to_owned(), index.into_bytes()),
+            ("/v1/objects/client.jar".to_owned(), client.clone()),
+            ("/com/mojang/logging/1.1.1/logging-1.1.1.jar".to_owned(), library.clone()),
+            (format!("/{}/{asset_hash}", &asset_hash[..2]), asset.clone()),
+        ]));
+        let downloader = Downloader::new().with_retries(0).with_base_url(&server.url);
+
+        let base_path = temp_dir("vanilla");
+        let assets_path = temp_dir("vanilla-assets");
+        let mut core = LauncherCore::new(base_path.clone());
+        core.set_assets_path(assets_path.clone());
+        let version = manifest.install(core, "1.20.1", &downloader).unwrap();
+        assert_eq!(version.id, "1.20.1");
+
+        let written: Value = serde_json::from_str(&fs::read_to_string(format!("{base_path}/versions/1.20.1/1.20.1.json")).unwrap()).unwrap();
+        assert_eq!(written["id"], "1.20.1");
+        assert_eq!(written["mainClass"], "net.minecraft.client.main.Main");
+        assert_eq!(written.get("inheritsFrom"), None);
+        assert_eq!(written["libraries"][0]["name"], "com.mojang:logging:1.1.1");
+        assert_eq!(fs::read(format!("{base_path}/versions/1.20.1/1.20.1.jar")).unwrap(), client);
+        assert_eq!(fs::read(format!("{assets_path}/libraries/com/mojang/logging/1.1.1/logging-1.1.1.jar")).unwrap(), library);
+        assert_eq!(fs::read(format!("{assets_path}/assets/objects/{}/{asset_hash}", &asset_hash[..2])).unwrap(), asset);
+
+        let result = manifest.install(LauncherCore::new(base_path), "1.21", &downloader);
+        assert!(matches!(result, Err(CoreError::UnknownVersion(id)) if id == "1.21"));
+    }
+}
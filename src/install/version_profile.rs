@@ -0,0 +1,57 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::download::downloader::Downloader;
+use crate::error::{CoreError, Result};
+use crate::game_version::GameVersion;
+use crate::launcher_core::LauncherCore;
+use crate::platform::rule_evaluator::RuleEnvironment;
+
+/// 检查`inheritsFrom`指向的版本已经安装，加载器需要先安装对应的原版
+pub fn ensure_parent_installed(core: &LauncherCore, id: &str, parent: &str) -> Result<()> {
+    let parent_json = format!("{}/versions/{parent}/{parent}.json", core.base_path);
+    if !Path::new(&parent_json).is_file() {
+        return Err(CoreError::MissingParentVersion { version: id.to_owned(), parent: parent.to_owned() });
+    }
+    Ok(())
+}
+
+/// 把版本json写入`versions/<id>/<id>.json`，`json`中的`id`会被改为`id`
+pub fn write_version_json(core: &LauncherCore, id: &str, json: &mut Value) -> Result<String> {
+    if let Some(object) = json.as_object_mut() {
+        object.insert("id".to_owned(), Value::String(id.to_owned()));
+    }
+    let version_dir = format!("{}/versions/{id}", core.base_path);
+    fs::create_dir_all(&version_dir).map_err(|err| CoreError::io(&version_dir, err))?;
+    let json_path = format!("{version_dir}/{id}.json");
+    let contents = serde_json::to_string_pretty(json).map_err(|err| CoreError::io(&json_path, err.into()))?;
+    fs::write(&json_path, contents).map_err(|err| CoreError::io(&json_path, err))?;
+    Ok(json_path)
+}
+
+/// 加载还没有写入的版本json，`json`中的`id`会被改为`id`
+///
+/// 用于在写入版本json之前下载文件、运行处理器，安装失败时`versions`中不会留下无法启动的版本
+pub fn load_version_json(core: LauncherCore, id: &str, json: &mut Value) -> Result<GameVersion> {
+    if let Some(object) = json.as_object_mut() {
+        object.insert("id".to_owned(), Value::String(id.to_owned()));
+    }
+    let json_path = format!("{}/versions/{id}/{id}.json", core.base_path);
+    let version_json = serde_json::from_value(json.clone()).map_err(|err| CoreError::parse_json(&json_path, err))?;
+    GameVersion::from_json(core, id.to_owned(), version_json)
+}
+
+/// 下载加载器的版本json需要的库文件，全部完成后再写入版本json并加载版本
+///
+/// # 参数
+/// `id`: 安装后的版本名字
+///
+/// `json`: 加载器提供的版本json，需要通过`inheritsFrom`继承原版
+pub fn install_version_json(core: LauncherCore, id: &str, mut json: Value, downloader: &Downloader) -> Result<GameVersion> {
+    let version = load_version_json(core.clone(), id, &mut json)?;
+    version.download_missing_files(downloader, &RuleEnvironment::current())?;
+    write_version_json(&core, id, &mut json)?;
+    GameVersion::load(core, id.to_owned())
+}
@@ -0,0 +1,435 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use crate::error::{CoreError, Result};
+use crate::game_version::GameVersion;
+use crate::json::json_version::JsonVersion;
+use crate::tools::file_tools::MavenCoordinate;
+
+/// 模组加载器（以及OptiFine等会修改游戏的组件）的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoaderKind {
+    Forge,
+    NeoForge,
+    Fabric,
+    Quilt,
+    OptiFine,
+    LiteLoader,
+}
+
+impl fmt::Display for LoaderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LoaderKind::Forge => "Forge",
+            LoaderKind::NeoForge => "NeoForge",
+            LoaderKind::Fabric => "Fabric",
+            LoaderKind::Quilt => "Quilt",
+            LoaderKind::OptiFine => "OptiFine",
+            LoaderKind::LiteLoader => "LiteLoader",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// 版本中安装的加载器
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModLoader {
+    pub kind: LoaderKind,
+    /// 加载器的版本，无法从库文件中得到时为`None`
+    pub version: Option<String>,
+}
+
+/// 版本无法启动的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionProblem {
+    /// 版本文件夹中没有`<id>.json`
+    MissingJson,
+    /// 版本json不是合法的json
+    InvalidJson(String),
+    /// 游戏本体jar不存在
+    MissingJar { path: String },
+    /// `inheritsFrom`指向的版本不存在
+    MissingParent { parent: String },
+    /// `inheritsFrom`出现循环继承
+    InheritanceCycle { chain: Vec<String> },
+}
+
+impl fmt::Display for VersionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionProblem::MissingJson => write!(f, "Version json not found"),
+            VersionProblem::InvalidJson(message) => write!(f, "Invalid version json: {message}"),
+            VersionProblem::MissingJar { path } => write!(f, "Game jar not found: {path}"),
+            VersionProblem::MissingParent { parent } => write!(f, "Parent version {parent} not found"),
+            VersionProblem::InheritanceCycle { chain } => write!(f, "Inheritance cycle detected: {}", chain.join(" -> ")),
+        }
+    }
+}
+
+/// `versions`文件夹中的一个版本
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstalledVersion {
+    /// 版本文件夹的名字
+    pub id: String,
+    pub path: String,
+    /// 底层的游戏版本，例如Forge版本`1.20.1-forge-47.1.0`的`1.20.1`
+    pub game_version: Option<String>,
+    /// 安装的加载器，为空时是原版
+    pub loaders: Vec<ModLoader>,
+    /// 版本json中的`type`，例如`release`、`snapshot`
+    pub type_: Option<String>,
+    pub release_time: Option<DateTime<Utc>>,
+    /// 版本的继承链，从当前版本开始，依次为它的父版本
+    pub inheritance_chain: Vec<String>,
+    /// 版本无法启动的原因，可以启动时为`None`
+    pub problem: Option<VersionProblem>,
+}
+
+impl InstalledVersion {
+    pub fn is_vanilla(&self) -> bool {
+        self.loaders.is_empty()
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.problem.is_some()
+    }
+
+    pub fn has_loader(&self, kind: LoaderKind) -> bool {
+        self.loaders.iter().any(|loader| loader.kind == kind)
+    }
+}
+
+/// 扫描`<base_path>/versions`中的所有版本
+///
+/// 版本json和`GameVersion::load`使用相同的方式读取和合并，无法加载的版本也会出现在结果中，
+/// 并在`problem`中给出原因。结果按照发布时间从新到旧排序，没有发布时间的版本排在最后
+pub fn scan_versions(base_path: &str) -> Result<Vec<InstalledVersion>> {
+    let versions_dir = format!("{base_path}/versions");
+    let entries = match fs::read_dir(&versions_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(CoreError::io(&versions_dir, err)),
+    };
+    let mut ids: Vec<String> = vec![];
+    for entry in entries {
+        let entry = entry.map_err(|err| CoreError::io(&versions_dir, err))?;
+        if entry.path().is_dir() {
+            ids.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+
+    let mut versions: Vec<InstalledVersion> = ids.iter().map(|id| scan_version(base_path, id)).collect();
+    versions.sort_by(|a, b| b.release_time.cmp(&a.release_time).then_with(|| a.id.cmp(&b.id)));
+    Ok(versions)
+}
+
+/// 读取一个版本，无法加载时记录原因
+fn scan_version(base_path: &str, id: &str) -> InstalledVersion {
+    let mut version = InstalledVersion {
+        id: id.to_owned(),
+        path: format!("{base_path}/versions/{id}"),
+        game_version: None,
+        loaders: vec![],
+        type_: None,
+        release_time: None,
+        inheritance_chain: vec![],
+        problem: None,
+    };
+
+    let mut chain: Vec<String> = vec![];
+    let (json, root) = match GameVersion::resolve_json(base_path, id, &mut chain) {
+        Ok(json) => {
+            let root = (chain.len() > 1).then(|| chain.last().cloned()).flatten();
+            version.inheritance_chain = chain;
+            (json, root)
+        }
+        Err(err) => {
+            let problem = match err {
+                CoreError::MissingVersionJson { .. } if chain.len() == 1 => VersionProblem::MissingJson,
+                CoreError::MissingVersionJson { path } => VersionProblem::MissingParent { parent: chain.last().cloned().unwrap_or(path) },
+                CoreError::MissingParentVersion { parent, .. } => VersionProblem::MissingParent { parent },
+                CoreError::InheritanceCycle { chain } => VersionProblem::InheritanceCycle { chain },
+                CoreError::ParseJson { message, .. } if chain.len() == 1 => VersionProblem::InvalidJson(message),
+                CoreError::ParseJson { message, .. } => VersionProblem::InvalidJson(format!("{}: {message}", chain.last().cloned().unwrap_or_default())),
+                err => VersionProblem::InvalidJson(err.to_string()),
+            };
+            // 出错的版本不算在继承链中
+            if !matches!(problem, VersionProblem::InheritanceCycle { .. }) {
+                chain.pop();
+            }
+            version.inheritance_chain = chain;
+            // 父版本有问题时仍然读取版本自己的json，用于识别加载器
+            let root = match &problem {
+                VersionProblem::MissingParent { parent } => Some(parent.clone()),
+                VersionProblem::InheritanceCycle { .. } => None,
+                _ => {
+                    version.problem = Some(problem);
+                    return version;
+                }
+            };
+            let own_json = GameVersion::read_json(base_path, id);
+            version.problem = Some(problem);
+            match own_json {
+                Ok(json) => (json, root),
+                Err(_) => return version,
+            }
+        }
+    };
+
+    version.type_ = json.type_.clone();
+    version.release_time = json.release_time;
+    let libraries: Vec<String> = json.libraries.iter().flatten().map(|lib| lib.name.clone()).collect();
+    let game_version = resolve_game_version(&json, root.as_deref(), &libraries, id);
+    version.loaders = detect_loaders(&json.main_class, &libraries, game_version.as_deref());
+    // 循环继承时无法确定游戏版本
+    if !matches!(version.problem, Some(VersionProblem::InheritanceCycle { .. })) {
+        version.game_version = game_version;
+    }
+
+    // 时间等字段缺失时仍然可以启动，但整个继承链都没有主类时无法启动
+    if version.problem.is_none() && json.main_class.is_empty() {
+        version.problem = Some(VersionProblem::InvalidJson("missing field `mainClass`".to_owned()));
+    }
+    // 继承链完整时检查游戏本体，和`GameVersion::jar_path`使用相同的规则
+    if version.problem.is_none() {
+        let jar = json.jar.clone().unwrap_or_else(|| id.to_owned());
+        let jar_path = format!("{base_path}/versions/{jar}/{jar}.jar");
+        if !Path::new(&jar_path).is_file() {
+            version.problem = Some(VersionProblem::MissingJar { path: jar_path });
+        }
+    }
+    version
+}
+
+/// 获取底层的游戏版本
+///
+/// 依次使用：HMCL等启动器写入的`patches`中`game`的版本、继承链最顶层的版本（`root`）、
+/// `jar`字段以及合并安装的版本中`intermediary`等库的版本
+fn resolve_game_version(json: &JsonVersion, root: Option<&str>, libraries: &[String], id: &str) -> Option<String> {
+    let patch_version = json.patches.iter().flatten()
+        .find(|patch| patch.id == "game")
+        .and_then(|patch| patch.version.clone());
+    if patch_version.is_some() {
+        return patch_version;
+    }
+    if let Some(root) = root {
+        return Some(root.to_owned());
+    }
+    if let Some(jar) = &json.jar {
+        return Some(jar.clone());
+    }
+    let library_version = libraries.iter()
+        .filter_map(|name| name.parse::<MavenCoordinate>().ok())
+        .find_map(|coordinate| match (coordinate.group_id.as_str(), coordinate.artifact_id.as_str()) {
+            ("net.fabricmc", "intermediary") | ("org.quiltmc", "hashed") => Some(coordinate.version),
+            ("net.minecraftforge", "forge") => coordinate.version.split_once('-').map(|(game, _)| game.to_owned()),
+            _ => None,
+        });
+    library_version.or_else(|| Some(id.to_owned()))
+}
+
+/// 根据主类和库文件判断安装了哪些加载器
+fn detect_loaders(main_class: &str, libraries: &[String], game_version: Option<&str>) -> Vec<ModLoader> {
+    let mut loaders: Vec<ModLoader> = vec![];
+    let mut add = |kind: LoaderKind, version: Option<String>| {
+        match loaders.iter_mut().find(|loader| loader.kind == kind) {
+            Some(loader) => loader.version = loader.version.take().or(version),
+            None => loaders.push(ModLoader { kind, version }),
+        }
+    };
+    for coordinate in libraries.iter().filter_map(|name| name.parse::<MavenCoordinate>().ok()) {
+        let version = coordinate.version.clone();
+        match (coordinate.group_id.as_str(), coordinate.artifact_id.as_str()) {
+            ("net.neoforged", "neoforge") => add(LoaderKind::NeoForge, Some(version)),
+            ("net.neoforged", "forge") => add(LoaderKind::NeoForge, Some(strip_game_version(&version, game_version))),
+            ("net.neoforged.fancymodloader", _) | ("net.neoforged", "fancymodloader") => add(LoaderKind::NeoForge, None),
+            ("net.minecraftforge", "forge") => add(LoaderKind::Forge, Some(strip_game_version(&version, game_version))),
+            ("net.minecraftforge", "fmlloader") => add(LoaderKind::Forge, Some(strip_game_version(&version, game_version))),
+            ("net.fabricmc", "fabric-loader") => add(LoaderKind::Fabric, Some(version)),
+            ("org.quiltmc", "quilt-loader") => add(LoaderKind::Quilt, Some(version)),
+            ("optifine", "OptiFine") => add(LoaderKind::OptiFine, Some(strip_game_version(&version, game_version))),
+            ("com.mumfrey", "liteloader") => add(LoaderKind::LiteLoader, Some(version)),
+            _ => { }
+        }
+    }
+    // NeoForge的早期版本依然依赖Forge的库
+    if loaders.iter().any(|loader| loader.kind == LoaderKind::NeoForge) {
+        loaders.retain(|loader| loader.kind != LoaderKind::Forge);
+    }
+
+    // 库文件没有提供时根据主类判断
+    let main_class_kind = match main_class {
+        "net.fabricmc.loader.impl.launch.knot.KnotClient" | "net.fabricmc.loader.launch.knot.KnotClient" => Some(LoaderKind::Fabric),
+        "org.quiltmc.loader.impl.launch.knot.KnotClient" => Some(LoaderKind::Quilt),
+        "net.minecraftforge.bootstrap.ForgeBootstrap" => Some(LoaderKind::Forge),
+        "cpw.mods.bootstraplauncher.BootstrapLauncher" | "cpw.mods.modlauncher.Launcher" => {
+            let has_neoforge = loaders.iter().any(|loader| loader.kind == LoaderKind::NeoForge);
+            Some(if has_neoforge { LoaderKind::NeoForge } else { LoaderKind::Forge })
+        }
+        _ => None,
+    };
+    if let Some(kind) = main_class_kind {
+        if !loaders.iter().any(|loader| loader.kind == kind) {
+            loaders.insert(0, ModLoader { kind, version: None });
+        }
+    }
+    loaders
+}
+
+/// 去掉加载器版本中的游戏版本，例如`1.20.1-47.1.0`变为`47.1.0`
+fn strip_game_version(version: &str, game_version: Option<&str>) -> String {
+    game_version
+        .and_then(|game_version| version.strip_prefix(game_version))
+        .and_then(|rest| rest.strip_prefix('-').or_else(|| rest.strip_prefix('_')))
+        .unwrap_or(version)
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::tools::test_tools::{temp_dir, vanilla_version_json, write_file, write_vanilla_version, write_version_json};
+
+    use super::*;
+
+    fn write_version(base_path: &str, id: &str, json: Value) {
+        write_version_json(base_path, id, &json);
+    }
+
+    fn vanilla(id: &str, release_time: &str) -> Value {
+        let mut json = vanilla_version_json(id);
+        json["time"] = json!(release_time);
+        json["releaseTime"] = json!(release_time);
+        json
+    }
+
+    fn find<'a>(versions: &'a [InstalledVersion], id: &str) -> &'a InstalledVersion {
+        versions.iter().find(|version| version.id == id).unwrap()
+    }
+
+    #[test]
+    fn scans_good_and_broken_versions() {
+        let base_path = temp_dir("scan-versions");
+        write_vanilla_version(&base_path, "1.20.1");
+        write_version(&base_path, "fabric-loader-0.14.21-1.20.1", json!({
+            "id": "fabric-loader-0.14.21-1.20.1",
+            "inheritsFrom": "1.20.1",
+            "time": "2023-06-14T12:00:00+0000",
+            "releaseTime": "2023-06-14T12:00:00+0000",
+            "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+            "libraries": [{ "name": "net.fabricmc:intermediary:1.20.1" }, { "name": "net.fabricmc:fabric-loader:0.14.21" }]
+        }));
+        write_version(&base_path, "1.19.4-forge-45.1.0", json!({
+            "id": "1.19.4-forge-45.1.0",
+            "inheritsFrom": "1.19.4",
+            "time": "2023-05-01T00:00:00+00:00",
+            "releaseTime": "2023-05-01T00:00:00+00:00",
+            "mainClass": "cpw.mods.bootstraplauncher.BootstrapLauncher",
+            "libraries": [{ "name": "net.minecraftforge:forge:1.19.4-45.1.0" }]
+        }));
+        write_file(&format!("{base_path}/versions/broken/broken.json"), "{ \"id\": \"broken\", ");
+        write_version(&base_path, "1.18.2", vanilla("1.18.2", "2022-02-28T10:42:45+00:00"));
+        fs::create_dir_all(format!("{base_path}/versions/empty")).unwrap();
+
+        let versions = scan_versions(&base_path).unwrap();
+        let ids: Vec<&str> = versions.iter().map(|version| version.id.as_str()).collect();
+        assert_eq!(ids, ["fabric-loader-0.14.21-1.20.1", "1.20.1", "1.19.4-forge-45.1.0", "1.18.2", "broken", "empty"]);
+
+        let vanilla = find(&versions, "1.20.1");
+        assert!(vanilla.is_vanilla() && !vanilla.is_broken());
+        assert_eq!(vanilla.game_version.as_deref(), Some("1.20.1"));
+        assert_eq!(vanilla.type_.as_deref(), Some("release"));
+
+        let fabric = find(&versions, "fabric-loader-0.14.21-1.20.1");
+        assert_eq!(fabric.problem, None);
+        assert_eq!(fabric.inheritance_chain, ["fabric-loader-0.14.21-1.20.1", "1.20.1"]);
+        assert_eq!(fabric.game_version.as_deref(), Some("1.20.1"));
+        assert_eq!(fabric.loaders, [ModLoader { kind: LoaderKind::Fabric, version: Some("0.14.21".to_owned()) }]);
+        assert_eq!(fabric.type_.as_deref(), Some("release"));
+        assert_eq!(fabric.release_time.unwrap().to_rfc3339(), "2023-06-14T12:00:00+00:00");
+
+        let forge = find(&versions, "1.19.4-forge-45.1.0");
+        assert_eq!(forge.problem, Some(VersionProblem::MissingParent { parent: "1.19.4".to_owned() }));
+        assert_eq!(forge.inheritance_chain, ["1.19.4-forge-45.1.0"]);
+        assert_eq!(forge.game_version.as_deref(), Some("1.19.4"));
+        assert_eq!(forge.loaders, [ModLoader { kind: LoaderKind::Forge, version: Some("45.1.0".to_owned()) }]);
+
+        let broken = find(&versions, "broken");
+        assert!(matches!(broken.problem, Some(VersionProblem::InvalidJson(_))));
+        assert!(broken.inheritance_chain.is_empty());
+        assert_eq!(find(&versions, "empty").problem, Some(VersionProblem::MissingJson));
+        assert_eq!(
+            find(&versions, "1.18.2").problem,
+            Some(VersionProblem::MissingJar { path: format!("{base_path}/versions/1.18.2/1.18.2.jar") })
+        );
+    }
+
+    #[test]
+    fn reports_inheritance_cycle() {
+        let base_path = temp_dir("scan-versions-cycle");
+        let mut a = vanilla("a", "2023-01-01T00:00:00+00:00");
+        a["inheritsFrom"] = json!("b");
+        let mut b = vanilla("b", "2023-01-01T00:00:00+00:00");
+        b["inheritsFrom"] = json!("a");
+        write_version(&base_path, "a", a);
+        write_version(&base_path, "b", b);
+
+        let versions = scan_versions(&base_path).unwrap();
+        let a = find(&versions, "a");
+        assert_eq!(a.problem, Some(VersionProblem::InheritanceCycle { chain: vec!["a".to_owned(), "b".to_owned(), "a".to_owned()] }));
+        assert_eq!(a.game_version, None);
+    }
+
+    #[test]
+    fn reads_game_version_from_patches() {
+        let base_path = temp_dir("scan-versions-patches");
+        let mut json = vanilla_version_json("My Pack");
+        let mut game = vanilla_version_json("game");
+        game["version"] = json!("1.20.1");
+        json["patches"] = json!([game]);
+        write_version(&base_path, "My Pack", json);
+
+        let versions = scan_versions(&base_path).unwrap();
+        assert_eq!(versions[0].game_version.as_deref(), Some("1.20.1"));
+    }
+
+    #[test]
+    fn reads_versions_without_optional_fields() {
+        let base_path = temp_dir("scan-versions-lenient");
+        let mut json = vanilla_version_json("1.20.1");
+        json.as_object_mut().unwrap().remove("releaseTime");
+        write_version(&base_path, "1.20.1", json);
+        write_file(&format!("{base_path}/versions/1.20.1/1.20.1.jar"), "jar");
+        // 手动编辑的版本，没有时间和主类，使用父版本的
+        write_version(&base_path, "edited", json!({
+            "id": "edited",
+            "inheritsFrom": "1.20.1",
+            "libraries": [{ "name": "net.fabricmc:fabric-loader:0.14.21" }]
+        }));
+        write_version(&base_path, "no-main-class", json!({ "id": "no-main-class" }));
+        write_file(&format!("{base_path}/versions/no-main-class/no-main-class.jar"), "jar");
+
+        let versions = scan_versions(&base_path).unwrap();
+        let vanilla = find(&versions, "1.20.1");
+        assert_eq!(vanilla.problem, None);
+        assert_eq!(vanilla.release_time, None);
+        assert_eq!(vanilla.type_.as_deref(), Some("release"));
+
+        let edited = find(&versions, "edited");
+        assert_eq!(edited.problem, None);
+        assert_eq!(edited.release_time, None);
+        assert_eq!(edited.game_version.as_deref(), Some("1.20.1"));
+        assert_eq!(edited.loaders, [ModLoader { kind: LoaderKind::Fabric, version: Some("0.14.21".to_owned()) }]);
+
+        assert_eq!(find(&versions, "no-main-class").problem, Some(VersionProblem::InvalidJson("missing field `mainClass`".to_owned())));
+    }
+
+    #[test]
+    fn returns_empty_list_without_versions_directory() {
+        assert!(scan_versions(&temp_dir("scan-versions-empty")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// `assets/indexes/<id>.json`的内容
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonAssets {
+    #[serde(default)]
+    pub objects: HashMap<String, JsonAssetObject>,
+    /// 1.6 ~ 1.7之前的版本，资源需要按名字复制到`assets/virtual/<id>`
+    #[serde(default, rename = "virtual")]
+    pub virtual_: bool,
+    /// 1.6之前的版本，资源需要按名字复制到游戏目录的`resources`
+    #[serde(default)]
+    pub map_to_resources: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonAssetObject {
+    pub hash: String,
+    pub size: u64,
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::json_version::JsonLibrary;
+
+/// 1.13及以后的Forge安装器中的`install_profile.json`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonForgeInstallProfile {
+    #[serde(default)]
+    pub spec: u32,
+    /// `forge`或者`NeoForge`
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// 安装后的版本名字，例如`1.20.1-forge-47.1.0`
+    pub version: String,
+    /// 对应的原版
+    pub minecraft: String,
+    /// 安装器中版本json的路径，一般为`/version.json`
+    #[serde(default)]
+    pub json: Option<String>,
+    /// 处理器参数中使用的变量
+    #[serde(default)]
+    pub data: HashMap<String, JsonForgeData>,
+    #[serde(default)]
+    pub processors: Vec<JsonForgeProcessor>,
+    /// 处理器需要的库文件
+    #[serde(default)]
+    pub libraries: Vec<JsonLibrary>,
+}
+
+/// 变量在客户端和服务端的值
+///
+/// 值为`[maven坐标]`时表示库文件的路径，`'文本'`表示文本，`/`开头表示安装器中的文件
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonForgeData {
+    pub client: String,
+    pub server: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonForgeProcessor {
+    /// 处理器jar的Maven坐标
+    pub jar: String,
+    #[serde(default)]
+    pub classpath: Vec<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// 输出文件以及它们的SHA-1，可以使用变量
+    #[serde(default)]
+    pub outputs: HashMap<String, String>,
+    /// 处理器只在这些端运行，为`None`时两端都运行
+    #[serde(default)]
+    pub sides: Option<Vec<String>>,
+}
+
+/// 1.12.2及以前的Forge安装器中的`install_profile.json`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonLegacyForgeInstallProfile {
+    pub install: JsonLegacyForgeInstall,
+    /// 完整的版本json，1.7.10等旧版本没有`inheritsFrom`
+    #[serde(rename = "versionInfo")]
+    pub version_info: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonLegacyForgeInstall {
+    /// 安装后的版本名字，例如`1.12.2-forge1.12.2-14.23.5.2838`
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Forge本体的Maven坐标
+    pub path: String,
+    /// 安装器中Forge本体（universal）的文件名
+    #[serde(rename = "filePath")]
+    pub file_path: String,
+    pub minecraft: String,
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// Fabric meta（以及结构相同的Quilt meta）中的游戏版本
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonLoaderGameVersion {
+    pub version: String,
+    #[serde(default)]
+    pub stable: bool,
+}
+
+/// 加载器的版本
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonLoaderVersion {
+    #[serde(default)]
+    pub separator: Option<String>,
+    #[serde(default)]
+    pub build: Option<u32>,
+    /// 加载器的Maven坐标
+    pub maven: String,
+    pub version: String,
+    /// Quilt meta没有这个字段
+    #[serde(default)]
+    pub stable: bool,
+}
+
+/// intermediary（Quilt为hashed）映射的版本，版本号和游戏版本相同
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonIntermediaryVersion {
+    pub maven: String,
+    pub version: String,
+    #[serde(default)]
+    pub stable: bool,
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::json_version::JsonDownload;
+
+/// Java运行时的总清单`all.json`，结构为`平台 -> 组件 -> 版本列表`
+pub type JsonRuntimeAll = HashMap<String, HashMap<String, Vec<JsonRuntimeEntry>>>;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonRuntimeEntry {
+    pub manifest: JsonDownload,
+    pub version: JsonRuntimeVersion,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonRuntimeVersion {
+    pub name: String,
+    #[serde(default)]
+    pub released: Option<String>,
+}
+
+/// 单个组件的文件清单
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonRuntimeManifest {
+    pub files: HashMap<String, JsonRuntimeFile>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonRuntimeFile {
+    /// `file`、`directory`或者`link`
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(default)]
+    pub executable: bool,
+    /// `raw`和`lzma`两种下载方式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<HashMap<String, JsonDownload>>,
+    /// 链接指向的路径
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
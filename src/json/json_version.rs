@@ -1,18 +1,23 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct JsonVersion {
     #[serde(rename = "id")]
     pub id: String,
-    #[serde(with = "serde_date_format")]
-    pub time: DateTime<Utc>,
+    /// 手动编辑或者其他启动器生成的json可能没有时间
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "serde_date_format")]
+    pub time: Option<DateTime<Utc>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub patches: Option<Vec<JsonVersion>>,
-    #[serde(rename = "releaseTime", with = "serde_date_format")]
-    pub release_time: DateTime<Utc>,
+    /// `patches`中组件的版本，例如`game`的`1.20.1`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "serde_date_format")]
+    #[serde(rename = "releaseTime")]
+    pub release_time: Option<DateTime<Utc>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     #[serde(rename = "type")]
     pub type_: Option<String>,
@@ -32,33 +37,56 @@ pub struct JsonVersion {
     pub jar: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub assets: Option<String>,
-    #[serde(rename = "mainClass")]
+    /// 继承的版本可以不写，使用父版本的主类
+    #[serde(default, rename = "mainClass")]
     pub main_class: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub downloads: Option<HashMap<String, JsonDownload>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<HashMap<String, JsonLogging>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "javaVersion")]
+    pub java_version: Option<JsonJavaVersion>,
 }
 
 
 impl JsonVersion {
+    /// 将当前版本（子版本）合并到`json`（父版本）中
+    ///
+    /// 子版本存在的字段覆盖父版本，不存在的字段保留父版本的值；
+    /// 库文件按照子版本在前、父版本在后的顺序拼接，新版参数追加在父版本参数之后，
+    /// 子版本没有`jar`时使用父版本的jar
     pub fn set_to_json(&self, json: &mut JsonVersion) {
+        let patch = self.patches.as_ref().and_then(|p| p.first());
+        json.jar = self.jar.clone().or_else(|| json.jar.take()).or_else(|| Some(json.id.clone()));
         json.id = self.id.clone();
-        json.time = self.time;
-        json.release_time = self.release_time;
-        json.type_ = self.type_.clone().or_else(|| self.patches.as_ref().and_then(|p| p.get(0)).and_then(|p| p.type_.clone()));
-        json.arguments = self.arguments.clone();
-        json.minimum_launcher_version = self.minimum_launcher_version.or_else(|| self.patches.as_ref().and_then(|p| p.get(0)).and_then(|p| p.minimum_launcher_version));
-        json.asset_index = self.asset_index.clone().or_else(|| self.patches.as_ref().and_then(|p| p.get(0)).and_then(|p| p.asset_index.clone()));
+        json.time = self.time.or(json.time);
+        json.release_time = self.release_time.or(json.release_time);
+        json.type_ = self.type_.clone().or_else(|| patch.and_then(|p| p.type_.clone())).or_else(|| json.type_.take());
+        json.arguments_old = self.arguments_old.clone().or_else(|| json.arguments_old.take());
+        if let Some(arguments) = &self.arguments {
+            let json_arguments = json.arguments.get_or_insert(JsonArguments { game: None, jvm: None });
+            if let Some(game) = &arguments.game {
+                json_arguments.game.get_or_insert_with(Vec::new).extend(game.iter().cloned());
+            }
+            if let Some(jvm) = &arguments.jvm {
+                json_arguments.jvm.get_or_insert_with(Vec::new).extend(jvm.iter().cloned());
+            }
+        }
+        json.minimum_launcher_version = self.minimum_launcher_version.or_else(|| patch.and_then(|p| p.minimum_launcher_version)).or(json.minimum_launcher_version);
+        json.asset_index = self.asset_index.clone().or_else(|| patch.and_then(|p| p.asset_index.clone())).or_else(|| json.asset_index.take());
         json.override_ = self.override_.clone();
-        json.jar = self.jar.clone();
-        json.assets = self.assets.clone().or_else(|| self.patches.as_ref().and_then(|p| p.get(0)).and_then(|p| p.assets.clone()));
-        json.main_class = self.main_class.clone();
+        json.assets = self.assets.clone().or_else(|| patch.and_then(|p| p.assets.clone())).or_else(|| json.assets.take());
+        if !self.main_class.is_empty() {
+            json.main_class = self.main_class.clone();
+        }
         if let Some(libraries) = &self.libraries {
-            if let Some(json_libraries) = &mut json.libraries {
-                json_libraries.extend(libraries.iter().cloned());
-            } else {
-                json.libraries = Some(libraries.clone());
-            }
+            let mut json_libraries = libraries.clone();
+            json_libraries.extend(json.libraries.take().unwrap_or_default());
+            json.libraries = Some(json_libraries);
         }
+        json.logging = self.logging.clone().or_else(|| json.logging.take());
+        json.java_version = self.java_version.clone().or_else(|| json.java_version.take());
         if let Some(downloads) = &self.downloads {
             if let Some(json_downloads) = &mut json.downloads {
                 for (key, value) in downloads {
@@ -94,7 +122,8 @@ pub struct JsonAssetsIndex {
     #[serde(default = "zero_default")]
     pub size: i32,
     #[serde(default = "zero_default")]
-    pub totalSize: i32,
+    #[serde(rename = "totalSize")]
+    pub total_size: i32,
     pub url: String,
     #[serde(default)]
     pub known: bool,
@@ -105,6 +134,11 @@ pub struct JsonLibrary {
     pub name: String,
     #[serde(default)]
     pub url: Option<String>,
+    /// Fabric等加载器的库没有`downloads`，直接在库中给出SHA-1和大小
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
     #[serde(default)]
     pub natives: Option<HashMap<String, String>>,
     #[serde(default)]
@@ -125,6 +159,8 @@ pub struct JsonRule {
     pub action: String,
     #[serde(default)]
     pub os: Option<JsonOperatingSystem>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<HashMap<String, bool>>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -141,11 +177,36 @@ pub struct JsonExtract {
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct JsonDownloads {
-    pub artifact: JsonDownload,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<JsonDownload>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub classifiers: Option<HashMap<String, JsonDownload>>,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonJavaVersion {
+    #[serde(default)]
+    pub component: String,
+    #[serde(rename = "majorVersion")]
+    pub major_version: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonLogging {
+    pub argument: String,
+    pub file: JsonLoggingFile,
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonLoggingFile {
+    pub id: String,
+    pub sha1: String,
+    pub size: i32,
+    pub url: String,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct JsonDownload {
     pub url: Option<String>,
@@ -155,25 +216,33 @@ pub struct JsonDownload {
 }
 
 mod serde_date_format {
-    use chrono::{DateTime, Utc, TimeZone};
+    use chrono::{DateTime, Utc};
     use serde::{self, Deserialize, Deserializer, Serializer};
 
     const FORMAT: &str = "%Y-%m-%dT%H:%M:%S%z";
 
-    pub fn serialize<S>(date: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    pub fn serialize<S>(date: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let s = date.format(FORMAT).to_string();
-        serializer.serialize_str(&s)
+        match date {
+            Some(date) => serializer.serialize_str(&date.format(FORMAT).to_string()),
+            None => serializer.serialize_none(),
+        }
     }
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let s = String::deserialize(deserializer)?;
-        Utc.datetime_from_str(&s, FORMAT).map_err(serde::de::Error::custom)
+        let Some(s) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        // 时区不一定是UTC，例如旧版Forge的`1960-01-01T00:00:00-0700`
+        DateTime::parse_from_str(&s, FORMAT)
+            .or_else(|_| DateTime::parse_from_rfc3339(&s))
+            .map(|date| Some(date.with_timezone(&Utc)))
+            .map_err(serde::de::Error::custom)
     }
 }
 
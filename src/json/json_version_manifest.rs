@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// `version_manifest_v2.json`的内容
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonVersionManifest {
+    pub latest: JsonLatestVersions,
+    pub versions: Vec<JsonManifestVersion>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonLatestVersions {
+    pub release: String,
+    pub snapshot: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonManifestVersion {
+    pub id: String,
+    /// `release`、`snapshot`、`old_beta`、`old_alpha`
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// 版本json的下载地址
+    pub url: String,
+    pub time: DateTime<Utc>,
+    #[serde(rename = "releaseTime")]
+    pub release_time: DateTime<Utc>,
+    /// 版本json的sha1，旧的`version_manifest.json`没有
+    #[serde(default)]
+    pub sha1: Option<String>,
+    #[serde(default, rename = "complianceLevel")]
+    pub compliance_level: Option<u32>,
+}
@@ -1 +1,6 @@
-pub mod json_version;
\ No newline at end of file
+pub mod json_version;
+pub mod json_assets;
+pub mod json_runtime;
+pub mod json_version_manifest;
+pub mod json_loader_meta;
+pub mod json_forge;
\ No newline at end of file
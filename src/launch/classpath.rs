@@ -0,0 +1,111 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::error::{CoreError, Result};
+use crate::json::json_version::JsonLibrary;
+use crate::tools::file_tools::{lib_name_to_path, MavenCoordinate};
+use crate::tools::string_tools::compare_versions;
+
+/// 当前系统的classpath分隔符，Windows为`;`，其他系统为`:`
+pub const CLASSPATH_SEPARATOR: &str = if cfg!(windows) { ";" } else { ":" };
+
+/// classpath中的一项
+#[derive(Debug, Clone)]
+pub struct ClasspathEntry {
+    /// 库的名字，例如`org.ow2.asm:asm:9.5`，游戏本体为版本名
+    pub name: String,
+    /// 文件的完整路径
+    pub path: String,
+}
+
+/// 启动时使用的classpath
+#[derive(Debug, Clone, Default)]
+pub struct Classpath {
+    pub entries: Vec<ClasspathEntry>,
+}
+
+impl Classpath {
+    /// 构建classpath
+    ///
+    /// # 参数
+    /// `libraries`: 已经按照继承链排好顺序（子版本在前）并且经过规则过滤的库文件
+    ///
+    /// `libraries_path`: `libraries`目录
+    ///
+    /// 同一个`group:artifact`（以及classifier）只保留版本最高的一个，位置为它第一次出现的位置。
+    /// 没有`downloads`的库会根据名字推导出maven路径
+    pub fn build(libraries: &[JsonLibrary], libraries_path: &str) -> Self {
+        let mut entries: Vec<ClasspathEntry> = vec![];
+        let mut versions: Vec<String> = vec![];
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for lib in libraries {
+            let Some(path) = library_path(lib) else {
+                continue;
+            };
+            let (key, version) = library_key(&lib.name);
+            let entry = ClasspathEntry { name: lib.name.clone(), path: format!("{libraries_path}/{path}") };
+            match positions.get(&key) {
+                Some(&index) => {
+                    if compare_versions(&version, &versions[index]) == Ordering::Greater {
+                        entries[index] = entry;
+                        versions[index] = version;
+                    }
+                }
+                None => {
+                    positions.insert(key, entries.len());
+                    entries.push(entry);
+                    versions.push(version);
+                }
+            }
+        }
+        Self { entries }
+    }
+
+    /// 在末尾添加一项，例如游戏本体
+    pub fn push(&mut self, name: &str, path: &str) {
+        self.entries.push(ClasspathEntry { name: name.to_owned(), path: path.to_owned() });
+    }
+
+    /// 获取本地不存在的文件
+    pub fn get_missing(&self) -> Vec<&ClasspathEntry> {
+        self.entries.iter().filter(|entry| !Path::new(&entry.path).is_file()).collect()
+    }
+
+    /// 检查所有文件都存在，缺失时返回包含所有缺失文件的错误
+    pub fn verify(&self) -> Result<()> {
+        let missing = self.get_missing();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(CoreError::MissingFiles { paths: missing.iter().map(|entry| entry.path.clone()).collect() })
+    }
+}
+
+impl fmt::Display for Classpath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let paths: Vec<&str> = self.entries.iter().map(|entry| entry.path.as_str()).collect();
+        write!(f, "{}", paths.join(CLASSPATH_SEPARATOR))
+    }
+}
+
+/// 库文件相对于`libraries`目录的路径，优先使用`downloads.artifact.path`
+fn library_path(lib: &JsonLibrary) -> Option<String> {
+    match lib.downloads.as_ref() {
+        Some(downloads) => {
+            // 只有classifiers的库（旧版本的natives）不加入classpath
+            let artifact = downloads.artifact.as_ref()?;
+            artifact.path.clone().or_else(|| lib_name_to_path(&lib.name))
+        }
+        None => lib_name_to_path(&lib.name),
+    }
+}
+
+/// 去重用的`group:artifact[:classifier]`以及版本号
+fn library_key(name: &str) -> (String, String) {
+    match name.parse::<MavenCoordinate>() {
+        Ok(coordinate) => (coordinate.key(), coordinate.version),
+        Err(_) => (name.to_owned(), String::new()),
+    }
+}
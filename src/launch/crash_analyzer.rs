@@ -0,0 +1,408 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::SystemTime;
+
+use regex::Regex;
+
+/// 崩溃报告中各部分之间的分隔内容
+const WALKTHROUGH_MARKER: &str = "A detailed walkthrough of the error";
+const SYSTEM_DETAILS_MARKER: &str = "-- System Details --";
+/// 系统信息中包含模组列表的项
+const MOD_LIST_KEYS: [&str; 3] = ["Fabric Mods", "Mod List", "Quilt Mods"];
+/// 显卡驱动崩溃时出现的内容
+const GRAPHICS_SIGNATURES: [&str; 10] = [
+    "Pixel format not accelerated",
+    "The driver does not appear to support OpenGL",
+    "Couldn't set pixel format",
+    "GLFW error 65542",
+    "GLFW error 65543",
+    "Failed to create OpenGL context",
+    "atio6axx.dll",
+    "ig9icd64.dll",
+    "ig75icd64.dll",
+    "nvoglv64.dll",
+];
+
+/// 识别崩溃原因使用的正则，只编译一次
+struct Signatures {
+    class_version: Regex,
+    /// 正则，模组id所在的组，前置所在的组
+    dependencies: Vec<(Regex, usize, usize)>,
+    mixin_config: Regex,
+    duplicates: Vec<Regex>,
+    corrupt_jarfile: Regex,
+    zip_path: Regex,
+}
+
+static SIGNATURES: LazyLock<Signatures> = LazyLock::new(|| {
+    let regex = |pattern: &str| Regex::new(pattern).unwrap();
+    Signatures {
+        class_version: regex(r"UnsupportedClassVersionError.*class file version (\d+)"),
+        dependencies: vec![
+            // Fabric: Mod 'Sodium' (sodium) 0.4.10 requires version 0.83 or later of mod 'Fabric API' (fabric-api), but only ...
+            (regex(r"Mod '[^']+' \(([\w-]+)\) \S+ requires [^']*? of (?:mod )?'[^']+' \(([\w-]+)\)"), 1, 2),
+            // 旧版本Fabric: Mod 'Sodium' (sodium) 0.4.10 requires any version of fabric-api, which is missing!
+            (regex(r"Mod '[^']+' \(([\w-]+)\) \S+ requires .*? of ([\w-]+), which is missing"), 1, 2),
+            // Forge: Mod ID: 'jei', Requested by: 'appleskin', Expected range: ...
+            (regex(r"Mod ID: '([\w-]+)', Requested by: '([\w-]+)'"), 2, 1),
+        ],
+        mixin_config: regex(r"([\w.-]+\.mixins?\.json|[\w.-]+mixins\.json)"),
+        duplicates: vec![
+            regex(r"Found duplicate mods?:?\s*Mod ID: '([\w-]+)'"),
+            regex(r"(?i)duplicate mods? (?:found )?(?:for )?'([\w-]+)'"),
+            regex(r"Mod ID '([\w-]+)' .*?(?:multiple|duplicate)"),
+        ],
+        corrupt_jarfile: regex(r"Invalid or corrupt jarfile (\S+)"),
+        zip_path: regex(r"(?:zip file|jarfile|ZipFile) ?:? ?([^\s]+\.jar)"),
+    }
+});
+
+/// 解析后的`crash-reports/*.txt`
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    pub path: String,
+    /// 例如`Unexpected error`、`Rendering overlay`
+    pub description: Option<String>,
+    pub stack_trace: Option<String>,
+    /// `Fabric Mods`、`Mod List`中的每一行
+    pub mods: Vec<String>,
+    /// `-- System Details --`中的每一项
+    pub system_details: Vec<(String, String)>,
+    /// 完整内容
+    pub content: String,
+}
+
+impl CrashReport {
+    pub fn load(path: &str) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        Some(Self::parse(path, &content))
+    }
+
+    pub fn parse(path: &str, content: &str) -> Self {
+        let description = content.lines()
+            .find_map(|line| line.strip_prefix("Description:"))
+            .map(|description| description.trim().to_owned());
+        let stack_trace = content.split_once("Description:")
+            .and_then(|(_, rest)| rest.split_once('\n'))
+            .map(|(_, rest)| rest.split(WALKTHROUGH_MARKER).next().unwrap_or(rest).trim().to_owned())
+            .filter(|stack_trace| !stack_trace.is_empty());
+
+        let mut mods: Vec<String> = vec![];
+        let mut system_details: Vec<(String, String)> = vec![];
+        let mut in_mod_list = false;
+        let details = content.split_once(SYSTEM_DETAILS_MARKER).map(|(_, details)| details).unwrap_or_default();
+        for line in details.lines() {
+            if line.starts_with("\t\t") {
+                if in_mod_list && !line.trim().is_empty() {
+                    mods.push(line.trim().to_owned());
+                }
+                continue;
+            }
+            in_mod_list = false;
+            let Some((key, value)) = line.strip_prefix('\t').and_then(|line| line.split_once(':')) else {
+                continue;
+            };
+            let key = key.trim().to_owned();
+            in_mod_list = MOD_LIST_KEYS.iter().any(|mod_list_key| key.starts_with(mod_list_key));
+            system_details.push((key, value.trim().to_owned()));
+        }
+
+        Self { path: path.to_owned(), description, stack_trace, mods, system_details, content: content.to_owned() }
+    }
+
+    pub fn system_detail(&self, key: &str) -> Option<&str> {
+        self.system_details.iter().find(|(detail_key, _)| detail_key == key).map(|(_, value)| value.as_str())
+    }
+}
+
+/// 识别出的崩溃原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrashDiagnosis {
+    /// Java版本不对，`required`为需要的主版本号
+    WrongJavaVersion { required: Option<u32> },
+    /// 内存不足
+    OutOfMemory,
+    /// 模组缺少前置
+    MissingModDependency { mod_id: String, dependency: String },
+    /// Mixin注入失败，`config`为出错的mixin配置
+    MixinFailure { config: Option<String> },
+    /// 显卡驱动或者OpenGL出错
+    GraphicsDriver,
+    /// 同一个模组安装了多个
+    DuplicateMods { mod_id: String },
+    /// jar文件损坏
+    CorruptedJar { path: Option<String> },
+}
+
+impl CrashDiagnosis {
+    /// 给用户的建议
+    pub fn suggestion(&self) -> String {
+        match self {
+            CrashDiagnosis::WrongJavaVersion { required: Some(required) } => format!("Use Java {required} to launch this version"),
+            CrashDiagnosis::WrongJavaVersion { required: None } => "Use the Java version required by this version".to_owned(),
+            CrashDiagnosis::OutOfMemory => "Increase the maximum memory, or close other programs to free memory".to_owned(),
+            CrashDiagnosis::MissingModDependency { mod_id, dependency } => format!("Install {dependency}, which is required by {mod_id}"),
+            CrashDiagnosis::MixinFailure { config: Some(config) } => format!("Remove or update the mod that provides {config}"),
+            CrashDiagnosis::MixinFailure { config: None } => "A mod failed to apply its mixins, remove or update recently added mods".to_owned(),
+            CrashDiagnosis::GraphicsDriver => "Update your graphics driver, or make sure the game runs on the dedicated GPU".to_owned(),
+            CrashDiagnosis::DuplicateMods { mod_id } => format!("Remove the duplicate copies of {mod_id} from the mods folder"),
+            CrashDiagnosis::CorruptedJar { path: Some(path) } => format!("Delete {path} and download it again"),
+            CrashDiagnosis::CorruptedJar { path: None } => "A jar file is corrupted, verify the game files".to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for CrashDiagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            CrashDiagnosis::WrongJavaVersion { .. } => "Wrong Java version",
+            CrashDiagnosis::OutOfMemory => "Out of memory",
+            CrashDiagnosis::MissingModDependency { .. } => "Missing mod dependency",
+            CrashDiagnosis::MixinFailure { .. } => "Mixin failure",
+            CrashDiagnosis::GraphicsDriver => "Graphics driver failure",
+            CrashDiagnosis::DuplicateMods { .. } => "Duplicate mods",
+            CrashDiagnosis::CorruptedJar { .. } => "Corrupted jar file",
+        };
+        write!(f, "{reason}: {}", self.suggestion())
+    }
+}
+
+/// 崩溃分析的结果
+#[derive(Debug, Clone)]
+pub struct CrashAnalysis {
+    pub crash_report: Option<CrashReport>,
+    /// JVM崩溃日志`hs_err_pid*.log`的路径
+    pub jvm_crash_log: Option<String>,
+    pub diagnoses: Vec<CrashDiagnosis>,
+}
+
+/// 分析游戏崩溃的原因
+///
+/// # 参数
+/// `game_directory`: 游戏目录，会查找其中最新的`crash-reports/*.txt`和`hs_err_pid*.log`
+///
+/// `since`: 游戏启动的时间，只使用这之后生成的文件，避免读到以前的崩溃报告
+///
+/// `output`: 游戏的输出，有些崩溃（例如Java版本不对）不会生成崩溃报告
+pub fn analyze_crash(game_directory: &str, since: Option<SystemTime>, output: &[String]) -> CrashAnalysis {
+    let crash_report = newest_file(&Path::new(game_directory).join("crash-reports"), since, |name| name.ends_with(".txt"))
+        .and_then(|path| CrashReport::load(&path.to_string_lossy()));
+    let jvm_crash_log = newest_file(Path::new(game_directory), since, |name| name.starts_with("hs_err_pid") && name.ends_with(".log"));
+
+    let mut text = output.join("\n");
+    if let Some(crash_report) = &crash_report {
+        text += "\n";
+        text += &crash_report.content;
+    }
+    if let Some(content) = jvm_crash_log.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+        text += "\n";
+        text += &content;
+    }
+
+    CrashAnalysis {
+        crash_report,
+        jvm_crash_log: jvm_crash_log.map(|path| path.to_string_lossy().into_owned()),
+        diagnoses: diagnose(&text),
+    }
+}
+
+/// 根据已知的特征识别崩溃原因
+pub fn diagnose(text: &str) -> Vec<CrashDiagnosis> {
+    let mut diagnoses: Vec<CrashDiagnosis> = vec![];
+    let mut push = |diagnosis: CrashDiagnosis| {
+        if !diagnoses.contains(&diagnosis) {
+            diagnoses.push(diagnosis);
+        }
+    };
+    let signatures = &*SIGNATURES;
+
+    // class file version 61.0对应Java 17
+    if let Some(captures) = signatures.class_version.captures(text) {
+        let required = captures[1].parse::<u32>().ok().and_then(|version| version.checked_sub(44));
+        push(CrashDiagnosis::WrongJavaVersion { required });
+    } else if text.contains("AppClassLoader cannot be cast to class java.net.URLClassLoader") {
+        // 1.13以前的Forge只能使用Java 8
+        push(CrashDiagnosis::WrongJavaVersion { required: Some(8) });
+    }
+
+    if text.contains("java.lang.OutOfMemoryError")
+        || text.contains("Could not reserve enough space")
+        || text.contains("There is insufficient memory for the Java Runtime Environment") {
+        push(CrashDiagnosis::OutOfMemory);
+    }
+
+    for (regex, mod_group, dependency_group) in &signatures.dependencies {
+        for captures in regex.captures_iter(text) {
+            push(CrashDiagnosis::MissingModDependency {
+                mod_id: captures[*mod_group].to_owned(),
+                dependency: captures[*dependency_group].to_owned(),
+            });
+        }
+    }
+
+    if text.contains("MixinApplyError") || text.contains("MixinTransformerError") || text.contains("Mixin apply failed")
+        || text.contains("InvalidInjectionException") {
+        let config = signatures.mixin_config.captures(text)
+            .map(|captures| captures[1].to_owned());
+        push(CrashDiagnosis::MixinFailure { config });
+    }
+
+    if GRAPHICS_SIGNATURES.iter().any(|signature| text.contains(signature)) {
+        push(CrashDiagnosis::GraphicsDriver);
+    }
+
+    if let Some(captures) = signatures.duplicates.iter().find_map(|regex| regex.captures(text)) {
+        push(CrashDiagnosis::DuplicateMods { mod_id: captures[1].to_owned() });
+    }
+
+    if let Some(captures) = signatures.corrupt_jarfile.captures(text) {
+        push(CrashDiagnosis::CorruptedJar { path: Some(captures[1].to_owned()) });
+    } else if text.contains("java.util.zip.ZipException") || text.contains("java.util.zip.ZipError") || text.contains("zip END header not found") {
+        let path = signatures.zip_path.captures(text).map(|captures| captures[1].to_owned());
+        push(CrashDiagnosis::CorruptedJar { path });
+    }
+
+    diagnoses
+}
+
+/// 查找目录中修改时间在`since`之后的最新文件
+fn newest_file(directory: &Path, since: Option<SystemTime>, filter: impl Fn(&str) -> bool) -> Option<PathBuf> {
+    fs::read_dir(directory).ok()?
+        .flatten()
+        .filter(|entry| filter(&entry.file_name().to_string_lossy()))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .filter(|(modified, _)| since.is_none_or(|since| *modified >= since))
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tools::test_tools::{temp_dir, write_file};
+
+    use super::*;
+
+    const CRASH_REPORT: &str = "---- Minecraft Crash Report ----
+// Who set us up the TNT?
+
+Time: 2023-11-14 22:13:20
+Description: Rendering overlay
+
+java.lang.NullPointerException: Cannot invoke \"Object.toString()\"
+	at net.minecraft.client.Foo.bar(Foo.java:1)
+
+
+A detailed walkthrough of the error, its code path and all known details is as follows:
+---------------------------------------------------------------------------------------
+
+-- Head --
+Thread: Render thread
+
+-- System Details --
+Details:
+	Minecraft Version: 1.20.1
+	Java Version: 17.0.8, Microsoft
+	Fabric Mods: 
+		fabric-api: Fabric API 0.86.1+1.20.1
+		sodium: Sodium 0.5.3
+	Launched Version: fabric-loader-0.14.21-1.20.1
+";
+
+    #[test]
+    fn parses_crash_report() {
+        let report = CrashReport::parse("crash.txt", CRASH_REPORT);
+        assert_eq!(report.description.as_deref(), Some("Rendering overlay"));
+        assert_eq!(
+            report.stack_trace.as_deref(),
+            Some("java.lang.NullPointerException: Cannot invoke \"Object.toString()\"\n\tat net.minecraft.client.Foo.bar(Foo.java:1)")
+        );
+        assert_eq!(report.mods, ["fabric-api: Fabric API 0.86.1+1.20.1", "sodium: Sodium 0.5.3"]);
+        assert_eq!(report.system_detail("Minecraft Version"), Some("1.20.1"));
+        assert_eq!(report.system_detail("Java Version"), Some("17.0.8, Microsoft"));
+        assert_eq!(report.system_detail("Launched Version"), Some("fabric-loader-0.14.21-1.20.1"));
+    }
+
+    #[test]
+    fn diagnoses_known_signatures() {
+        let cases = [
+            (
+                "java.lang.UnsupportedClassVersionError: net/minecraft/client/main/Main has been compiled by a more recent version of the Java Runtime (class file version 65.0), this version of the Java Runtime only recognizes class file versions up to 52.0",
+                vec![CrashDiagnosis::WrongJavaVersion { required: Some(21) }],
+            ),
+            (
+                "java.lang.ClassCastException: class jdk.internal.loader.ClassLoaders$AppClassLoader cannot be cast to class java.net.URLClassLoader",
+                vec![CrashDiagnosis::WrongJavaVersion { required: Some(8) }],
+            ),
+            ("java.lang.OutOfMemoryError: Java heap space", vec![CrashDiagnosis::OutOfMemory]),
+            ("Error occurred during initialization of VM\nCould not reserve enough space for 4194304KB object heap", vec![CrashDiagnosis::OutOfMemory]),
+            ("# There is insufficient memory for the Java Runtime Environment to continue.", vec![CrashDiagnosis::OutOfMemory]),
+            (
+                "Unmet dependency listing:\n\t - Mod 'Sodium' (sodium) 0.4.10 requires version 0.83 or later of mod 'Fabric API' (fabric-api), but only the wrong version is present: 0.76.0!",
+                vec![CrashDiagnosis::MissingModDependency { mod_id: "sodium".to_owned(), dependency: "fabric-api".to_owned() }],
+            ),
+            (
+                "Mod 'Iris' (iris) 1.6.4 requires any version of sodium, which is missing!",
+                vec![CrashDiagnosis::MissingModDependency { mod_id: "iris".to_owned(), dependency: "sodium".to_owned() }],
+            ),
+            (
+                "\tMod ID: 'jei', Requested by: 'appleskin', Expected range: '[15,)', Actual version: '[MISSING]'",
+                vec![CrashDiagnosis::MissingModDependency { mod_id: "appleskin".to_owned(), dependency: "jei".to_owned() }],
+            ),
+            (
+                "Caused by: org.spongepowered.asm.mixin.injection.throwables.InvalidInjectionException: Critical injection failure. Using refmap sodium-refmap.json [PREINJECT Applicator Phase -> sodium.mixins.json:core.MixinGameRenderer]",
+                vec![CrashDiagnosis::MixinFailure { config: Some("sodium.mixins.json".to_owned()) }],
+            ),
+            (
+                "org.spongepowered.asm.mixin.throwables.MixinApplyError: Mixin [core.MixinFoo] from phase [DEFAULT] in config [unknown] FAILED during APPLY",
+                vec![CrashDiagnosis::MixinFailure { config: None }],
+            ),
+            ("GLFW error 65542: WGL: The driver does not appear to support OpenGL", vec![CrashDiagnosis::GraphicsDriver]),
+            ("# Problematic frame:\n# C  [atio6axx.dll+0x1a2b3c]", vec![CrashDiagnosis::GraphicsDriver]),
+            (
+                "Found duplicate mods:\n\tMod ID: 'jei' from mod files: jei-1.jar, jei-2.jar",
+                vec![CrashDiagnosis::DuplicateMods { mod_id: "jei".to_owned() }],
+            ),
+            ("Duplicate mod found for 'sodium'", vec![CrashDiagnosis::DuplicateMods { mod_id: "sodium".to_owned() }]),
+            ("\t - Mod ID 'lithium' has multiple instances", vec![CrashDiagnosis::DuplicateMods { mod_id: "lithium".to_owned() }]),
+            (
+                "Error: Invalid or corrupt jarfile /home/steve/.minecraft/versions/1.20.1/1.20.1.jar",
+                vec![CrashDiagnosis::CorruptedJar { path: Some("/home/steve/.minecraft/versions/1.20.1/1.20.1.jar".to_owned()) }],
+            ),
+            (
+                "java.util.zip.ZipException: error in opening zip file mods/broken.jar",
+                vec![CrashDiagnosis::CorruptedJar { path: Some("mods/broken.jar".to_owned()) }],
+            ),
+            ("java.util.zip.ZipError: zip END header not found", vec![CrashDiagnosis::CorruptedJar { path: None }]),
+            ("Exception in thread \"main\" java.lang.NullPointerException", vec![]),
+            (
+                "java.lang.OutOfMemoryError: Java heap space\nGLFW error 65543: Failed to create OpenGL context\njava.lang.OutOfMemoryError: GC overhead limit exceeded",
+                vec![CrashDiagnosis::OutOfMemory, CrashDiagnosis::GraphicsDriver],
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(diagnose(text), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn analyzes_newest_crash_report() {
+        let game_directory = temp_dir("crash-analyzer");
+        write_file(&format!("{game_directory}/crash-reports/crash-2023-11-14_22.13.20-client.txt"), CRASH_REPORT.replace(
+            "java.lang.NullPointerException: Cannot invoke \"Object.toString()\"",
+            "java.lang.OutOfMemoryError: Java heap space",
+        ));
+
+        let analysis = analyze_crash(&game_directory, None, &["Exiting with code -1".to_owned()]);
+        assert_eq!(analysis.crash_report.unwrap().description.as_deref(), Some("Rendering overlay"));
+        assert_eq!(analysis.jvm_crash_log, None);
+        assert_eq!(analysis.diagnoses, [CrashDiagnosis::OutOfMemory]);
+
+        // 启动之前的崩溃报告不会被使用
+        let later = SystemTime::now() + std::time::Duration::from_secs(60);
+        let analysis = analyze_crash(&game_directory, Some(later), &[]);
+        assert!(analysis.crash_report.is_none());
+        assert!(analysis.diagnoses.is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use super::crash_analyzer::{self, CrashAnalysis};
+
+/// 出现这些内容时认为游戏窗口已经打开
+const WINDOW_READY_MARKERS: [&str; 3] = ["setting user:", "lwjgl version", "sound engine started"];
+/// 检查进程是否退出的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// 进程退出后等待输出读取完的最长时间
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 游戏进程的事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    /// 标准输出的一行
+    Stdout(String),
+    /// 标准错误的一行
+    Stderr(String),
+    /// 游戏窗口大概已经打开，只会发送一次
+    WindowReady,
+    /// 进程已经退出，是最后一个事件，参数为退出码（被信号结束时为`None`）
+    Exited(Option<i32>),
+}
+
+#[derive(Default)]
+struct ProcessState {
+    finished: bool,
+    status: Option<ExitStatus>,
+    exited_at: Option<Instant>,
+}
+
+/// 正在运行的游戏进程
+///
+/// 输出会在后台线程中按行读取，通过`events()`获取；进程退出后会发送`GameEvent::Exited`
+pub struct GameProcess {
+    /// 游戏目录，崩溃报告等文件在这里
+    pub game_directory: String,
+    pid: u32,
+    started_at: Instant,
+    started_at_time: SystemTime,
+    child: Arc<Mutex<Child>>,
+    state: Arc<(Mutex<ProcessState>, Condvar)>,
+    window_ready: Arc<AtomicBool>,
+    events: Receiver<GameEvent>,
+}
+
+impl GameProcess {
+    /// 启动进程，`command`的stdout和stderr会被重定向
+    pub fn spawn(mut command: Command, game_directory: &str) -> std::io::Result<Self> {
+        let mut child = command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+        let pid = child.id();
+        let (sender, events) = mpsc::channel();
+        let window_ready = Arc::new(AtomicBool::new(false));
+        let readers = [
+            child.stdout.take().map(|stdout| read_lines(stdout, sender.clone(), window_ready.clone(), GameEvent::Stdout)),
+            child.stderr.take().map(|stderr| read_lines(stderr, sender.clone(), window_ready.clone(), GameEvent::Stderr)),
+        ];
+
+        let child = Arc::new(Mutex::new(child));
+        let state: Arc<(Mutex<ProcessState>, Condvar)> = Arc::default();
+        let monitor_child = child.clone();
+        let monitor_state = state.clone();
+        thread::spawn(move || {
+            // 轮询而不是直接wait，这样kill不会被阻塞
+            let status = loop {
+                match monitor_child.lock().unwrap_or_else(|err| err.into_inner()).try_wait() {
+                    Ok(Some(status)) => break Some(status),
+                    Ok(None) => { }
+                    Err(_) => break None,
+                }
+                thread::sleep(POLL_INTERVAL);
+            };
+            let exited_at = Some(Instant::now());
+            // 等待剩余的输出读取完；子进程启动的其他进程可能一直占用输出，因此最多等待一段时间
+            let deadline = Instant::now() + OUTPUT_DRAIN_TIMEOUT;
+            while readers.iter().flatten().any(|reader| !reader.is_finished()) && Instant::now() < deadline {
+                thread::sleep(POLL_INTERVAL / 10);
+            }
+            let (lock, condvar) = &*monitor_state;
+            *lock.lock().unwrap_or_else(|err| err.into_inner()) = ProcessState { finished: true, status, exited_at };
+            condvar.notify_all();
+            let _ = sender.send(GameEvent::Exited(status.and_then(|status| status.code())));
+        });
+
+        Ok(Self { game_directory: game_directory.to_owned(), pid, started_at: Instant::now(), started_at_time: SystemTime::now(), child, state, window_ready, events })
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// 进程的事件，可以在界面线程中用`try_recv`获取
+    pub fn events(&self) -> &Receiver<GameEvent> {
+        &self.events
+    }
+
+    /// 从启动到现在（或者到退出）的时间
+    pub fn uptime(&self) -> Duration {
+        match self.state.0.lock().unwrap_or_else(|err| err.into_inner()).exited_at {
+            Some(exited_at) => exited_at.duration_since(self.started_at),
+            None => self.started_at.elapsed(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.state.0.lock().unwrap_or_else(|err| err.into_inner()).finished
+    }
+
+    /// 游戏窗口是否已经打开
+    pub fn is_window_ready(&self) -> bool {
+        self.window_ready.load(Ordering::Relaxed)
+    }
+
+    /// 退出状态，进程还在运行时返回`None`
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.state.0.lock().unwrap_or_else(|err| err.into_inner()).status
+    }
+
+    /// 等待进程退出并且所有输出都已经读取完
+    pub fn wait(&self) -> Option<ExitStatus> {
+        let (lock, condvar) = &*self.state;
+        let state = lock.lock().unwrap_or_else(|err| err.into_inner());
+        let state = condvar.wait_while(state, |state| !state.finished).unwrap_or_else(|err| err.into_inner());
+        state.status
+    }
+
+    /// 游戏异常退出时分析崩溃原因，进程还在运行或者正常退出时返回`None`
+    ///
+    /// `output`为收集到的游戏输出
+    pub fn analyze_crash(&self, output: &[String]) -> Option<CrashAnalysis> {
+        let state = self.state.0.lock().unwrap_or_else(|err| err.into_inner());
+        if !state.finished || state.status.is_some_and(|status| status.success()) {
+            return None;
+        }
+        // 文件系统的时间精度可能比较低，往前放宽一点
+        let since = self.started_at_time.checked_sub(Duration::from_secs(2));
+        Some(crash_analyzer::analyze_crash(&self.game_directory, since, output))
+    }
+
+    /// 结束游戏进程
+    pub fn kill(&self) -> std::io::Result<()> {
+        let mut child = self.child.lock().unwrap_or_else(|err| err.into_inner());
+        match child.try_wait()? {
+            Some(_) => Ok(()),
+            None => child.kill(),
+        }
+    }
+}
+
+/// 在后台线程中按行读取输出，游戏在Windows下可能输出非UTF-8的内容，因此按字节读取
+fn read_lines(
+    output: impl Read + Send + 'static,
+    sender: Sender<GameEvent>,
+    window_ready: Arc<AtomicBool>,
+    to_event: fn(String) -> GameEvent,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(output);
+        let mut buffer: Vec<u8> = vec![];
+        while matches!(reader.read_until(b'\n', &mut buffer), Ok(size) if size > 0) {
+            let line = String::from_utf8_lossy(&buffer).trim_end_matches(['\r', '\n']).to_owned();
+            buffer.clear();
+            let lowercase = line.to_lowercase();
+            let is_ready = WINDOW_READY_MARKERS.iter().any(|marker| lowercase.contains(marker));
+            let _ = sender.send(to_event(line));
+            if is_ready && !window_ready.swap(true, Ordering::Relaxed) {
+                let _ = sender.send(GameEvent::WindowReady);
+            }
+        }
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use crate::tools::test_tools::temp_dir;
+
+    fn shell(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        command
+    }
+
+    /// 读取所有事件直到进程退出
+    fn collect_events(process: &GameProcess) -> Vec<GameEvent> {
+        let mut events = vec![];
+        loop {
+            let event = process.events().recv_timeout(Duration::from_secs(10)).unwrap();
+            let exited = matches!(event, GameEvent::Exited(_));
+            events.push(event);
+            if exited {
+                return events;
+            }
+        }
+    }
+
+    #[test]
+    fn reports_output_and_exit_code() {
+        let game_directory = temp_dir("game-process");
+        let script = "echo 'Setting user: Steve'; echo 'LWJGL Version: 3.3.1'; echo 'warning' >&2; exit 3";
+        let process = GameProcess::spawn(shell(script), &game_directory).unwrap();
+        let events = collect_events(&process);
+
+        let stdout: Vec<&GameEvent> = events.iter().filter(|event| matches!(event, GameEvent::Stdout(_))).collect();
+        assert_eq!(stdout, [&GameEvent::Stdout("Setting user: Steve".to_owned()), &GameEvent::Stdout("LWJGL Version: 3.3.1".to_owned())]);
+        assert!(events.contains(&GameEvent::Stderr("warning".to_owned())));
+        // 只发送一次，并且在触发它的那一行之后
+        assert_eq!(events.iter().filter(|event| **event == GameEvent::WindowReady).count(), 1);
+        let ready = events.iter().position(|event| *event == GameEvent::WindowReady).unwrap();
+        let setting_user = events.iter().position(|event| *event == GameEvent::Stdout("Setting user: Steve".to_owned())).unwrap();
+        assert!(setting_user < ready);
+        assert_eq!(events.last(), Some(&GameEvent::Exited(Some(3))));
+
+        assert_eq!(process.wait().and_then(|status| status.code()), Some(3));
+        assert!(!process.is_running());
+        assert!(process.is_window_ready());
+        assert!(process.analyze_crash(&[]).is_some());
+    }
+
+    #[test]
+    fn skips_crash_analysis_on_success() {
+        let process = GameProcess::spawn(shell("echo done"), &temp_dir("game-process-success")).unwrap();
+        let events = collect_events(&process);
+        assert_eq!(events, [GameEvent::Stdout("done".to_owned()), GameEvent::Exited(Some(0))]);
+        assert!(!process.is_window_ready());
+        assert!(process.exit_status().is_some_and(|status| status.success()));
+        assert!(process.analyze_crash(&["done".to_owned()]).is_none());
+    }
+
+    #[test]
+    fn kills_running_process() {
+        let process = GameProcess::spawn(shell("exec sleep 30"), &temp_dir("game-process-kill")).unwrap();
+        assert!(process.is_running());
+        assert!(process.analyze_crash(&[]).is_none());
+        process.kill().unwrap();
+        // 被信号结束时没有退出码
+        assert_eq!(collect_events(&process), [GameEvent::Exited(None)]);
+        assert!(process.uptime() < Duration::from_secs(10));
+        // 已经退出时不会出错
+        process.kill().unwrap();
+    }
+}
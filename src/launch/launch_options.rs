@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use crate::platform::rule_evaluator::{
+    RuleEnvironment, FEATURE_HAS_CUSTOM_RESOLUTION, FEATURE_IS_QUICK_PLAY_MULTIPLAYER, FEATURE_IS_QUICK_PLAY_REALMS,
+    FEATURE_IS_QUICK_PLAY_SINGLEPLAYER,
+};
+
+/// 启动后直接进入的世界或者服务器
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuickPlay {
+    /// 进入服务器，旧版本使用`--server`和`--port`
+    Multiplayer { host: String, port: Option<u16> },
+    /// 进入单人世界（1.20+），参数为存档文件夹的名字
+    Singleplayer(String),
+    /// 进入Realms（1.20+），参数为Realm的id
+    Realms(String),
+}
+
+impl QuickPlay {
+    /// 对应的规则特性
+    pub fn feature(&self) -> &'static str {
+        match self {
+            QuickPlay::Multiplayer { .. } => FEATURE_IS_QUICK_PLAY_MULTIPLAYER,
+            QuickPlay::Singleplayer(_) => FEATURE_IS_QUICK_PLAY_SINGLEPLAYER,
+            QuickPlay::Realms(_) => FEATURE_IS_QUICK_PLAY_REALMS,
+        }
+    }
+}
+
+/// 启动选项
+///
+/// 使用`LaunchOptions::new()`创建，然后通过`with_*`方法设置，例如
+/// ```LaunchOptions::new().with_max_memory(4096).with_resolution(1280, 720)```
+#[derive(Debug, Clone)]
+pub struct LaunchOptions {
+    /// 最小内存（MB），对应`-Xms`
+    pub min_memory: Option<u32>,
+    /// 最大内存（MB），对应`-Xmx`
+    pub max_memory: Option<u32>,
+    /// 窗口大小，设置后会启用`has_custom_resolution`特性
+    pub resolution: Option<(u32, u32)>,
+    pub fullscreen: bool,
+    /// 额外的jvm参数，放在主类之前
+    pub jvm_arguments: Vec<String>,
+    /// 额外的游戏参数，放在最后
+    pub game_arguments: Vec<String>,
+    /// 自定义游戏目录（版本隔离），为`None`时使用`.minecraft`
+    pub game_directory: Option<String>,
+    /// 包装命令，例如`gamemoderun`、`prime-run`，java会作为它的参数启动
+    pub wrapper_command: Vec<String>,
+    /// 额外的环境变量
+    pub environment: HashMap<String, String>,
+    pub launcher_name: String,
+    pub launcher_version: String,
+    pub quick_play: Option<QuickPlay>,
+}
+
+impl Default for LaunchOptions {
+    fn default() -> Self {
+        Self {
+            min_memory: None,
+            max_memory: None,
+            resolution: None,
+            fullscreen: false,
+            jvm_arguments: vec![],
+            game_arguments: vec![],
+            game_directory: None,
+            wrapper_command: vec![],
+            environment: HashMap::new(),
+            launcher_name: "BakaXL".to_owned(),
+            launcher_version: "4.0".to_owned(),
+            quick_play: None,
+        }
+    }
+}
+
+impl LaunchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_min_memory(mut self, megabytes: u32) -> Self {
+        self.min_memory = Some(megabytes);
+        self
+    }
+
+    pub fn with_max_memory(mut self, megabytes: u32) -> Self {
+        self.max_memory = Some(megabytes);
+        self
+    }
+
+    pub fn with_resolution(mut self, width: u32, height: u32) -> Self {
+        self.resolution = Some((width, height));
+        self
+    }
+
+    pub fn with_fullscreen(mut self, fullscreen: bool) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    pub fn with_jvm_argument(mut self, argument: &str) -> Self {
+        self.jvm_arguments.push(argument.to_owned());
+        self
+    }
+
+    pub fn with_game_argument(mut self, argument: &str) -> Self {
+        self.game_arguments.push(argument.to_owned());
+        self
+    }
+
+    pub fn with_game_directory(mut self, game_directory: &str) -> Self {
+        self.game_directory = Some(game_directory.to_owned());
+        self
+    }
+
+    /// 设置包装命令，按空白分割，例如`"prime-run"`、`"gamemoderun"`
+    pub fn with_wrapper_command(mut self, command: &str) -> Self {
+        self.wrapper_command = command.split_whitespace().map(str::to_owned).collect();
+        self
+    }
+
+    pub fn with_env(mut self, key: &str, value: &str) -> Self {
+        self.environment.insert(key.to_owned(), value.to_owned());
+        self
+    }
+
+    pub fn with_launcher(mut self, name: &str, version: &str) -> Self {
+        self.launcher_name = name.to_owned();
+        self.launcher_version = version.to_owned();
+        self
+    }
+
+    /// 启动后自动进入服务器
+    pub fn with_server(mut self, host: &str, port: Option<u16>) -> Self {
+        self.quick_play = Some(QuickPlay::Multiplayer { host: host.to_owned(), port });
+        self
+    }
+
+    pub fn with_quick_play(mut self, quick_play: QuickPlay) -> Self {
+        self.quick_play = Some(quick_play);
+        self
+    }
+
+    /// 根据启动选项启用规则特性
+    pub fn apply_features(&self, env: RuleEnvironment) -> RuleEnvironment {
+        let env = env.with_feature(FEATURE_HAS_CUSTOM_RESOLUTION, self.resolution.is_some());
+        match &self.quick_play {
+            Some(quick_play) => env.with_feature(quick_play.feature(), true),
+            None => env,
+        }
+    }
+
+    /// 内存参数，例如`-Xms1024m`、`-Xmx4096m`
+    pub fn memory_arguments(&self) -> Vec<String> {
+        let mut arguments: Vec<String> = vec![];
+        if let Some(min_memory) = self.min_memory {
+            arguments.push(format!("-Xms{min_memory}m"));
+        }
+        if let Some(max_memory) = self.max_memory {
+            arguments.push(format!("-Xmx{max_memory}m"));
+        }
+        arguments
+    }
+
+    /// 不支持快速游戏的旧版本使用的进入服务器参数
+    pub fn legacy_server_arguments(&self) -> Vec<String> {
+        let Some(QuickPlay::Multiplayer { host, port }) = &self.quick_play else {
+            return vec![];
+        };
+        let mut arguments = vec!["--server".to_owned(), host.clone()];
+        if let Some(port) = port {
+            arguments.extend(["--port".to_owned(), port.to_string()]);
+        }
+        arguments
+    }
+
+    /// 快速游戏参数的变量值，例如`quickPlayMultiplayer`为`host:port`
+    pub fn quick_play_variables(&self) -> Vec<(&'static str, String)> {
+        match &self.quick_play {
+            Some(QuickPlay::Multiplayer { host, port: Some(port) }) => vec![("quickPlayMultiplayer", format!("{host}:{port}"))],
+            Some(QuickPlay::Multiplayer { host, port: None }) => vec![("quickPlayMultiplayer", host.clone())],
+            Some(QuickPlay::Singleplayer(world)) => vec![("quickPlaySingleplayer", world.clone())],
+            Some(QuickPlay::Realms(realm)) => vec![("quickPlayRealms", realm.clone())],
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_memory_arguments() {
+        assert!(LaunchOptions::new().memory_arguments().is_empty());
+        assert_eq!(LaunchOptions::new().with_max_memory(4096).memory_arguments(), ["-Xmx4096m"]);
+        assert_eq!(LaunchOptions::new().with_max_memory(4096).with_min_memory(1024).memory_arguments(), ["-Xms1024m", "-Xmx4096m"]);
+    }
+
+    #[test]
+    fn enables_features() {
+        let env = LaunchOptions::new().apply_features(RuleEnvironment::new("linux", "6.1", "x86_64"));
+        assert!(!env.has_feature(FEATURE_HAS_CUSTOM_RESOLUTION));
+        assert!(!env.has_feature(FEATURE_IS_QUICK_PLAY_MULTIPLAYER));
+
+        let options = LaunchOptions::new().with_resolution(1280, 720).with_quick_play(QuickPlay::Realms("1234".to_owned()));
+        let env = options.apply_features(RuleEnvironment::new("linux", "6.1", "x86_64"));
+        assert!(env.has_feature(FEATURE_HAS_CUSTOM_RESOLUTION));
+        assert!(env.has_feature(FEATURE_IS_QUICK_PLAY_REALMS));
+        assert!(!env.has_feature(FEATURE_IS_QUICK_PLAY_MULTIPLAYER));
+    }
+
+    #[test]
+    fn selects_server_arguments() {
+        let options = LaunchOptions::new().with_server("mc.example.com", Some(25566));
+        assert_eq!(options.legacy_server_arguments(), ["--server", "mc.example.com", "--port", "25566"]);
+        assert_eq!(options.quick_play_variables(), [("quickPlayMultiplayer", "mc.example.com:25566".to_owned())]);
+
+        let options = LaunchOptions::new().with_server("mc.example.com", None);
+        assert_eq!(options.legacy_server_arguments(), ["--server", "mc.example.com"]);
+        assert_eq!(options.quick_play_variables(), [("quickPlayMultiplayer", "mc.example.com".to_owned())]);
+
+        let options = LaunchOptions::new().with_quick_play(QuickPlay::Singleplayer("New World".to_owned()));
+        assert!(options.legacy_server_arguments().is_empty());
+        assert_eq!(options.quick_play_variables(), [("quickPlaySingleplayer", "New World".to_owned())]);
+        assert!(LaunchOptions::new().quick_play_variables().is_empty());
+    }
+
+    #[test]
+    fn keeps_extra_arguments_in_order() {
+        let options = LaunchOptions::new()
+            .with_jvm_argument("-XX:+UseG1GC")
+            .with_jvm_argument("-Dfile.encoding=UTF-8")
+            .with_game_argument("--demo")
+            .with_wrapper_command("  prime-run   gamemoderun ");
+        assert_eq!(options.jvm_arguments, ["-XX:+UseG1GC", "-Dfile.encoding=UTF-8"]);
+        assert_eq!(options.game_arguments, ["--demo"]);
+        assert_eq!(options.wrapper_command, ["prime-run", "gamemoderun"]);
+    }
+}
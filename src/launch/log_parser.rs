@@ -0,0 +1,320 @@
+use std::fmt;
+
+use chrono::{DateTime, Local, NaiveTime, TimeZone, Utc};
+use regex::Regex;
+
+/// log4j事件的开始标签
+const EVENT_START: &str = "<log4j:Event";
+/// log4j事件的结束标签
+const EVENT_END: &str = "</log4j:Event>";
+const CDATA_START: &str = "<![CDATA[";
+const CDATA_END: &str = "]]>";
+
+/// 日志等级
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+    /// 无法识别等级的输出，例如崩溃时直接打印到stderr的内容
+    Unknown,
+}
+
+impl LogLevel {
+    pub fn parse(level: &str) -> Self {
+        match level.trim().to_uppercase().as_str() {
+            "TRACE" | "FINEST" | "FINER" => LogLevel::Trace,
+            "DEBUG" | "FINE" => LogLevel::Debug,
+            "INFO" | "CONFIG" => LogLevel::Info,
+            "WARN" | "WARNING" => LogLevel::Warn,
+            "ERROR" | "SEVERE" => LogLevel::Error,
+            "FATAL" => LogLevel::Fatal,
+            _ => LogLevel::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+            LogLevel::Fatal => "FATAL",
+            LogLevel::Unknown => "UNKNOWN",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// 一条日志
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub level: LogLevel,
+    pub thread: Option<String>,
+    pub logger: Option<String>,
+    pub message: String,
+    /// 异常的堆栈
+    pub throwable: Option<String>,
+}
+
+impl LogRecord {
+    /// 无法解析的一行输出
+    fn plain(line: &str) -> Self {
+        Self { timestamp: None, level: LogLevel::Unknown, thread: None, logger: None, message: line.to_owned(), throwable: None }
+    }
+}
+
+/// 游戏输出的日志解析器
+///
+/// 使用版本json中`logging.client`的配置时，游戏输出`<log4j:Event>`格式的XML；
+/// 旧版本或者没有使用配置时输出`[12:34:56] [main/INFO]: message`格式的文本，两种格式都可以解析。
+/// 按行调用`feed`，一条XML日志跨多行时在结束标签出现后才会返回
+pub struct LogParser {
+    buffer: Option<String>,
+    attribute_regex: Regex,
+    text_regex: Regex,
+}
+
+impl Default for LogParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogParser {
+    pub fn new() -> Self {
+        Self {
+            buffer: None,
+            attribute_regex: Regex::new(r#"([\w:]+)="([^"]*)""#).unwrap(),
+            text_regex: Regex::new(r"^\[(\d{1,2}:\d{2}:\d{2})(?:\.\d+)?\] \[([^\]]*?)/(\w+)\](?: \[([^\]]*)\])?: ?(.*)$").unwrap(),
+        }
+    }
+
+    /// 解析一行输出，这一行结束了一条日志时返回它
+    pub fn feed(&mut self, line: &str) -> Option<LogRecord> {
+        match self.buffer.as_mut() {
+            Some(buffer) => {
+                buffer.push('\n');
+                buffer.push_str(line);
+            }
+            None if line.trim_start().starts_with(EVENT_START) => self.buffer = Some(line.to_owned()),
+            None => return Some(self.parse_text(line)),
+        }
+        // CDATA中的结束标签不算事件结束
+        if !line.contains(EVENT_END) || !self.buffer.as_deref().is_some_and(is_event_complete) {
+            return None;
+        }
+        let event = self.buffer.take()?;
+        Some(self.parse_event(&event))
+    }
+
+    /// 输出结束时取出还没有结束的日志
+    pub fn flush(&mut self) -> Option<LogRecord> {
+        self.buffer.take().map(|event| self.parse_event(&event))
+    }
+
+    /// 解析一条`<log4j:Event>`
+    fn parse_event(&self, event: &str) -> LogRecord {
+        let start_tag = event.split_once('>').map(|(start_tag, _)| start_tag).unwrap_or(event);
+        let attribute = |name: &str| {
+            self.attribute_regex.captures_iter(start_tag)
+                .find(|captures| &captures[1] == name)
+                .map(|captures| unescape_xml(&captures[2]))
+        };
+        let Some(message) = element_text(event, "log4j:Message") else {
+            return LogRecord::plain(event);
+        };
+        LogRecord {
+            timestamp: attribute("timestamp")
+                .and_then(|timestamp| timestamp.parse::<i64>().ok())
+                .and_then(|millis| Utc.timestamp_millis_opt(millis).single()),
+            level: attribute("level").map(|level| LogLevel::parse(&level)).unwrap_or(LogLevel::Unknown),
+            thread: attribute("thread"),
+            logger: attribute("logger"),
+            message,
+            throwable: element_text(event, "log4j:Throwable"),
+        }
+    }
+
+    /// 解析`[12:34:56] [main/INFO]: message`或者`[12:34:56] [main/INFO] [logger]: message`
+    fn parse_text(&self, line: &str) -> LogRecord {
+        let Some(captures) = self.text_regex.captures(line) else {
+            return LogRecord::plain(line);
+        };
+        // 文本日志只有时间，日期按照今天计算
+        let timestamp = NaiveTime::parse_from_str(&captures[1], "%H:%M:%S").ok()
+            .and_then(|time| Local::now().date_naive().and_time(time).and_local_timezone(Local).single())
+            .map(|time| time.with_timezone(&Utc));
+        LogRecord {
+            timestamp,
+            level: LogLevel::parse(&captures[3]),
+            thread: Some(captures[2].to_owned()),
+            logger: captures.get(4).map(|logger| logger.as_str().to_owned()),
+            message: captures[5].to_owned(),
+            throwable: None,
+        }
+    }
+}
+
+/// 去掉CDATA之后是否出现了事件的结束标签，CDATA还没有结束时返回`false`
+fn is_event_complete(event: &str) -> bool {
+    let mut rest = event;
+    loop {
+        match (rest.find(CDATA_START), rest.find(EVENT_END)) {
+            (Some(cdata), Some(end)) if cdata < end => match rest[cdata..].find(CDATA_END) {
+                Some(cdata_end) => rest = &rest[cdata + cdata_end + CDATA_END.len()..],
+                None => return false,
+            },
+            (_, end) => return end.is_some(),
+        }
+    }
+}
+
+/// 获取元素的内容，支持CDATA和转义后的文本
+fn element_text(event: &str, name: &str) -> Option<String> {
+    let start_tag = format!("<{name}>");
+    let end_tag = format!("</{name}>");
+    let start = event.find(&start_tag)? + start_tag.len();
+    let content = event[start..].trim_start();
+    // CDATA中可能包含结束标签，先找CDATA的结尾
+    if let Some(cdata) = content.strip_prefix(CDATA_START) {
+        return cdata.find(CDATA_END).map(|end| cdata[..end].to_owned());
+    }
+    let end = content.find(&end_tag)?;
+    Some(unescape_xml(content[..end].trim()))
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按行喂给解析器，返回解析出的所有日志
+    fn parse_lines(lines: &[&str]) -> Vec<LogRecord> {
+        let mut parser = LogParser::new();
+        let mut records: Vec<LogRecord> = lines.iter().filter_map(|line| parser.feed(line)).collect();
+        records.extend(parser.flush());
+        records
+    }
+
+    #[test]
+    fn parses_event_split_across_lines() {
+        let mut parser = LogParser::new();
+        let lines = [
+            r#"<log4j:Event logger="net.minecraft.client.Minecraft" timestamp="1700000000000" level="ERROR" thread="Render thread">"#,
+            r#"  <log4j:Message><![CDATA[Unreported exception thrown!]]></log4j:Message>"#,
+            r#"  <log4j:Throwable><![CDATA[java.lang.NullPointerException"#,
+            r#"	at net.minecraft.client.Minecraft.run(Minecraft.java:100)]]></log4j:Throwable>"#,
+        ];
+        for line in lines {
+            assert_eq!(parser.feed(line), None);
+        }
+        let record = parser.feed("</log4j:Event>").unwrap();
+        assert_eq!(record.level, LogLevel::Error);
+        assert_eq!(record.thread.as_deref(), Some("Render thread"));
+        assert_eq!(record.logger.as_deref(), Some("net.minecraft.client.Minecraft"));
+        assert_eq!(record.message, "Unreported exception thrown!");
+        assert_eq!(record.throwable.as_deref(), Some("java.lang.NullPointerException\n\tat net.minecraft.client.Minecraft.run(Minecraft.java:100)"));
+        assert_eq!(record.timestamp, Utc.timestamp_millis_opt(1700000000000).single());
+        assert_eq!(parser.flush(), None);
+    }
+
+    #[test]
+    fn parses_cdata_and_escaped_messages() {
+        let cases = [
+            (r#"<log4j:Message><![CDATA[if (a < b && c > d)]]></log4j:Message>"#, "if (a < b && c > d)"),
+            (r#"<log4j:Message>if (a &lt; b &amp;&amp; c &gt; d) &quot;ok&quot;</log4j:Message>"#, "if (a < b && c > d) \"ok\""),
+            (r#"<log4j:Message><![CDATA[nested </log4j:Message> tag]]></log4j:Message>"#, "nested </log4j:Message> tag"),
+            (r#"<log4j:Message><![CDATA[]]></log4j:Message>"#, ""),
+        ];
+        for (message, expected) in cases {
+            let records = parse_lines(&[r#"<log4j:Event logger="test" timestamp="0" level="INFO" thread="main">"#, message, "</log4j:Event>"]);
+            assert_eq!(records.len(), 1, "{message}");
+            assert_eq!(records[0].message, expected, "{message}");
+            assert_eq!(records[0].level, LogLevel::Info);
+        }
+    }
+
+    #[test]
+    fn waits_for_end_tag_outside_cdata() {
+        let records = parse_lines(&[
+            r#"<log4j:Event logger="test" timestamp="0" level="WARN" thread="main">"#,
+            r#"<log4j:Message><![CDATA[first line"#,
+            r#"an xml dump: </log4j:Event>"#,
+            r#"last line]]></log4j:Message>"#,
+            "</log4j:Event>",
+            "[12:34:56] [main/INFO]: after",
+        ]);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].level, LogLevel::Warn);
+        assert_eq!(records[0].message, "first line\nan xml dump: </log4j:Event>\nlast line");
+        assert_eq!(records[1].message, "after");
+    }
+
+    #[test]
+    fn parses_single_line_event() {
+        let records = parse_lines(&[
+            r#"<log4j:Event logger="a" timestamp="0" level="DEBUG" thread="main"><log4j:Message><![CDATA[one]]></log4j:Message></log4j:Event>"#,
+            r#"<log4j:Event logger="b" timestamp="0" level="FATAL" thread="main"><log4j:Message>two</log4j:Message></log4j:Event>"#,
+        ]);
+        let summary: Vec<(LogLevel, &str)> = records.iter().map(|record| (record.level, record.message.as_str())).collect();
+        assert_eq!(summary, [(LogLevel::Debug, "one"), (LogLevel::Fatal, "two")]);
+    }
+
+    #[test]
+    fn parses_text_lines() {
+        let cases = [
+            ("[12:34:56] [main/INFO]: Setting user: Steve", LogLevel::Info, Some("main"), None, "Setting user: Steve"),
+            ("[12:34:56.789] [Render thread/WARN] [minecraft/Options]: Skipping bad option", LogLevel::Warn, Some("Render thread"), Some("minecraft/Options"), "Skipping bad option"),
+            ("[01:02:03] [Server thread/ERROR]: ", LogLevel::Error, Some("Server thread"), None, ""),
+            ("Exception in thread \"main\" java.lang.OutOfMemoryError", LogLevel::Unknown, None, None, "Exception in thread \"main\" java.lang.OutOfMemoryError"),
+        ];
+        for (line, level, thread, logger, message) in cases {
+            let record = LogParser::new().feed(line).unwrap();
+            assert_eq!(record.level, level, "{line}");
+            assert_eq!(record.thread.as_deref(), thread, "{line}");
+            assert_eq!(record.logger.as_deref(), logger, "{line}");
+            assert_eq!(record.message, message, "{line}");
+        }
+    }
+
+    #[test]
+    fn flushes_unfinished_event() {
+        let mut parser = LogParser::new();
+        assert_eq!(parser.feed(r#"<log4j:Event logger="test" timestamp="0" level="INFO" thread="main">"#), None);
+        assert_eq!(parser.feed("<log4j:Message>cut off</log4j:Message>"), None);
+        let record = parser.flush().unwrap();
+        assert_eq!(record.message, "cut off");
+        assert_eq!(parser.flush(), None);
+    }
+
+    #[test]
+    fn parses_levels() {
+        let cases = [
+            ("TRACE", LogLevel::Trace),
+            ("fine", LogLevel::Debug),
+            ("INFO", LogLevel::Info),
+            ("WARNING", LogLevel::Warn),
+            ("SEVERE", LogLevel::Error),
+            ("FATAL", LogLevel::Fatal),
+            ("VERBOSE", LogLevel::Unknown),
+        ];
+        for (name, level) in cases {
+            assert_eq!(LogLevel::parse(name), level, "{name}");
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod classpath;
+pub mod natives;
+pub mod launch_options;
+pub mod game_process;
+pub mod log_parser;
+pub mod crash_analyzer;
\ No newline at end of file
@@ -0,0 +1,335 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use zip::read::ZipArchive;
+use zip::result::ZipError;
+
+use crate::error::{CoreError, Result};
+use crate::json::json_version::JsonLibrary;
+use crate::platform::rule_evaluator::RuleEnvironment;
+use crate::tools::file_tools::{lib_name_to_path, MavenCoordinate};
+use crate::tools::hash_tools::sha1_bytes;
+
+/// 记录上次解压内容的文件名
+const STAMP_FILE: &str = ".natives-stamp";
+/// 1.19+的natives jar中只解压这些动态库文件
+const NATIVE_EXTENSIONS: [&str; 4] = ["so", "dll", "dylib", "jnilib"];
+
+/// 需要解压的natives文件
+#[derive(Debug, Clone)]
+pub struct NativeLibrary {
+    pub name: String,
+    /// jar文件的完整路径
+    pub path: String,
+    /// 不需要解压的路径前缀，例如`META-INF/`
+    pub exclude: Vec<String>,
+    /// 只解压动态库文件并放到目录的根部（1.19+的`natives-*`库）
+    pub flatten: bool,
+}
+
+/// 是否是1.19+的natives库，例如`org.lwjgl:lwjgl:3.3.1:natives-linux`
+pub fn is_classified_native(lib: &JsonLibrary) -> bool {
+    lib.natives.is_none() && lib.name.parse::<MavenCoordinate>()
+        .is_ok_and(|coordinate| coordinate.classifier.is_some_and(|classifier| classifier.starts_with("natives-")))
+}
+
+/// 旧版本natives库在当前系统下的classifier，`${arch}`会被替换为`32`或`64`
+pub fn native_classifier(lib: &JsonLibrary, env: &RuleEnvironment) -> Option<String> {
+    let classifier = lib.natives.as_ref()?.get(&env.os_name)?;
+    let arch = if env.os_arch.contains("64") { "64" } else { "32" };
+    Some(classifier.replace("${arch}", arch))
+}
+
+/// 获取natives库对应的jar文件
+///
+/// # 参数
+/// `natives`: 已经经过规则过滤的natives库
+///
+/// `libraries_path`: `libraries`目录
+pub fn collect_natives(natives: &[JsonLibrary], libraries_path: &str, env: &RuleEnvironment) -> Result<Vec<NativeLibrary>> {
+    let mut result: Vec<NativeLibrary> = vec![];
+    for native in natives {
+        let exclude = native.extract.as_ref().map(|extract| extract.exclude.clone()).unwrap_or_default();
+        let path = match native_classifier(native, env) {
+            Some(classifier) => native.downloads.as_ref()
+                .and_then(|downloads| downloads.classifiers.as_ref()?.get(&classifier)?.path.clone())
+                .or_else(|| {
+                    let coordinate = native.name.parse::<MavenCoordinate>().ok()?;
+                    Some(coordinate.with_classifier(&classifier).path())
+                }),
+            // 当前系统没有对应的natives
+            None if native.natives.is_some() => continue,
+            None => native.downloads.as_ref()
+                .and_then(|downloads| downloads.artifact.as_ref()?.path.clone())
+                .or_else(|| lib_name_to_path(&native.name)),
+        };
+        let path = path.ok_or_else(|| CoreError::InvalidMavenCoordinate(native.name.clone()))?;
+        result.push(NativeLibrary {
+            name: native.name.clone(),
+            path: format!("{libraries_path}/{path}"),
+            exclude,
+            flatten: is_classified_native(native),
+        });
+    }
+    Ok(result)
+}
+
+/// 管理一个版本的natives目录
+pub struct NativesManager {
+    /// natives解压到的目录
+    pub directory: String,
+}
+
+impl NativesManager {
+    pub fn new(directory: &str) -> Self {
+        Self { directory: directory.to_owned() }
+    }
+
+    /// 解压natives
+    ///
+    /// 和上次解压的内容相同时跳过；否则先清空目录再解压，避免留下旧版本的文件
+    pub fn extract(&self, natives: &[NativeLibrary]) -> Result<()> {
+        let stamp = Self::stamp(natives)?;
+        let stamp_path = format!("{}/{STAMP_FILE}", self.directory);
+        if fs::read_to_string(&stamp_path).is_ok_and(|old_stamp| old_stamp == stamp) {
+            return Ok(());
+        }
+
+        if Path::new(&self.directory).exists() {
+            fs::remove_dir_all(&self.directory).map_err(|err| CoreError::io(&self.directory, err))?;
+        }
+        fs::create_dir_all(&self.directory).map_err(|err| CoreError::io(&self.directory, err))?;
+        for native in natives {
+            self.extract_library(native)?;
+        }
+        fs::write(&stamp_path, stamp).map_err(|err| CoreError::io(&stamp_path, err))
+    }
+
+    /// 删除同一个版本目录下其他的`natives*`目录（例如旧版本启动器或者其他架构留下的）
+    pub fn cleanup_old(&self) -> Result<()> {
+        let directory = Path::new(&self.directory);
+        let (Some(parent_dir), Some(current)) = (directory.parent(), directory.file_name()) else {
+            return Ok(());
+        };
+        let Ok(entries) = fs::read_dir(parent_dir) else {
+            return Ok(());
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            if name != current && name.to_string_lossy().starts_with("natives") && entry.path().is_dir() {
+                fs::remove_dir_all(entry.path()).map_err(|err| CoreError::io(entry.path().to_string_lossy(), err))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 根据jar的路径、大小、修改时间和排除列表计算标记
+    fn stamp(natives: &[NativeLibrary]) -> Result<String> {
+        let mut content = String::new();
+        for native in natives {
+            let metadata = fs::metadata(&native.path)
+                .map_err(|_| CoreError::MissingLibrary { name: native.name.clone(), path: native.path.clone() })?;
+            let modified = metadata.modified().ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default();
+            content += &format!("{}|{}|{modified}|{}|{}\n", native.path, metadata.len(), native.exclude.join(","), native.flatten);
+        }
+        Ok(sha1_bytes(content.as_bytes()))
+    }
+
+    fn extract_library(&self, native: &NativeLibrary) -> Result<()> {
+        let file_path = &native.path;
+        let file = File::open(file_path).map_err(|_| CoreError::MissingLibrary { name: native.name.clone(), path: file_path.clone() })?;
+        let mut archive = ZipArchive::new(file).map_err(|source| CoreError::CorruptZip { path: file_path.clone(), source })?;
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).map_err(|source| CoreError::CorruptZip { path: file_path.clone(), source })?;
+            // 防止`../`之类的路径写到natives目录之外
+            let Some(entry_path) = file.enclosed_name().map(Path::to_path_buf) else {
+                let source = ZipError::InvalidArchive("Entry path escapes the extraction directory");
+                return Err(CoreError::CorruptZip { path: file_path.clone(), source });
+            };
+            if native.exclude.iter().any(|exclude| file.name().starts_with(exclude.as_str())) {
+                continue;
+            }
+            let output_path = match self.output_path(native, &entry_path, file.is_dir()) {
+                Some(output_path) => output_path,
+                None => continue,
+            };
+
+            if file.is_dir() {
+                fs::create_dir_all(&output_path).map_err(|err| CoreError::io(output_path.to_string_lossy(), err))?;
+            } else {
+                if let Some(parent_dir) = output_path.parent() {
+                    fs::create_dir_all(parent_dir).map_err(|err| CoreError::io(parent_dir.to_string_lossy(), err))?;
+                }
+                let mut output_file = File::create(&output_path).map_err(|err| CoreError::io(output_path.to_string_lossy(), err))?;
+                std::io::copy(&mut file, &mut output_file).map_err(|err| CoreError::io(output_path.to_string_lossy(), err))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 文件解压后的路径，不需要解压时返回`None`
+    fn output_path(&self, native: &NativeLibrary, entry_path: &Path, is_dir: bool) -> Option<PathBuf> {
+        if !native.flatten {
+            return Some(Path::new(&self.directory).join(entry_path));
+        }
+        let is_native = entry_path.extension()
+            .is_some_and(|extension| NATIVE_EXTENSIONS.iter().any(|native_extension| extension == *native_extension));
+        if is_dir || !is_native {
+            return None;
+        }
+        Some(Path::new(&self.directory).join(entry_path.file_name()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::tools::test_tools::{temp_dir, write_file, write_zip};
+
+    use super::*;
+
+    fn library(json: serde_json::Value) -> JsonLibrary {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn native(name: &str, path: &str, exclude: &[&str], flatten: bool) -> NativeLibrary {
+        NativeLibrary { name: name.to_owned(), path: path.to_owned(), exclude: exclude.iter().map(|exclude| exclude.to_string()).collect(), flatten }
+    }
+
+    #[test]
+    fn substitutes_arch_in_classifier() {
+        let lib = library(json!({ "name": "tv.twitch:twitch-platform:5.16", "natives": { "windows": "natives-windows-${arch}", "osx": "natives-osx" } }));
+        assert_eq!(native_classifier(&lib, &RuleEnvironment::new("windows", "10.0", "x86")).as_deref(), Some("natives-windows-32"));
+        assert_eq!(native_classifier(&lib, &RuleEnvironment::new("windows", "10.0", "x86_64")).as_deref(), Some("natives-windows-64"));
+        assert_eq!(native_classifier(&lib, &RuleEnvironment::new("macos", "13.4", "aarch64")).as_deref(), Some("natives-osx"));
+        assert_eq!(native_classifier(&lib, &RuleEnvironment::new("linux", "6.1.0", "x86_64")), None);
+    }
+
+    #[test]
+    fn collects_native_jars() {
+        let env = RuleEnvironment::new("linux", "6.1.0", "x86_64");
+        let natives = [
+            library(json!({
+                "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209",
+                "natives": { "linux": "natives-linux", "windows": "natives-windows" },
+                "extract": { "exclude": ["META-INF/"] },
+                "downloads": { "classifiers": { "natives-linux": { "path": "custom/lwjgl-platform-natives-linux.jar", "sha1": "", "size": 0, "url": "" } } }
+            })),
+            library(json!({ "name": "net.java.jinput:jinput-platform:2.0.5", "natives": { "linux": "natives-linux" } })),
+            library(json!({ "name": "ca.weblite:java-objc-bridge:1.0.0", "natives": { "osx": "natives-osx" } })),
+            library(json!({ "name": "org.lwjgl:lwjgl:3.3.1:natives-linux" })),
+        ];
+        assert!(!is_classified_native(&natives[0]));
+        assert!(is_classified_native(&natives[3]));
+
+        let collected = collect_natives(&natives, "/libraries", &env).unwrap();
+        let paths: Vec<(&str, bool)> = collected.iter().map(|native| (native.path.as_str(), native.flatten)).collect();
+        assert_eq!(paths, [
+            ("/libraries/custom/lwjgl-platform-natives-linux.jar", false),
+            ("/libraries/net/java/jinput/jinput-platform/2.0.5/jinput-platform-2.0.5-natives-linux.jar", false),
+            ("/libraries/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar", true),
+        ]);
+        assert_eq!(collected[0].exclude, ["META-INF/"]);
+        assert!(collected[1].exclude.is_empty());
+    }
+
+    #[test]
+    fn extracts_with_exclude_list() {
+        let dir = temp_dir("natives-exclude");
+        let jar = format!("{dir}/lwjgl-platform.jar");
+        write_zip(&jar, &[("META-INF/MANIFEST.MF", b"manifest"), ("liblwjgl.so", b"lwjgl"), ("sub/libopenal.so", b"openal")]);
+
+        let manager = NativesManager::new(&format!("{dir}/natives"));
+        manager.extract(&[native("lwjgl-platform", &jar, &["META-INF/"], false)]).unwrap();
+        assert_eq!(fs::read(format!("{dir}/natives/liblwjgl.so")).unwrap(), b"lwjgl");
+        assert_eq!(fs::read(format!("{dir}/natives/sub/libopenal.so")).unwrap(), b"openal");
+        assert!(!Path::new(&format!("{dir}/natives/META-INF")).exists());
+    }
+
+    #[test]
+    fn flattens_classified_natives() {
+        let dir = temp_dir("natives-flatten");
+        let jar = format!("{dir}/lwjgl-natives-linux.jar");
+        write_zip(&jar, &[
+            ("META-INF/MANIFEST.MF", b"manifest"),
+            ("linux/x64/org/lwjgl/liblwjgl.so", b"lwjgl"),
+            ("linux/x64/org/lwjgl/liblwjgl.so.sha1", b"sha1"),
+            ("org/lwjgl/Version.class", b"class"),
+        ]);
+
+        let manager = NativesManager::new(&format!("{dir}/natives"));
+        manager.extract(&[native("org.lwjgl:lwjgl:3.3.1:natives-linux", &jar, &[], true)]).unwrap();
+        let mut names: Vec<String> = fs::read_dir(format!("{dir}/natives")).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, [STAMP_FILE, "liblwjgl.so"]);
+        assert_eq!(fs::read(format!("{dir}/natives/liblwjgl.so")).unwrap(), b"lwjgl");
+    }
+
+    #[test]
+    fn skips_unchanged_natives() {
+        let dir = temp_dir("natives-stamp");
+        let jar = format!("{dir}/lwjgl-platform.jar");
+        write_zip(&jar, &[("liblwjgl.so", b"lwjgl")]);
+        let natives = [native("lwjgl-platform", &jar, &[], false)];
+        let manager = NativesManager::new(&format!("{dir}/natives"));
+        manager.extract(&natives).unwrap();
+
+        // 内容没有变化时不会重新解压，目录中的其他文件保留
+        let marker = format!("{dir}/natives/marker");
+        write_file(&marker, "marker");
+        manager.extract(&natives).unwrap();
+        assert!(Path::new(&marker).exists());
+
+        // jar变化后清空目录重新解压
+        write_zip(&jar, &[("liblwjgl.so", b"lwjgl 2"), ("libopenal.so", b"openal")]);
+        manager.extract(&natives).unwrap();
+        assert!(!Path::new(&marker).exists());
+        assert_eq!(fs::read(format!("{dir}/natives/liblwjgl.so")).unwrap(), b"lwjgl 2");
+
+        // 排除列表变化也会重新解压
+        write_file(&marker, "marker");
+        manager.extract(&[native("lwjgl-platform", &jar, &["libopenal.so"], false)]).unwrap();
+        assert!(!Path::new(&marker).exists());
+        assert!(!Path::new(&format!("{dir}/natives/libopenal.so")).exists());
+
+        let missing = [native("missing", &format!("{dir}/missing.jar"), &[], false)];
+        assert!(matches!(manager.extract(&missing), Err(CoreError::MissingLibrary { .. })));
+    }
+
+    #[test]
+    fn rejects_entries_outside_directory() {
+        let dir = temp_dir("natives-zip-slip");
+        let jar = format!("{dir}/evil.jar");
+        write_zip(&jar, &[("liblwjgl.so", b"lwjgl"), ("../evil.so", b"evil")]);
+
+        let manager = NativesManager::new(&format!("{dir}/natives"));
+        let result = manager.extract(&[native("evil", &jar, &[], false)]);
+        assert!(matches!(result, Err(CoreError::CorruptZip { .. })));
+        assert!(!Path::new(&format!("{dir}/evil.so")).exists());
+        // 没有写入标记，下次启动会重新解压
+        assert!(!Path::new(&format!("{dir}/natives/{STAMP_FILE}")).exists());
+    }
+
+    #[test]
+    fn removes_other_natives_directories() {
+        let dir = temp_dir("natives-cleanup");
+        for name in ["natives-linux-x86_64", "natives", "natives-windows-x86", "saves"] {
+            write_file(&format!("{dir}/{name}/file"), "file");
+        }
+        write_file(&format!("{dir}/natives.txt"), "file");
+
+        NativesManager::new(&format!("{dir}/natives-linux-x86_64")).cleanup_old().unwrap();
+        let mut names: Vec<String> = fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["natives-linux-x86_64", "natives.txt", "saves"]);
+    }
+}
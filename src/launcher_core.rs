@@ -1,20 +1,36 @@
-use std::fmt::format;
+use crate::error::Result;
+use crate::installed_version::{self, InstalledVersion};
 
+#[derive(Debug, Clone)]
 pub struct LauncherCore {
     pub base_path: String,
     pub assets_path: String,
+    /// authlib-injector的jar路径，外置登录时使用
+    pub authlib_injector_path: String,
 }
 
 impl LauncherCore {
     /// 初始化启动核心的路径
     pub fn new(path: String) -> Self {
-        Self { base_path: path.clone(), assets_path: format!("{}", path) }
+        Self { base_path: path.clone(), authlib_injector_path: format!("{path}/authlib-injector.jar"), assets_path: path }
     }
     
     /// 手动设置资源文件（包括libraries）的路径
     /// 
     /// 该方法可以让所有mc游戏共用同一个资源文件
-    pub fn set_assets_path(mut self, path: String) {
+    pub fn set_assets_path(&mut self, path: String) {
         self.assets_path = path;
     }
-}
\ No newline at end of file
+
+    /// 手动设置authlib-injector的jar路径
+    pub fn set_authlib_injector_path(&mut self, path: String) {
+        self.authlib_injector_path = path;
+    }
+
+    /// 列出`versions`文件夹中安装的所有版本，包括无法启动的版本
+    ///
+    /// 结果按照发布时间从新到旧排序，详见[`installed_version::scan_versions`]
+    pub fn list_versions(&self) -> Result<Vec<InstalledVersion>> {
+        installed_version::scan_versions(&self.base_path)
+    }
+}
@@ -0,0 +1,12 @@
+pub mod error;
+pub mod users;
+pub mod tools;
+pub mod game_version;
+pub mod game_assets;
+pub mod launcher_core;
+pub mod installed_version;
+pub mod json;
+pub mod platform;
+pub mod download;
+pub mod launch;
+pub mod install;
@@ -1,26 +1,48 @@
-mod users;
-mod tools;
-mod game_version;
-mod launcher_core;
-mod json;
-
-use std::process::Command;
-
-use users::developer_user_type::DeveloperUserType;
-use tools::uuid_tools::{uuid_from_name};
-use crate::{game_version::GameVersion, launcher_core::LauncherCore, tools::file_tools::lib_name_to_path};
+use bakaxl_core_minecraft::users::developer_user_type::DeveloperUserType;
+use bakaxl_core_minecraft::tools::uuid_tools::uuid_from_name;
+use bakaxl_core_minecraft::launch::game_process::GameEvent;
+use bakaxl_core_minecraft::launch::launch_options::LaunchOptions;
+use bakaxl_core_minecraft::{game_version::GameVersion, launcher_core::LauncherCore};
 
 fn main() {
-    //println!("{}", test1.name());
     let test1 = DeveloperUserType::login("ZhaiShu");
     let test2 = uuid_from_name("test".to_owned());
     println!("{}", test2.as_str());
     let core: LauncherCore = LauncherCore::new("C:/mc/.minecraft".to_owned());
-    let test3 = GameVersion::load(core, "1.18.2".to_owned());
+    let test3 = match GameVersion::load(core, "1.18.2".to_owned()) {
+        Ok(version) => version,
+        Err(err) => {
+            eprintln!("{err}");
+            return;
+        }
+    };
     println!("{}", test3.version_json.id.clone());
     //println!("{}", test3.get_arguments().join(" "));
-    //println!("{}", lib_name_to_path(test3.get_libraries_and_natives().libs[0].name.clone()));
-    //println!("{}", test3.launch(test1))
-    test3.launch(test1)
+    //println!("{}", lib_name_to_path(&test3.get_libraries_and_natives(&RuleEnvironment::current()).libs[0].name));
+    let process = match test3.launch(test1, &LaunchOptions::new().with_max_memory(4096)) {
+        Ok(process) => process,
+        Err(err) => {
+            eprintln!("{err}");
+            return;
+        }
+    };
+    let mut output: Vec<String> = vec![];
+    for event in process.events() {
+        match event {
+            GameEvent::Stdout(line) | GameEvent::Stderr(line) => {
+                println!("{line}");
+                output.push(line);
+            }
+            GameEvent::WindowReady => println!("Game window is ready"),
+            GameEvent::Exited(code) => {
+                println!("Game exited with {code:?}");
+                break;
+            }
+        }
+    }
+    if let Some(analysis) = process.analyze_crash(&output) {
+        for diagnosis in analysis.diagnoses {
+            eprintln!("{diagnosis}");
+        }
+    }
 }
-
@@ -0,0 +1,281 @@
+use std::collections::HashSet;
+use std::env::consts::OS;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::{CoreError, Result};
+use crate::tools::system_tools;
+
+/// Java可执行文件的名字
+pub const JAVA_EXECUTABLE: &str = if cfg!(windows) { "java.exe" } else { "java" };
+
+/// 一个已安装的Java运行时
+#[derive(Debug, Clone)]
+pub struct JavaRuntime {
+    /// java可执行文件的路径
+    pub path: String,
+    /// 完整的版本号，例如`17.0.7`、`1.8.0_372`
+    pub version: String,
+    /// 主版本号，例如`17`、`8`
+    pub major_version: u32,
+    pub vendor: Option<String>,
+    /// Java的`os.arch`，例如`amd64`、`x86_64`、`aarch64`
+    pub arch: Option<String>,
+}
+
+impl JavaRuntime {
+    /// 读取java可执行文件的版本信息
+    ///
+    /// 优先读取Java目录下的`release`文件，没有时运行`java -XshowSettings:properties -version`
+    pub fn from_executable(path: &str) -> Result<Self> {
+        if !Path::new(path).is_file() {
+            return Err(CoreError::NoJavaFound);
+        }
+        let release = Path::new(path).parent()
+            .and_then(Path::parent)
+            .and_then(|home| fs::read_to_string(home.join("release")).ok());
+        if let Some(runtime) = release.and_then(|release| Self::from_release(path, &release)) {
+            return Ok(runtime);
+        }
+        Self::from_properties(path)
+    }
+
+    /// 解析`release`文件，例如`JAVA_VERSION="17.0.7"`
+    fn from_release(path: &str, release: &str) -> Option<Self> {
+        let value = |key: &str| {
+            release.lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+                .map(|value| value.trim().trim_matches('"').to_owned())
+        };
+        let version = value("JAVA_VERSION")?;
+        Some(Self {
+            path: path.to_owned(),
+            major_version: parse_major_version(&version)?,
+            version,
+            vendor: value("IMPLEMENTOR"),
+            arch: value("OS_ARCH"),
+        })
+    }
+
+    /// 运行java读取系统属性
+    fn from_properties(path: &str) -> Result<Self> {
+        let output = Command::new(path)
+            .args(["-XshowSettings:properties", "-version"])
+            .output()
+            .map_err(|_| CoreError::NoJavaFound)?;
+        // 系统属性和版本信息都输出在stderr中
+        let text = String::from_utf8_lossy(&output.stderr);
+        let property = |key: &str| {
+            text.lines()
+                .find_map(|line| line.trim().strip_prefix(key)?.trim_start().strip_prefix('='))
+                .map(|value| value.trim().to_owned())
+        };
+        let version = property("java.version")
+            .or_else(|| {
+                // 旧版本的Java不支持-XshowSettings，从`java version "1.6.0_45"`中读取
+                text.lines().next()?.split('"').nth(1).map(str::to_owned)
+            })
+            .ok_or(CoreError::NoJavaFound)?;
+        Ok(Self {
+            path: path.to_owned(),
+            major_version: parse_major_version(&version).ok_or(CoreError::NoJavaFound)?,
+            version,
+            vendor: property("java.vendor"),
+            arch: property("os.arch"),
+        })
+    }
+
+    /// 是否是64位的Java，不知道架构时视为64位
+    pub fn is_64_bit(&self) -> bool {
+        match self.arch.as_deref() {
+            Some(arch) => arch.contains("64"),
+            None => true,
+        }
+    }
+}
+
+/// 从版本号中解析主版本号，`1.8.0_372`为8，`17.0.7`为17
+pub fn parse_major_version(version: &str) -> Option<u32> {
+    let mut parts = version.split(|c: char| !c.is_ascii_digit()).filter(|part| !part.is_empty());
+    let first: u32 = parts.next()?.parse().ok()?;
+    if first == 1 {
+        parts.next()?.parse().ok()
+    } else {
+        Some(first)
+    }
+}
+
+/// 搜索系统中安装的Java
+///
+/// 会检查`JAVA_HOME`、`PATH`、系统常见的安装目录、sdkman，以及`extra_roots`中的目录（例如启动器自己的`runtime`目录）
+pub fn discover_java(extra_roots: &[&str]) -> Vec<JavaRuntime> {
+    let mut candidates: Vec<PathBuf> = vec![];
+
+    if let Ok(java_home) = std::env::var("JAVA_HOME") {
+        candidates.push(Path::new(&java_home).join("bin").join(JAVA_EXECUTABLE));
+    }
+    if let Some(path) = std::env::var_os("PATH") {
+        candidates.extend(std::env::split_paths(&path).map(|dir| dir.join(JAVA_EXECUTABLE)));
+    }
+
+    let home = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).unwrap_or_default();
+    let mut roots: Vec<PathBuf> = match OS {
+        "linux" => vec!["/usr/lib/jvm".into(), "/usr/lib64/jvm".into(), "/usr/java".into(), "/opt/java".into(), "/opt/jdk".into()],
+        "macos" => vec!["/Library/Java/JavaVirtualMachines".into(), Path::new(&home).join("Library/Java/JavaVirtualMachines")],
+        "windows" => ["ProgramFiles", "ProgramFiles(x86)"].iter()
+            .filter_map(|key| std::env::var(key).ok())
+            .flat_map(|dir| {
+                ["Java", "Eclipse Adoptium", "Eclipse Foundation", "Zulu", "Microsoft", "BellSoft", "Amazon Corretto"]
+                    .map(|vendor| Path::new(&dir).join(vendor))
+            })
+            .collect(),
+        _ => vec![],
+    };
+    if !home.is_empty() {
+        roots.push(Path::new(&home).join(".sdkman/candidates/java"));
+        roots.push(Path::new(&home).join(".jdks"));
+    }
+    for root in roots {
+        candidates.extend(find_java_in(&root, 1));
+    }
+    for root in extra_roots {
+        candidates.extend(find_java_in(Path::new(root), 4));
+    }
+
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    candidates.into_iter()
+        .filter(|candidate| candidate.is_file())
+        .filter(|candidate| seen.insert(fs::canonicalize(candidate).unwrap_or_else(|_| candidate.clone())))
+        .filter_map(|candidate| JavaRuntime::from_executable(&candidate.to_string_lossy()).ok())
+        .collect()
+}
+
+/// 在`root`下`depth`层以内的目录中查找java可执行文件
+fn find_java_in(root: &Path, depth: u32) -> Vec<PathBuf> {
+    let mut found = vec![];
+    for home in [root.to_path_buf(), root.join("Contents/Home"), root.join("jre.bundle/Contents/Home")] {
+        let java = home.join("bin").join(JAVA_EXECUTABLE);
+        if java.is_file() {
+            found.push(java);
+            return found;
+        }
+    }
+    if depth == 0 {
+        return found;
+    }
+    if let Ok(entries) = fs::read_dir(root) {
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                found.extend(find_java_in(&entry.path(), depth - 1));
+            }
+        }
+    }
+    found
+}
+
+/// 选择满足版本要求的Java
+///
+/// 优先选择主版本号完全一致的；没有时选择比要求高的版本中最低的一个（Java 8及以下必须完全一致）。
+/// 没有版本要求时优先使用Java 8。同样条件下优先使用和系统架构一致的Java
+pub fn select_java(runtimes: &[JavaRuntime], required: Option<u32>) -> Result<&JavaRuntime> {
+    select_java_for_arch(runtimes, required, system_tools::os_arch().contains("64"))
+}
+
+/// 和`select_java`一样，系统是否是64位由参数指定
+fn select_java_for_arch(runtimes: &[JavaRuntime], required: Option<u32>, system_64_bit: bool) -> Result<&JavaRuntime> {
+    if runtimes.is_empty() {
+        return Err(CoreError::NoJavaFound);
+    }
+    let preference = |runtime: &JavaRuntime| (runtime.is_64_bit() != system_64_bit, runtime.major_version);
+    let required_version = required.unwrap_or(8);
+
+    if let Some(runtime) = runtimes.iter().filter(|runtime| runtime.major_version == required_version).min_by_key(|runtime| preference(runtime)) {
+        return Ok(runtime);
+    }
+    let newer = runtimes.iter().filter(|runtime| runtime.major_version > required_version);
+    match required {
+        Some(required) if required > 8 => newer.min_by_key(|runtime| preference(runtime)).ok_or(CoreError::NoSuitableJava { required }),
+        Some(required) => Err(CoreError::NoSuitableJava { required }),
+        None => runtimes.iter().min_by_key(|runtime| preference(runtime)).ok_or(CoreError::NoJavaFound),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runtime(path: &str, major_version: u32, arch: &str) -> JavaRuntime {
+        JavaRuntime {
+            path: path.to_owned(),
+            version: major_version.to_string(),
+            major_version,
+            vendor: None,
+            arch: Some(arch.to_owned()),
+        }
+    }
+
+    fn selected(runtimes: &[JavaRuntime], required: Option<u32>, system_64_bit: bool) -> Option<&str> {
+        select_java_for_arch(runtimes, required, system_64_bit).ok().map(|runtime| runtime.path.as_str())
+    }
+
+    #[test]
+    fn parses_major_versions() {
+        assert_eq!(parse_major_version("1.8.0_392"), Some(8));
+        assert_eq!(parse_major_version("1.6.0_45"), Some(6));
+        assert_eq!(parse_major_version("17.0.2"), Some(17));
+        assert_eq!(parse_major_version("21-ea"), Some(21));
+        assert_eq!(parse_major_version("21"), Some(21));
+        assert_eq!(parse_major_version("1"), None);
+        assert_eq!(parse_major_version("ea"), None);
+    }
+
+    #[test]
+    fn parses_release_file() {
+        let release = "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"17.0.7\"\nOS_ARCH=\"aarch64\"\n";
+        let runtime = JavaRuntime::from_release("/jdk/bin/java", release).unwrap();
+        assert_eq!(runtime.path, "/jdk/bin/java");
+        assert_eq!(runtime.version, "17.0.7");
+        assert_eq!(runtime.major_version, 17);
+        assert_eq!(runtime.vendor.as_deref(), Some("Eclipse Adoptium"));
+        assert_eq!(runtime.arch.as_deref(), Some("aarch64"));
+        assert!(runtime.is_64_bit());
+
+        let runtime = JavaRuntime::from_release("/jre/bin/java", "JAVA_VERSION=\"1.8.0_392\"").unwrap();
+        assert_eq!(runtime.major_version, 8);
+        assert_eq!(runtime.vendor, None);
+        assert!(JavaRuntime::from_release("/jre/bin/java", "IMPLEMENTOR=\"Oracle\"").is_none());
+    }
+
+    #[test]
+    fn prefers_exact_major_version() {
+        let runtimes = [runtime("21", 21, "amd64"), runtime("17", 17, "amd64"), runtime("8", 8, "amd64")];
+        assert_eq!(selected(&runtimes, Some(17), true), Some("17"));
+        assert_eq!(selected(&runtimes, Some(8), true), Some("8"));
+        assert_eq!(selected(&runtimes, None, true), Some("8"));
+    }
+
+    #[test]
+    fn falls_back_to_lowest_newer_version() {
+        let runtimes = [runtime("21", 21, "amd64"), runtime("17", 17, "amd64"), runtime("8", 8, "amd64")];
+        assert_eq!(selected(&runtimes, Some(16), true), Some("17"));
+        assert_eq!(selected(&runtimes, Some(18), true), Some("21"));
+        assert!(matches!(select_java_for_arch(&runtimes, Some(22), true), Err(CoreError::NoSuitableJava { required: 22 })));
+        // Java 8及以下必须完全一致
+        let runtimes = [runtime("17", 17, "amd64")];
+        assert!(matches!(select_java_for_arch(&runtimes, Some(8), true), Err(CoreError::NoSuitableJava { required: 8 })));
+        assert_eq!(selected(&runtimes, None, true), Some("17"));
+        assert!(matches!(select_java_for_arch(&[], Some(17), true), Err(CoreError::NoJavaFound)));
+    }
+
+    #[test]
+    fn prefers_system_arch() {
+        let runtimes = [runtime("17-x86", 17, "x86"), runtime("17-x64", 17, "amd64")];
+        assert_eq!(selected(&runtimes, Some(17), true), Some("17-x64"));
+        assert_eq!(selected(&runtimes, Some(17), false), Some("17-x86"));
+        // 架构不一致也优先选择主版本号一致的
+        let runtimes = [runtime("17-x86", 17, "x86"), runtime("21-x64", 21, "amd64")];
+        assert_eq!(selected(&runtimes, Some(17), true), Some("17-x86"));
+    }
+}
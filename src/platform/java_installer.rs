@@ -0,0 +1,349 @@
+use std::env::consts::{ARCH, OS};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::download::download_task::DownloadTask;
+use crate::download::downloader::Downloader;
+use crate::error::{CoreError, Result};
+use crate::json::json_runtime::{JsonRuntimeAll, JsonRuntimeManifest};
+use crate::tools::file_tools::is_safe_relative_path;
+
+use super::java_discovery::{JavaRuntime, JAVA_EXECUTABLE};
+
+/// Mojang的Java运行时总清单
+pub const JAVA_RUNTIME_MANIFEST_URL: &str =
+    "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+/// 当前系统在Mojang运行时清单中的平台名，Mojang没有提供的平台返回`None`
+pub fn runtime_platform() -> Option<&'static str> {
+    match (OS, ARCH) {
+        ("linux", "x86_64") => Some("linux"),
+        ("linux", "x86") => Some("linux-i386"),
+        ("macos", "x86_64") => Some("mac-os"),
+        ("macos", "aarch64") => Some("mac-os-arm64"),
+        ("windows", "x86_64") => Some("windows-x64"),
+        ("windows", "x86") => Some("windows-x86"),
+        ("windows", "aarch64") => Some("windows-arm64"),
+        _ => None,
+    }
+}
+
+/// 需要下载后解压的文件
+struct LzmaFile {
+    /// 下载的`.lzma`文件
+    compressed: String,
+    /// 解压后的文件，用于校验
+    task: DownloadTask,
+}
+
+/// 安装Mojang提供的Java运行时（`java-runtime-gamma`、`jre-legacy`等）
+///
+/// 运行时会安装到`<runtime_dir>/<component>/<platform>`
+pub struct JavaRuntimeInstaller {
+    /// 运行时的安装目录，一般为`<base>/runtime`
+    pub runtime_dir: String,
+    /// `all.json`的地址
+    pub manifest_url: String,
+    /// Mojang清单中的平台名，例如`linux`、`windows-x64`
+    pub platform: String,
+}
+
+impl JavaRuntimeInstaller {
+    pub fn new(runtime_dir: &str) -> Self {
+        Self {
+            runtime_dir: runtime_dir.to_owned(),
+            manifest_url: JAVA_RUNTIME_MANIFEST_URL.to_owned(),
+            platform: runtime_platform().unwrap_or_default().to_owned(),
+        }
+    }
+
+    pub fn with_manifest_url(mut self, manifest_url: &str) -> Self {
+        self.manifest_url = manifest_url.to_owned();
+        self
+    }
+
+    pub fn with_platform(mut self, platform: &str) -> Self {
+        self.platform = platform.to_owned();
+        self
+    }
+
+    /// 组件的安装目录
+    pub fn component_dir(&self, component: &str) -> String {
+        format!("{}/{component}/{}", self.runtime_dir, self.platform)
+    }
+
+    /// 组件中java可执行文件的路径，macOS的运行时在`jre.bundle`中
+    pub fn java_path(&self, component: &str) -> String {
+        if self.platform.starts_with("mac-os") {
+            format!("{}/jre.bundle/Contents/Home/bin/java", self.component_dir(component))
+        } else {
+            format!("{}/bin/{JAVA_EXECUTABLE}", self.component_dir(component))
+        }
+    }
+
+    /// 已经安装的运行时，没有安装时返回`None`
+    pub fn installed_runtime(&self, component: &str) -> Option<JavaRuntime> {
+        JavaRuntime::from_executable(&self.java_path(component)).ok()
+    }
+
+    /// 下载并安装运行时，已经存在并且完整的文件会跳过
+    pub fn install(&self, component: &str, downloader: &Downloader) -> Result<JavaRuntime> {
+        let unavailable = || CoreError::RuntimeUnavailable { component: component.to_owned(), platform: self.platform.clone() };
+        let all: JsonRuntimeAll = downloader.get_json(&self.manifest_url)?;
+        let entry = all.get(&self.platform)
+            .and_then(|components| components.get(component))
+            .and_then(|entries| entries.first())
+            .ok_or_else(unavailable)?;
+
+        let manifest_path = format!("{}/manifests/{component}-{}.json", self.runtime_dir, self.platform);
+        let manifest_task = DownloadTask::from_json(&entry.manifest, &manifest_path).ok_or_else(unavailable)?;
+        downloader.download(&manifest_task)?;
+        let contents = fs::read_to_string(&manifest_path).map_err(|err| CoreError::io(&manifest_path, err))?;
+        let manifest: JsonRuntimeManifest = serde_json::from_str(&contents).map_err(|err| CoreError::parse_json(&manifest_path, err))?;
+
+        let component_dir = self.component_dir(component);
+        let mut tasks: Vec<DownloadTask> = vec![];
+        let mut lzma_files: Vec<LzmaFile> = vec![];
+        let mut executables: Vec<String> = vec![];
+        let mut links: Vec<(String, String)> = vec![];
+        for (name, file) in &manifest.files {
+            // 清单中的文件名和链接都不能指向组件目录之外
+            let unsafe_path = |name: &str| CoreError::UnsafePath { root: component_dir.clone(), name: name.to_owned() };
+            if !is_safe_relative_path(name) {
+                return Err(unsafe_path(name));
+            }
+            let path = format!("{component_dir}/{name}");
+            match file.type_.as_str() {
+                "directory" => fs::create_dir_all(&path).map_err(|err| CoreError::io(&path, err))?,
+                "link" => {
+                    let Some(target) = &file.target else {
+                        continue;
+                    };
+                    if !is_link_inside(name, target) {
+                        return Err(unsafe_path(&format!("{name} -> {target}")));
+                    }
+                    links.push((path, target.clone()));
+                }
+                "file" => {
+                    let downloads = file.downloads.as_ref();
+                    let Some(raw) = downloads.and_then(|downloads| downloads.get("raw")).and_then(|raw| DownloadTask::from_json(raw, &path)) else {
+                        continue;
+                    };
+                    if file.executable {
+                        executables.push(path.clone());
+                    }
+                    if raw.is_complete() {
+                        continue;
+                    }
+                    let compressed = format!("{path}.lzma");
+                    match downloads.and_then(|downloads| downloads.get("lzma")).and_then(|lzma| DownloadTask::from_json(lzma, &compressed)) {
+                        Some(lzma) => {
+                            tasks.push(lzma);
+                            lzma_files.push(LzmaFile { compressed, task: raw });
+                        }
+                        None => tasks.push(raw),
+                    }
+                }
+                _ => { }
+            }
+        }
+
+        downloader.download_all(&tasks)?;
+        for lzma_file in &lzma_files {
+            decompress_lzma(&lzma_file.compressed, &lzma_file.task.path)?;
+            let _ = fs::remove_file(&lzma_file.compressed);
+            lzma_file.task.verify(&lzma_file.task.path)?;
+        }
+        for executable in &executables {
+            set_executable(executable)?;
+        }
+        for (path, target) in &links {
+            create_link(path, target)?;
+        }
+        fs::write(format!("{component_dir}/.version"), &entry.version.name).map_err(|err| CoreError::io(&component_dir, err))?;
+
+        JavaRuntime::from_executable(&self.java_path(component))
+    }
+}
+
+/// 链接`name`指向的`target`（相对于链接所在目录）是否仍然在组件目录中
+fn is_link_inside(name: &str, target: &str) -> bool {
+    if target.starts_with(['/', '\\']) || target.contains(':') {
+        return false;
+    }
+    let parts = |path: &str| path.split(['/', '\\']).filter(|part| !part.is_empty() && *part != ".").map(str::to_owned).collect::<Vec<_>>();
+    let mut depth = parts(name).len() as isize - 1;
+    for part in parts(target) {
+        depth += if part == ".." { -1 } else { 1 };
+        if depth < 0 {
+            return false;
+        }
+    }
+    true
+}
+
+/// 解压`.lzma`文件
+fn decompress_lzma(source: &str, target: &str) -> Result<()> {
+    let input = File::open(source).map_err(|err| CoreError::io(source, err))?;
+    let mut output = File::create(target).map_err(|err| CoreError::io(target, err))?;
+    lzma_rs::lzma_decompress(&mut BufReader::new(input), &mut output)
+        .map_err(|err| CoreError::Decompress { path: source.to_owned(), message: err.to_string() })
+}
+
+#[cfg(unix)]
+fn set_executable(path: &str) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).map_err(|err| CoreError::io(path, err))
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &str) -> Result<()> {
+    Ok(())
+}
+
+/// 创建符号链接，`target`是相对于链接所在目录的路径
+#[cfg(unix)]
+fn create_link(path: &str, target: &str) -> Result<()> {
+    if let Some(parent_dir) = Path::new(path).parent() {
+        fs::create_dir_all(parent_dir).map_err(|err| CoreError::io(parent_dir.to_string_lossy(), err))?;
+    }
+    let _ = fs::remove_file(path);
+    std::os::unix::fs::symlink(target, path).map_err(|err| CoreError::io(path, err))
+}
+
+/// Windows下创建符号链接需要管理员权限，复制目标文件代替
+#[cfg(not(unix))]
+fn create_link(path: &str, target: &str) -> Result<()> {
+    let source = Path::new(path).parent().map(|parent| parent.join(target)).unwrap_or_else(|| target.into());
+    if source.is_file() {
+        fs::copy(&source, path).map_err(|err| CoreError::io(path, err))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{json, Value};
+
+    use crate::tools::hash_tools::sha1_bytes;
+    use crate::tools::test_tools::{temp_dir, MockResponse, MockServer};
+
+    use super::*;
+
+    const COMPONENT: &str = "java-runtime-gamma";
+
+    /// 提供`all.json`、组件清单和文件的本地服务器
+    struct RuntimeServer {
+        server: MockServer,
+        files: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    }
+
+    impl RuntimeServer {
+        fn start() -> Self {
+            let files: Arc<Mutex<HashMap<String, Vec<u8>>>> = Arc::default();
+            let served = files.clone();
+            let server = MockServer::start(move |request| match served.lock().unwrap().get(&request.path) {
+                Some(contents) => MockResponse::new(200, contents.clone()),
+                None => MockResponse::new(404, ""),
+            });
+            Self { server, files }
+        }
+
+        fn serve(&self, path: &str, contents: impl Into<Vec<u8>>) -> Value {
+            let contents = contents.into();
+            let download = json!({ "url": format!("{}{path}", self.server.url), "sha1": sha1_bytes(&contents), "size": contents.len() });
+            self.files.lock().unwrap().insert(path.to_owned(), contents);
+            download
+        }
+
+        /// 发布组件清单，返回`all.json`的地址
+        fn publish(&self, files: Value) -> String {
+            let manifest = self.serve("/manifest.json", json!({ "files": files }).to_string());
+            self.serve("/all.json", json!({
+                "linux": { COMPONENT: [{ "manifest": manifest, "version": { "name": "17.0.8" } }] }
+            }).to_string());
+            format!("{}/all.json", self.server.url)
+        }
+
+        fn raw_file(&self, name: &str, contents: &[u8], executable: bool) -> Value {
+            json!({ "type": "file", "executable": executable, "downloads": { "raw": self.serve(&format!("/{name}"), contents) } })
+        }
+    }
+
+    fn installer(runtime_dir: &str, manifest_url: &str) -> JavaRuntimeInstaller {
+        JavaRuntimeInstaller::new(runtime_dir).with_manifest_url(manifest_url).with_platform("linux")
+    }
+
+    #[test]
+    fn installs_files_directories_and_links() {
+        let server = RuntimeServer::start();
+        let library = b"native library".repeat(64);
+        let mut compressed = vec![];
+        lzma_rs::lzma_compress(&mut library.as_slice(), &mut compressed).unwrap();
+        let library_file = json!({
+            "type": "file",
+            "downloads": { "raw": server.serve("/lib/libjli.so", library.clone()), "lzma": server.serve("/lib/libjli.so.lzma", compressed) }
+        });
+        let manifest_url = server.publish(json!({
+            "bin": { "type": "directory" },
+            "bin/java": server.raw_file("bin/java", b"#!/bin/sh\n", true),
+            "release": server.raw_file("release", b"JAVA_VERSION=\"17.0.8\"\nIMPLEMENTOR=\"Microsoft\"\n", false),
+            "lib": { "type": "directory" },
+            "lib/libjli.so": library_file,
+            "legal/java.base/LICENSE": { "type": "link", "target": "../../lib/libjli.so" },
+        }));
+
+        let runtime_dir = temp_dir("java-runtime");
+        let installer = installer(&runtime_dir, &manifest_url);
+        let runtime = installer.install(COMPONENT, &Downloader::new().with_retries(0)).unwrap();
+        assert_eq!(runtime.major_version, 17);
+        assert_eq!(runtime.vendor.as_deref(), Some("Microsoft"));
+
+        let component_dir = installer.component_dir(COMPONENT);
+        assert_eq!(runtime.path, format!("{component_dir}/bin/java"));
+        assert_eq!(fs::read(format!("{component_dir}/lib/libjli.so")).unwrap(), library);
+        assert!(!Path::new(&format!("{component_dir}/lib/libjli.so.lzma")).exists());
+        assert_eq!(fs::read_to_string(format!("{component_dir}/.version")).unwrap(), "17.0.8");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |name: &str| fs::metadata(format!("{component_dir}/{name}")).unwrap().permissions().mode();
+            assert_eq!(mode("bin/java") & 0o111, 0o111);
+            assert_eq!(mode("release") & 0o111, 0);
+            let link = format!("{component_dir}/legal/java.base/LICENSE");
+            assert_eq!(fs::read_link(&link).unwrap(), Path::new("../../lib/libjli.so"));
+            assert_eq!(fs::read(&link).unwrap(), library);
+        }
+    }
+
+    #[test]
+    fn rejects_paths_outside_component() {
+        let server = RuntimeServer::start();
+        for files in [
+            json!({ "../../evil": server.raw_file("evil", b"evil", false) }),
+            json!({ "/tmp/evil": server.raw_file("evil", b"evil", false) }),
+            json!({ "bin/../../../evil": { "type": "directory" } }),
+            json!({ "legal/LICENSE": { "type": "link", "target": "../../../../etc/passwd" } }),
+            json!({ "legal/LICENSE": { "type": "link", "target": "/etc/passwd" } }),
+        ] {
+            let manifest_url = server.publish(files.clone());
+            let runtime_dir = temp_dir("java-runtime-unsafe");
+            let err = installer(&runtime_dir, &manifest_url).install(COMPONENT, &Downloader::new().with_retries(0)).unwrap_err();
+            assert!(matches!(err, CoreError::UnsafePath { .. }), "{files}: {err:?}");
+            assert!(!Path::new(&format!("{runtime_dir}/evil")).exists());
+        }
+    }
+
+    #[test]
+    fn checks_link_targets() {
+        assert!(is_link_inside("legal/java.base/LICENSE", "../../lib/libjli.so"));
+        assert!(is_link_inside("bin/java", "java-real"));
+        assert!(!is_link_inside("legal/LICENSE", "../../LICENSE"));
+        assert!(!is_link_inside("LICENSE", "../LICENSE"));
+        assert!(!is_link_inside("lib/link", "C:\\Windows"));
+    }
+}
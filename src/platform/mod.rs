@@ -0,0 +1,3 @@
+pub mod rule_evaluator;
+pub mod java_discovery;
+pub mod java_installer;
\ No newline at end of file
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::json::json_version::{JsonOperatingSystem, JsonRule};
+use crate::tools::system_tools;
+
+/// 演示模式
+pub const FEATURE_IS_DEMO_USER: &str = "is_demo_user";
+/// 自定义窗口大小
+pub const FEATURE_HAS_CUSTOM_RESOLUTION: &str = "has_custom_resolution";
+/// 支持快速游戏
+pub const FEATURE_HAS_QUICK_PLAYS_SUPPORT: &str = "has_quick_plays_support";
+/// 快速进入单人世界
+pub const FEATURE_IS_QUICK_PLAY_SINGLEPLAYER: &str = "is_quick_play_singleplayer";
+/// 快速进入服务器
+pub const FEATURE_IS_QUICK_PLAY_MULTIPLAYER: &str = "is_quick_play_multiplayer";
+/// 快速进入Realms
+pub const FEATURE_IS_QUICK_PLAY_REALMS: &str = "is_quick_play_realms";
+
+/// 判断规则时使用的运行环境
+///
+/// 默认使用当前系统的信息，也可以手动构造，用于在一个平台上判断其他平台的规则
+#[derive(Debug, Clone)]
+pub struct RuleEnvironment {
+    /// Mojang json中的系统名：`windows`、`osx`、`linux`
+    pub os_name: String,
+    /// 系统版本号，用于匹配`os.version`正则
+    pub os_version: String,
+    /// 系统架构，和Mojang规则中的一致，例如`x86`、`x86_64`、`arm64`
+    pub os_arch: String,
+    /// 启动器开启的功能，没有写在这里的功能视为关闭
+    pub features: HashMap<String, bool>,
+}
+
+impl RuleEnvironment {
+    /// 获取当前系统的运行环境，所有功能默认关闭
+    pub fn current() -> Self {
+        Self {
+            os_name: system_tools::os_name(),
+            os_version: system_tools::os_version(),
+            os_arch: system_tools::os_arch(),
+            features: HashMap::new(),
+        }
+    }
+
+    /// 手动指定运行环境，`os_name`和`os_arch`可以是Rust的名字（`macos`、`aarch64`），也可以是Mojang的名字（`osx`、`arm64`）
+    pub fn new(os_name: &str, os_version: &str, os_arch: &str) -> Self {
+        Self {
+            os_name: system_tools::mojang_os_name(os_name),
+            os_version: os_version.to_owned(),
+            os_arch: system_tools::mojang_arch(os_arch),
+            features: HashMap::new(),
+        }
+    }
+
+    /// 开启或关闭一个功能，例如`has_custom_resolution`
+    pub fn with_feature(mut self, name: &str, enabled: bool) -> Self {
+        self.features.insert(name.to_owned(), enabled);
+        self
+    }
+
+    /// 功能是否开启
+    pub fn has_feature(&self, name: &str) -> bool {
+        self.features.get(name).copied().unwrap_or(false)
+    }
+}
+
+impl JsonRule {
+    /// 规则的条件是否和运行环境匹配，不考虑`action`
+    pub fn matches(&self, env: &RuleEnvironment) -> bool {
+        if let Some(os) = &self.os {
+            if !os.matches(env) {
+                return false;
+            }
+        }
+        if let Some(features) = &self.features {
+            if features.iter().any(|(name, enabled)| env.has_feature(name) != *enabled) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl JsonOperatingSystem {
+    /// 系统名、版本号、架构是否都和运行环境匹配
+    pub fn matches(&self, env: &RuleEnvironment) -> bool {
+        if let Some(name) = &self.name {
+            if system_tools::mojang_os_name(name) != env.os_name {
+                return false;
+            }
+        }
+        if let Some(version) = &self.version {
+            if !version_matches(version, &env.os_version) {
+                return false;
+            }
+        }
+        if let Some(arch) = &self.arch {
+            if !arch_matches(arch, &env.os_arch) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 系统版本号是否匹配`os.version`正则
+///
+/// 和官方启动器一样在版本号中查找，Mojang json中的正则自己带有`^`和`$`，例如`^10\.`、`^10\.5\.\d$`。
+/// 正则无效时视为不匹配
+pub fn version_matches(pattern: &str, os_version: &str) -> bool {
+    Regex::new(pattern).is_ok_and(|regex| regex.is_match(os_version))
+}
+
+/// 架构是否匹配`os.arch`
+///
+/// 两边都转换为Mojang的架构名后完整比较，`x86`不能匹配`x86_64`
+pub fn arch_matches(arch: &str, os_arch: &str) -> bool {
+    system_tools::mojang_arch(arch) == system_tools::mojang_arch(os_arch)
+}
+
+/// 按照官方启动器的算法判断一组规则是否允许
+///
+/// 没有规则时允许；有规则时默认不允许，按顺序检查每条规则，最后一条匹配的规则决定结果
+pub fn is_allowed(rules: Option<&[JsonRule]>, env: &RuleEnvironment) -> bool {
+    let Some(rules) = rules.filter(|rules| !rules.is_empty()) else {
+        return true;
+    };
+    let mut allowed = false;
+    for rule in rules {
+        if rule.matches(env) {
+            allowed = rule.action == "allow";
+        }
+    }
+    allowed
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn rules(rules: serde_json::Value) -> Vec<JsonRule> {
+        serde_json::from_value(rules).unwrap()
+    }
+
+    fn allowed(rules_json: serde_json::Value, env: &RuleEnvironment) -> bool {
+        is_allowed(Some(&rules(rules_json)), env)
+    }
+
+    #[test]
+    fn maps_os_names() {
+        let mac = RuleEnvironment::new("macos", "13.4", "arm64");
+        assert_eq!(mac.os_name, "osx");
+        assert!(allowed(json!([{ "action": "allow", "os": { "name": "osx" } }]), &mac));
+        assert!(!allowed(json!([{ "action": "allow", "os": { "name": "windows" } }]), &mac));
+
+        let windows = RuleEnvironment::new("windows", "10.0.19045", "x86_64");
+        assert!(allowed(json!([{ "action": "allow", "os": { "name": "windows" } }]), &windows));
+        let linux = RuleEnvironment::new("linux", "6.1.0", "x86_64");
+        assert!(allowed(json!([{ "action": "allow", "os": { "name": "linux" } }]), &linux));
+        assert!(!allowed(json!([{ "action": "allow", "os": { "name": "osx" } }]), &linux));
+    }
+
+    #[test]
+    fn matches_os_version_regex() {
+        let rules_json = json!([{ "action": "allow", "os": { "name": "windows", "version": "^10\\." } }]);
+        assert!(allowed(rules_json.clone(), &RuleEnvironment::new("windows", "10.0.19045", "x86_64")));
+        assert!(!allowed(rules_json, &RuleEnvironment::new("windows", "6.1.7601", "x86_64")));
+
+        // 没有`^`和`$`时在版本号中查找
+        assert!(version_matches("10\\.5", "10.10.5"));
+        assert!(version_matches("^10\\.5\\.\\d$", "10.5.8"));
+        assert!(!version_matches("^10\\.5\\.\\d$", "10.5.10"));
+        assert!(!version_matches("[", "10.5.8"));
+    }
+
+    #[test]
+    fn matches_arch() {
+        let rules_json = json!([{ "action": "allow", "os": { "arch": "x86" } }]);
+        assert!(allowed(rules_json.clone(), &RuleEnvironment::new("windows", "10.0", "x86")));
+        // `x86`不能匹配`x86_64`
+        assert!(!allowed(rules_json, &RuleEnvironment::new("windows", "10.0", "x86_64")));
+        assert!(allowed(json!([{ "action": "allow", "os": { "arch": "arm64" } }]), &RuleEnvironment::new("osx", "13.4", "arm64")));
+    }
+
+    #[test]
+    fn matches_features() {
+        let rules_json = json!([{ "action": "allow", "features": { "has_custom_resolution": true } }]);
+        let env = RuleEnvironment::new("linux", "6.1.0", "x86_64");
+        assert!(!allowed(rules_json.clone(), &env));
+        assert!(allowed(rules_json.clone(), &env.clone().with_feature(FEATURE_HAS_CUSTOM_RESOLUTION, true)));
+        assert!(!allowed(rules_json, &env.with_feature(FEATURE_HAS_CUSTOM_RESOLUTION, false)));
+    }
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let rules_json = json!([
+            { "action": "allow" },
+            { "action": "disallow", "os": { "name": "osx" } },
+        ]);
+        assert!(allowed(rules_json.clone(), &RuleEnvironment::new("linux", "6.1.0", "x86_64")));
+        assert!(!allowed(rules_json, &RuleEnvironment::new("osx", "13.4", "arm64")));
+
+        let rules_json = json!([
+            { "action": "disallow", "os": { "name": "osx" } },
+            { "action": "allow" },
+        ]);
+        assert!(allowed(rules_json, &RuleEnvironment::new("osx", "13.4", "arm64")));
+        assert!(is_allowed(None, &RuleEnvironment::new("osx", "13.4", "arm64")));
+        assert!(is_allowed(Some(&[]), &RuleEnvironment::new("osx", "13.4", "arm64")));
+    }
+
+    #[test]
+    fn uses_mojang_arch_names() {
+        assert_eq!(system_tools::mojang_arch("x86_64"), "x86_64");
+        assert_eq!(system_tools::mojang_arch("x64"), "x86_64");
+        assert_eq!(system_tools::mojang_arch("amd64"), "x86_64");
+        assert_eq!(system_tools::mojang_arch("aarch64"), "arm64");
+        assert_eq!(system_tools::mojang_arch("x86"), "x86");
+        assert_eq!(system_tools::mojang_arch("i686"), "x86");
+
+        assert!(arch_matches("x86_64", "x64"));
+        assert!(arch_matches("arm64", "aarch64"));
+        assert!(!arch_matches("x86", "x86_64"));
+        assert!(!arch_matches("x86", "arm64"));
+        assert_eq!(RuleEnvironment::new("osx", "13.4", "aarch64").os_arch, "arm64");
+    }
+}
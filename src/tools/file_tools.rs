@@ -1,15 +1,224 @@
-use std::path::PathBuf;
-
-pub fn lib_name_to_path(name: String) -> String {
-    let parts: Vec<&str> = name.split(':').collect();
-    let artifact_id = parts[1];
-    let version = parts[2];
-    let classifier = parts[3];
-    let filename = format!("{}-{}-{}.jar", artifact_id, version, classifier);
-    let mut path = PathBuf::new();
-    path.push(parts[0].replace(".", "/"));
-    path.push(artifact_id);
-    path.push(version);
-    path.push(filename);
-    path.to_string_lossy().into_owned()
-}
\ No newline at end of file
+use std::fmt;
+use std::path::{Component, Path};
+use std::str::FromStr;
+
+use crate::error::CoreError;
+
+/// Maven坐标，格式为`group:artifact:version[:classifier][@extension]`
+///
+/// 例如`net.minecraftforge:forge:1.20.1-47.1.0:installer`、`de.oceanlabs.mcp:mcp_config:1.20.1@zip`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MavenCoordinate {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version: String,
+    pub classifier: Option<String>,
+    /// 文件扩展名，默认为`jar`
+    pub extension: String,
+}
+
+impl MavenCoordinate {
+    pub fn new(group_id: &str, artifact_id: &str, version: &str) -> Self {
+        Self {
+            group_id: group_id.to_owned(),
+            artifact_id: artifact_id.to_owned(),
+            version: version.to_owned(),
+            classifier: None,
+            extension: "jar".to_owned(),
+        }
+    }
+
+    pub fn with_classifier(mut self, classifier: &str) -> Self {
+        self.classifier = Some(classifier.to_owned());
+        self
+    }
+
+    pub fn with_extension(mut self, extension: &str) -> Self {
+        self.extension = extension.to_owned();
+        self
+    }
+
+    /// 是否是快照版本，包括`1.0-SNAPSHOT`和带时间戳的`1.0-20230101.123456-1`
+    pub fn is_snapshot(&self) -> bool {
+        self.base_version() != self.version || self.version.ends_with("-SNAPSHOT")
+    }
+
+    /// 目录使用的版本号，带时间戳的快照版本会转换为`-SNAPSHOT`
+    pub fn base_version(&self) -> String {
+        let parts: Vec<&str> = self.version.rsplitn(3, '-').collect();
+        if let [build, timestamp, base] = parts.as_slice() {
+            let is_timestamp = timestamp.len() == 15
+                && timestamp.as_bytes()[8] == b'.'
+                && timestamp.chars().filter(|c| *c != '.').all(|c| c.is_ascii_digit());
+            if is_timestamp && build.parse::<u32>().is_ok() {
+                return format!("{base}-SNAPSHOT");
+            }
+        }
+        self.version.clone()
+    }
+
+    /// 去重使用的`group:artifact[:classifier]`，不包含版本号
+    pub fn key(&self) -> String {
+        match &self.classifier {
+            Some(classifier) => format!("{}:{}:{classifier}", self.group_id, self.artifact_id),
+            None => format!("{}:{}", self.group_id, self.artifact_id),
+        }
+    }
+
+    /// 文件名，例如`forge-1.20.1-47.1.0-installer.jar`
+    pub fn file_name(&self) -> String {
+        match &self.classifier {
+            Some(classifier) => format!("{}-{}-{classifier}.{}", self.artifact_id, self.version, self.extension),
+            None => format!("{}-{}.{}", self.artifact_id, self.version, self.extension),
+        }
+    }
+
+    /// 相对于仓库根目录（或者`libraries`目录）的路径
+    pub fn path(&self) -> String {
+        format!("{}/{}/{}/{}", self.group_id.replace('.', "/"), self.artifact_id, self.base_version(), self.file_name())
+    }
+
+    /// 在`repository`仓库中的下载地址
+    pub fn url(&self, repository: &str) -> String {
+        format!("{}/{}", repository.trim_end_matches('/'), self.path())
+    }
+}
+
+impl FromStr for MavenCoordinate {
+    type Err = CoreError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let invalid = || CoreError::InvalidMavenCoordinate(name.to_owned());
+        let (coordinate, extension) = match name.split_once('@') {
+            Some((coordinate, extension)) => (coordinate, extension),
+            None => (name, "jar"),
+        };
+        let parts: Vec<&str> = coordinate.split(':').collect();
+        if parts.iter().any(|part| part.is_empty()) || extension.is_empty() {
+            return Err(invalid());
+        }
+        let coordinate = match parts.as_slice() {
+            [group_id, artifact_id, version] => Self::new(group_id, artifact_id, version),
+            [group_id, artifact_id, version, classifier] => Self::new(group_id, artifact_id, version).with_classifier(classifier),
+            _ => return Err(invalid()),
+        };
+        Ok(coordinate.with_extension(extension))
+    }
+}
+
+impl fmt::Display for MavenCoordinate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.group_id, self.artifact_id, self.version)?;
+        if let Some(classifier) = &self.classifier {
+            write!(f, ":{classifier}")?;
+        }
+        if self.extension != "jar" {
+            write!(f, "@{}", self.extension)?;
+        }
+        Ok(())
+    }
+}
+
+/// 把库的名字转换为相对于`libraries`目录的路径，名字不是合法的Maven坐标时返回`None`
+pub fn lib_name_to_path(name: &str) -> Option<String> {
+    name.parse::<MavenCoordinate>().ok().map(|coordinate| coordinate.path())
+}
+
+/// 检查索引或清单中的相对路径，不能是绝对路径，也不能包含`..`
+///
+/// 同时按`/`和`\`分隔检查，保证在所有系统上都不会写到目标目录之外
+pub fn is_safe_relative_path(path: &str) -> bool {
+    !path.is_empty()
+        && !path.starts_with(['/', '\\'])
+        && !path.contains(':')
+        && path.split(['/', '\\']).all(|part| part != "..")
+        && Path::new(path).components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(name: &str) -> MavenCoordinate {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_plain_coordinate() {
+        let coordinate = parse("org.ow2.asm:asm:9.5");
+        assert_eq!(coordinate, MavenCoordinate::new("org.ow2.asm", "asm", "9.5"));
+        assert_eq!(coordinate.path(), "org/ow2/asm/asm/9.5/asm-9.5.jar");
+        assert_eq!(coordinate.key(), "org.ow2.asm:asm");
+    }
+
+    #[test]
+    fn parses_classifier_and_extension() {
+        let coordinate = parse("net.minecraftforge:forge:1.20.1-47.1.0:installer");
+        assert_eq!(coordinate.classifier.as_deref(), Some("installer"));
+        assert_eq!(coordinate.path(), "net/minecraftforge/forge/1.20.1-47.1.0/forge-1.20.1-47.1.0-installer.jar");
+
+        let coordinate = parse("de.oceanlabs.mcp:mcp_config:1.20.1-20230612.114412@zip");
+        assert_eq!(coordinate.extension, "zip");
+        assert_eq!(coordinate.path(), "de/oceanlabs/mcp/mcp_config/1.20.1-20230612.114412/mcp_config-1.20.1-20230612.114412.zip");
+
+        let coordinate = parse("net.minecraft:client:1.20.1-20230612.114412:mappings@txt");
+        assert_eq!(coordinate.file_name(), "client-1.20.1-20230612.114412-mappings.txt");
+    }
+
+    #[test]
+    fn handles_snapshot_versions() {
+        let coordinate = parse("com.example:lib:1.0-SNAPSHOT");
+        assert!(coordinate.is_snapshot());
+        assert_eq!(coordinate.path(), "com/example/lib/1.0-SNAPSHOT/lib-1.0-SNAPSHOT.jar");
+
+        let coordinate = parse("com.example:lib:1.0-20230101.123456-3");
+        assert!(coordinate.is_snapshot());
+        assert_eq!(coordinate.base_version(), "1.0-SNAPSHOT");
+        assert_eq!(coordinate.path(), "com/example/lib/1.0-SNAPSHOT/lib-1.0-20230101.123456-3.jar");
+
+        assert!(!parse("org.ow2.asm:asm:9.5").is_snapshot());
+    }
+
+    #[test]
+    fn builds_repository_url() {
+        let coordinate = parse("net.fabricmc:fabric-loader:0.14.21");
+        let expected = "https://maven.fabricmc.net/net/fabricmc/fabric-loader/0.14.21/fabric-loader-0.14.21.jar";
+        assert_eq!(coordinate.url("https://maven.fabricmc.net/"), expected);
+        assert_eq!(coordinate.url("https://maven.fabricmc.net"), expected);
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        for name in [
+            "org.ow2.asm:asm:9.5",
+            "org.lwjgl:lwjgl:3.3.1:natives-linux",
+            "de.oceanlabs.mcp:mcp_config:1.20.1-20230612.114412@zip",
+            "net.minecraft:client:1.20.1:mappings@txt",
+            "com.example:lib:1.0-SNAPSHOT",
+        ] {
+            assert_eq!(parse(name).to_string(), name);
+            assert_eq!(parse(&parse(name).to_string()), parse(name));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_coordinates() {
+        for name in ["", "org.ow2.asm", "org.ow2.asm:asm", "org.ow2.asm::9.5", "a:b:c:d:e", "a:b:c@"] {
+            assert!(name.parse::<MavenCoordinate>().is_err(), "{name}");
+        }
+        assert_eq!(lib_name_to_path("org.ow2.asm"), None);
+    }
+
+    #[test]
+    fn checks_relative_paths() {
+        assert!(is_safe_relative_path("minecraft/sounds/ambient/cave/cave1.ogg"));
+        assert!(is_safe_relative_path("bin/java"));
+        assert!(!is_safe_relative_path(""));
+        assert!(!is_safe_relative_path("../../evil.txt"));
+        assert!(!is_safe_relative_path("sounds/../../evil.txt"));
+        assert!(!is_safe_relative_path("sounds\\..\\..\\evil.txt"));
+        assert!(!is_safe_relative_path("/etc/passwd"));
+        assert!(!is_safe_relative_path("\\\\server\\share"));
+        assert!(!is_safe_relative_path("C:\\Windows\\evil.dll"));
+    }
+}
@@ -0,0 +1,34 @@
+use std::fs::File;
+use std::io::Read;
+
+use crypto::{digest::Digest, sha1::Sha1, sha2::Sha256};
+
+/// 计算数据的SHA-1，返回小写的十六进制字符串
+pub fn sha1_bytes(input: &[u8]) -> String {
+    let mut sha1 = Sha1::new();
+    sha1.input(input);
+    sha1.result_str()
+}
+
+/// 计算文件的SHA-1，返回小写的十六进制字符串
+pub fn sha1_file(path: &str) -> std::io::Result<String> {
+    digest_file(path, Sha1::new())
+}
+
+/// 计算文件的SHA-256，返回小写的十六进制字符串
+pub fn sha256_file(path: &str) -> std::io::Result<String> {
+    digest_file(path, Sha256::new())
+}
+
+fn digest_file(path: &str, mut digest: impl Digest) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        digest.input(&buffer[..read]);
+    }
+    Ok(digest.result_str())
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+
+use crate::error::{CoreError, Result};
+
+/// 创建一个默认配置的HTTP客户端
+pub fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(15))
+        .timeout_read(Duration::from_secs(30))
+        .user_agent("BakaXL")
+        .build()
+}
+
+/// 把HTTP错误转换为`CoreError`，服务器返回错误状态码时为`CoreError::HttpStatus`
+pub fn http_error(url: &str, err: ureq::Error) -> CoreError {
+    match err {
+        ureq::Error::Status(status, _) => CoreError::HttpStatus { url: url.to_owned(), status },
+        err => CoreError::Download { url: url.to_owned(), message: err.to_string() },
+    }
+}
+
+/// 读取响应内容并解析为json
+pub fn read_json<T: DeserializeOwned>(url: &str, response: ureq::Response) -> Result<T> {
+    let body = response.into_string().map_err(|err| CoreError::Download { url: url.to_owned(), message: err.to_string() })?;
+    serde_json::from_str(&body).map_err(|err| CoreError::parse_json(url, err))
+}
+
+/// GET请求并解析json
+pub fn get_json<T: DeserializeOwned>(agent: &ureq::Agent, url: &str) -> Result<T> {
+    let response = agent.get(url).call().map_err(|err| http_error(url, err))?;
+    read_json(url, response)
+}
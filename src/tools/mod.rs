@@ -1,4 +1,8 @@
 pub mod uuid_tools;
 pub mod system_tools;
 pub mod file_tools;
-pub mod string_tools;
\ No newline at end of file
+pub mod string_tools;
+pub mod hash_tools;
+pub mod http_tools;
+#[cfg(test)]
+pub mod test_tools;
\ No newline at end of file
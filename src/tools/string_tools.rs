@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
 pub fn replace_variables(input_string: &str, variables: &HashMap<&str, String>) -> String {
@@ -9,4 +10,79 @@ pub fn replace_variables(input_string: &str, variables: &HashMap<&str, String>)
     }
 
     replaced_string
-}
\ No newline at end of file
+}
+
+/// 比较两个版本号，数字部分按数值比较，例如`9.2`小于`9.10`
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let split = |version: &str| -> Vec<String> {
+        version.split(['.', '-', '_', '+']).map(str::to_owned).collect()
+    };
+    let (a_parts, b_parts) = (split(a), split(b));
+    for (a_part, b_part) in a_parts.iter().zip(&b_parts) {
+        let ordering = match (a_part.parse::<u64>(), b_part.parse::<u64>()) {
+            (Ok(a_number), Ok(b_number)) => a_number.cmp(&b_number),
+            // 数字部分比字母部分（如`beta`）更新
+            (Ok(_), Err(_)) => Ordering::Greater,
+            (Err(_), Ok(_)) => Ordering::Less,
+            (Err(_), Err(_)) => compare_labels(a_part, b_part),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    // `1.0-beta`比`1.0`旧，`1.0.1`比`1.0`新
+    let is_release_part = |part: Option<&String>| part.is_some_and(|part| part.parse::<u64>().is_ok());
+    match a_parts.len().cmp(&b_parts.len()) {
+        Ordering::Greater if !is_release_part(a_parts.get(b_parts.len())) => Ordering::Less,
+        Ordering::Less if !is_release_part(b_parts.get(a_parts.len())) => Ordering::Greater,
+        ordering => ordering,
+    }
+}
+
+/// 比较`pre2`、`rc1`这样的标签，先比较字母部分，再按数值比较后面的数字
+fn compare_labels(a: &str, b: &str) -> Ordering {
+    let split = |label: &str| {
+        let index = label.find(|c: char| c.is_ascii_digit()).unwrap_or(label.len());
+        (label[..index].to_owned(), label[index..].parse::<u64>().ok())
+    };
+    split(a).cmp(&split(b)).then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_versions() {
+        let cases = [
+            ("1.10", "1.9", Ordering::Greater),
+            ("9.2", "9.10", Ordering::Less),
+            ("1.20.1", "1.20", Ordering::Greater),
+            ("1.20", "1.20.0.0", Ordering::Less),
+            ("1.20.1", "1.20.1", Ordering::Equal),
+            ("1.20-pre1", "1.20", Ordering::Less),
+            ("1.20-pre2", "1.20-pre10", Ordering::Less),
+            ("1.20-rc1", "1.20-pre7", Ordering::Greater),
+            ("1.20-rc1", "1.20", Ordering::Less),
+            ("1.20-rc1", "1.19.4", Ordering::Greater),
+            ("23w13a", "23w14a", Ordering::Less),
+            ("23w13a", "23w13b", Ordering::Less),
+            ("0.14.21", "0.14.9", Ordering::Greater),
+            ("47.1.0", "47.0.35", Ordering::Greater),
+            ("1.0.0-beta.2", "1.0.0-beta.11", Ordering::Less),
+            ("1.0.0-beta.11", "1.0.0", Ordering::Less),
+            ("1.8.0_372", "1.8.0_51", Ordering::Greater),
+        ];
+        for (a, b, expected) in cases {
+            assert_eq!(compare_versions(a, b), expected, "{a} vs {b}");
+            assert_eq!(compare_versions(b, a), expected.reverse(), "{b} vs {a}");
+        }
+    }
+
+    #[test]
+    fn sorts_versions() {
+        let mut versions = vec!["1.9", "1.10", "1.10-pre1", "1.9.4", "1.10.2", "1.10-pre2"];
+        versions.sort_by(|a, b| compare_versions(a, b));
+        assert_eq!(versions, ["1.9", "1.9.4", "1.10-pre1", "1.10-pre2", "1.10", "1.10.2"]);
+    }
+}
@@ -1,8 +1,50 @@
-pub fn arch() -> String {
-    let pointer_size = std::mem::size_of::<usize>();
-    if pointer_size == 8 {
-        "x64".to_owned()
-    } else {
-        "x86".to_owned()
+use std::env::consts::{ARCH, OS};
+use std::process::Command;
+
+/// 把Rust的系统名转换为Mojang json中使用的系统名（`windows`、`osx`、`linux`）
+pub fn mojang_os_name(os: &str) -> String {
+    match os {
+        "macos" => "osx".to_owned(),
+        other => other.to_owned(),
     }
-}
\ No newline at end of file
+}
+
+/// 当前系统在Mojang json中的名字
+pub fn os_name() -> String {
+    mojang_os_name(OS)
+}
+
+/// 把Rust的架构名以及常见的别名转换为Mojang规则中使用的架构名（`x86`、`x86_64`、`arm64`）
+pub fn mojang_arch(arch: &str) -> String {
+    match arch {
+        "x64" | "amd64" => "x86_64".to_owned(),
+        "i386" | "i586" | "i686" => "x86".to_owned(),
+        "aarch64" => "arm64".to_owned(),
+        "arm" => "arm32".to_owned(),
+        other => other.to_owned(),
+    }
+}
+
+/// 当前系统在Mojang规则中的架构名
+pub fn os_arch() -> String {
+    mojang_arch(ARCH)
+}
+
+/// 当前系统的版本号，获取失败时返回空字符串
+pub fn os_version() -> String {
+    let output = match OS {
+        "linux" => return std::fs::read_to_string("/proc/sys/kernel/osrelease").unwrap_or_default().trim().to_owned(),
+        "macos" => Command::new("sw_vers").arg("-productVersion").output(),
+        "windows" => Command::new("cmd").args(["/C", "ver"]).output(),
+        _ => return String::new(),
+    };
+    let Ok(output) = output else {
+        return String::new();
+    };
+    let text = String::from_utf8_lossy(&output.stdout);
+    // Windows的输出类似`Microsoft Windows [Version 10.0.19045.2965]`，不同语言的系统前缀不一样，只取数字部分
+    text.split(|c: char| c.is_whitespace() || c == '[' || c == ']')
+        .find(|part| part.starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or_default()
+        .to_owned()
+}
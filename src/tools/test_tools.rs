@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde_json::{json, Value};
+
+use crate::tools::file_tools::MavenCoordinate;
+
+static TEMP_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// 创建一个空的临时目录，每次调用都返回不同的目录
+pub fn temp_dir(name: &str) -> String {
+    let index = TEMP_DIR_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let path = std::env::temp_dir().join(format!("bakaxl-test-{}-{index}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// 写入文件，自动创建上级目录
+pub fn write_file(path: &str, contents: impl AsRef<[u8]>) {
+    if let Some(parent_dir) = Path::new(path).parent() {
+        fs::create_dir_all(parent_dir).unwrap();
+    }
+    fs::write(path, contents).unwrap();
+}
+
+/// 最小的原版版本json，发布时间为1.20.1的发布时间
+pub fn vanilla_version_json(id: &str) -> Value {
+    json!({
+        "id": id,
+        "time": "2023-06-12T13:25:51+00:00",
+        "releaseTime": "2023-06-12T13:25:51+00:00",
+        "type": "release",
+        "mainClass": "net.minecraft.client.main.Main",
+        "arguments": { "game": [], "jvm": [] }
+    })
+}
+
+/// 写入`versions/<id>/<id>.json`
+pub fn write_version_json(base_path: &str, id: &str, json: &Value) {
+    write_file(&format!("{base_path}/versions/{id}/{id}.json"), json.to_string());
+}
+
+/// 写入原版的版本json和游戏本体jar，jar的内容为`client`
+pub fn write_vanilla_version(base_path: &str, id: &str) {
+    write_version_json(base_path, id, &vanilla_version_json(id));
+    write_file(&format!("{base_path}/versions/{id}/{id}.jar"), "client");
+}
+
+/// 测试服务器收到的请求
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    /// 请求头，名字为小写
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl MockRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+}
+
+/// 测试服务器返回的响应
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self { status, headers: vec![], body: body.into() }
+    }
+
+    pub fn json(status: u16, body: &serde_json::Value) -> Self {
+        Self::new(status, body.to_string()).with_header("Content-Type", "application/json")
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+}
+
+/// 只在本机监听的HTTP测试服务器，每个连接处理一个请求
+pub struct MockServer {
+    /// 服务器地址，例如`http://127.0.0.1:12345`
+    pub url: String,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    /// 启动服务器，用`handler`生成每个请求的响应
+    pub fn start(handler: impl Fn(&MockRequest) -> MockResponse + Send + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests: Arc<Mutex<Vec<MockRequest>>> = Arc::default();
+        let received = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let Some(request) = read_request(&mut stream) else { continue };
+                let response = handler(&request);
+                received.lock().unwrap_or_else(|err| err.into_inner()).push(request);
+                let _ = write_response(&mut stream, &response);
+            }
+        });
+        Self { url, requests }
+    }
+
+    /// 启动只提供静态文件的服务器，`files`的键为请求的路径，例如`/v2/versions/loader`
+    pub fn serve_files(files: HashMap<String, Vec<u8>>) -> Self {
+        Self::start(move |request| match files.get(&request.path) {
+            Some(contents) => MockResponse::new(200, contents.clone()),
+            None => MockResponse::new(404, ""),
+        })
+    }
+
+    /// 到目前为止收到的请求
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+}
+
+fn read_request(stream: &mut TcpStream) -> Option<MockRequest> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_owned();
+    let path = parts.next()?.to_owned();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_owned());
+        }
+    }
+    let length = headers.get("content-length").and_then(|length| length.parse().ok()).unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    Some(MockRequest { method, path, headers, body: String::from_utf8_lossy(&body).into_owned() })
+}
+
+fn write_response(stream: &mut TcpStream, response: &MockResponse) -> std::io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n", response.status, response.body.len());
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+/// 创建包含`files`的zip文件，例如测试用的安装器jar
+pub fn write_zip(path: &str, files: &[(&str, &[u8])]) {
+    if let Some(parent_dir) = Path::new(path).parent() {
+        fs::create_dir_all(parent_dir).unwrap();
+    }
+    let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+    for (name, contents) in files {
+        zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+/// 提供加载器meta和Maven仓库的服务器
+///
+/// `meta`为接口路径和对应的`tests/fixtures`文件。`profile`中的库文件也由这个服务器提供，内容为库的名字，
+/// 没有`url`的库使用`default_maven`
+pub fn loader_meta_server(meta: &[(&str, &str)], profile: &str, default_maven: &str) -> MockServer {
+    let mut files: HashMap<String, Vec<u8>> = meta.iter()
+        .map(|(path, name)| (path.to_string(), fixture(name).into_bytes()))
+        .collect();
+    let profile: Value = serde_json::from_str(&fixture(profile)).unwrap();
+    for lib in profile["libraries"].as_array().unwrap() {
+        let name = lib["name"].as_str().unwrap();
+        let url = name.parse::<MavenCoordinate>().unwrap().url(lib["url"].as_str().unwrap_or(default_maven));
+        files.insert(url_path(&url), name.as_bytes().to_vec());
+    }
+    MockServer::serve_files(files)
+}
+
+/// 检查`libraries`中的库都已经下载到`<assets_path>/libraries`，内容为`loader_meta_server`提供的库的名字
+pub fn assert_libraries_downloaded(assets_path: &str, libraries: &[Value]) {
+    for lib in libraries {
+        let name = lib["name"].as_str().unwrap();
+        let path = format!("{assets_path}/libraries/{}", name.parse::<MavenCoordinate>().unwrap().path());
+        assert_eq!(fs::read_to_string(&path).unwrap_or_else(|err| panic!("failed to read {path}: {err}")), name);
+    }
+}
+
+/// 写入可执行的shell脚本，例如代替Java运行安装器的处理器，返回脚本的路径
+#[cfg(unix)]
+pub fn write_script(path: &str, body: &str) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    write_file(path, format!("#!/bin/sh\n{body}\n"));
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    path.to_owned()
+}
+
+/// 去掉地址中的协议和域名，例如`https://maven.fabricmc.net/a/b.jar`变为`/a/b.jar`
+pub fn url_path(url: &str) -> String {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    rest.find('/').map(|start| rest[start..].to_owned()).unwrap_or_else(|| "/".to_owned())
+}
+
+/// 读取`tests/fixtures`中的文件
+pub fn fixture(name: &str) -> String {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    fs::read_to_string(&path).unwrap_or_else(|err| panic!("failed to read {path}: {err}"))
+}
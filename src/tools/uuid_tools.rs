@@ -24,6 +24,5 @@ pub fn uuid_from_bytes(input: &[u8]) -> Uuid {
     bytes.swap(0, 3);
     bytes.swap(1, 2);
 
-    let uuid = Uuid::from_slice(&bytes).unwrap();
-    uuid
+    Uuid::from_slice(&bytes).unwrap()
 }
\ No newline at end of file
@@ -0,0 +1,340 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{Duration, Utc};
+use crypto::aead::{AeadDecryptor, AeadEncryptor};
+use crypto::aes::KeySize;
+use crypto::aes_gcm::AesGcm;
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{CoreError, Result};
+
+use super::custom_auth_user_type::CustomAuthUserType;
+use super::microsoft_user_type::MicrosoftUserType;
+use super::user_types::UserResult;
+
+/// 需要保护的字段
+const SECRET_FIELDS: [&str; 3] = ["access_token", "refresh_token", "client_token"];
+/// 加密后的字段值的前缀
+const ENCRYPTED_PREFIX: &str = "enc:";
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+#[derive(Default, Serialize, Deserialize)]
+struct JsonAccountStore {
+    #[serde(default)]
+    selected: Option<String>,
+    #[serde(default)]
+    accounts: Vec<Value>,
+}
+
+/// 账户存储
+///
+/// 把多个账户保存到一个json文件中，并记住选中的账户。
+/// 文件只有当前用户可以读写；提供了密钥时，令牌会使用AES-256-GCM加密后再保存
+pub struct AccountStore {
+    /// 账户文件的路径
+    pub path: String,
+    pub accounts: Vec<UserResult>,
+    /// 选中账户的`account_key`
+    pub selected: Option<String>,
+    encryption_key: Option<[u8; 32]>,
+    microsoft: Option<MicrosoftUserType>,
+}
+
+impl AccountStore {
+    /// 读取账户文件，文件不存在时返回空的账户存储
+    ///
+    /// `encryption_key`为启动器提供的密钥，任意长度，为`None`时令牌以明文保存
+    pub fn load(path: &str, encryption_key: Option<&[u8]>) -> Result<Self> {
+        let encryption_key = encryption_key.map(|key| {
+            let mut sha256 = Sha256::new();
+            sha256.input(key);
+            let mut hash = [0; 32];
+            sha256.result(&mut hash);
+            hash
+        });
+        let mut store = Self { path: path.to_owned(), accounts: vec![], selected: None, encryption_key, microsoft: None };
+        if !Path::new(path).exists() {
+            return Ok(store);
+        }
+
+        let contents = fs::read_to_string(path).map_err(|err| CoreError::io(path, err))?;
+        let json: JsonAccountStore = serde_json::from_str(&contents).map_err(|err| CoreError::parse_json(path, err))?;
+        for mut account in json.accounts {
+            store.transform_secrets(&mut account, |store, value| store.decrypt(value))?;
+            let account: UserResult = serde_json::from_value(account).map_err(|err| CoreError::parse_json(path, err))?;
+            store.accounts.push(account);
+        }
+        store.selected = json.selected;
+        Ok(store)
+    }
+
+    /// 设置Microsoft登录的配置，刷新Microsoft账户时需要
+    pub fn with_microsoft(mut self, microsoft: MicrosoftUserType) -> Self {
+        self.microsoft = Some(microsoft);
+        self
+    }
+
+    /// 保存到账户文件
+    pub fn save(&self) -> Result<()> {
+        let mut accounts = vec![];
+        for account in &self.accounts {
+            let mut account = serde_json::to_value(account).map_err(|err| CoreError::AccountStore(err.to_string()))?;
+            self.transform_secrets(&mut account, |store, value| store.encrypt(value))?;
+            accounts.push(account);
+        }
+        let json = JsonAccountStore { selected: self.selected.clone(), accounts };
+        let contents = serde_json::to_string_pretty(&json).map_err(|err| CoreError::AccountStore(err.to_string()))?;
+
+        if let Some(parent_dir) = Path::new(&self.path).parent() {
+            fs::create_dir_all(parent_dir).map_err(|err| CoreError::io(parent_dir.to_string_lossy(), err))?;
+        }
+        // 先写入临时文件再重命名，避免写入时崩溃导致账户丢失
+        let temp_path = format!("{}.tmp", self.path);
+        let _ = fs::remove_file(&temp_path);
+        let mut file = create_private_file(&temp_path).map_err(|err| CoreError::io(&temp_path, err))?;
+        file.write_all(contents.as_bytes()).map_err(|err| CoreError::io(&temp_path, err))?;
+        drop(file);
+        fs::rename(&temp_path, &self.path).map_err(|err| CoreError::io(&self.path, err))
+    }
+
+    /// 添加账户并选中它，已经存在的同一个账户会被替换
+    pub fn add_account(&mut self, user: UserResult) {
+        let key = user.account_key();
+        match self.accounts.iter_mut().find(|account| account.account_key() == key) {
+            Some(account) => *account = user,
+            None => self.accounts.push(user),
+        }
+        self.selected = Some(key);
+    }
+
+    /// 删除账户，删除的是选中的账户时取消选中
+    pub fn remove_account(&mut self, key: &str) -> Option<UserResult> {
+        let index = self.accounts.iter().position(|account| account.account_key() == key)?;
+        if self.selected.as_deref() == Some(key) {
+            self.selected = None;
+        }
+        Some(self.accounts.remove(index))
+    }
+
+    /// 选中账户，账户不存在时返回`false`
+    pub fn select(&mut self, key: &str) -> bool {
+        let exists = self.accounts.iter().any(|account| account.account_key() == key);
+        if exists {
+            self.selected = Some(key.to_owned());
+        }
+        exists
+    }
+
+    pub fn selected_account(&self) -> Option<&UserResult> {
+        let key = self.selected.as_deref()?;
+        self.accounts.iter().find(|account| account.account_key() == key)
+    }
+
+    /// 获取选中的账户用于启动，令牌过期时会先刷新并保存
+    pub fn get_launch_account(&mut self) -> Result<UserResult> {
+        let user = self.selected_account().cloned().ok_or_else(|| CoreError::AccountStore("No account selected".to_owned()))?;
+        let refreshed = self.refresh_if_needed(&user)?;
+        if let Some(refreshed) = &refreshed {
+            self.add_account(refreshed.clone());
+            self.save()?;
+        }
+        Ok(refreshed.unwrap_or(user))
+    }
+
+    /// 令牌过期时刷新，返回刷新后的账户；不需要刷新时返回`None`
+    pub fn refresh_if_needed(&self, user: &UserResult) -> Result<Option<UserResult>> {
+        match user {
+            UserResult::Developer { .. } => Ok(None),
+            UserResult::Microsoft { refresh_token, expires_at, .. } => {
+                // 提前五分钟刷新，避免启动过程中过期
+                if *expires_at > Utc::now() + Duration::minutes(5) {
+                    return Ok(None);
+                }
+                let microsoft = self.microsoft.as_ref()
+                    .ok_or_else(|| CoreError::UnsupportedAuth("Microsoft login is not configured".to_owned()))?;
+                microsoft.refresh(refresh_token).map(Some)
+            }
+            UserResult::CustomAuth { access_token, client_token, server_url, .. } => {
+                // 无法连接验证服务器时继续使用保存的令牌，离线时也可以启动
+                let custom_auth = CustomAuthUserType::new(server_url);
+                match custom_auth.validate(server_url, access_token, client_token) {
+                    Ok(true) => return Ok(None),
+                    Ok(false) => { }
+                    Err(err) if is_network_error(&err) => return Ok(None),
+                    Err(err) => return Err(err),
+                }
+                match custom_auth.refresh(server_url, access_token, client_token) {
+                    Ok(user) => Ok(Some(user)),
+                    Err(err) if is_network_error(&err) => Ok(None),
+                    Err(err) => Err(err),
+                }
+            }
+        }
+    }
+
+    /// 对账户json中的令牌字段进行转换（加密或者解密）
+    fn transform_secrets(&self, account: &mut Value, transform: impl Fn(&Self, &str) -> Result<String>) -> Result<()> {
+        let Some(object) = account.as_object_mut() else {
+            return Err(CoreError::AccountStore("Account entry is not an object".to_owned()));
+        };
+        for field in SECRET_FIELDS {
+            if let Some(Value::String(value)) = object.get_mut(field) {
+                *value = transform(self, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encrypt(&self, value: &str) -> Result<String> {
+        let Some(key) = &self.encryption_key else {
+            return Ok(value.to_owned());
+        };
+        // 每次加密都使用系统随机数生成新的nonce
+        let mut nonce = [0; NONCE_SIZE];
+        getrandom::getrandom(&mut nonce).map_err(|err| CoreError::AccountStore(format!("Failed to generate nonce: {err}")))?;
+        let mut cipher = AesGcm::new(KeySize::KeySize256, key, &nonce, &[]);
+        let mut output = vec![0; value.len()];
+        let mut tag = [0; TAG_SIZE];
+        cipher.encrypt(value.as_bytes(), &mut output, &mut tag);
+        let data = [&nonce[..], &output, &tag].concat();
+        Ok(format!("{ENCRYPTED_PREFIX}{}", STANDARD.encode(data)))
+    }
+
+    fn decrypt(&self, value: &str) -> Result<String> {
+        let Some(encoded) = value.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(value.to_owned());
+        };
+        let key = self.encryption_key.as_ref()
+            .ok_or_else(|| CoreError::AccountStore("Accounts are encrypted but no key was provided".to_owned()))?;
+        let data = STANDARD.decode(encoded).map_err(|err| CoreError::AccountStore(err.to_string()))?;
+        if data.len() < NONCE_SIZE + TAG_SIZE {
+            return Err(CoreError::AccountStore("Encrypted value is too short".to_owned()));
+        }
+        let (nonce, rest) = data.split_at(NONCE_SIZE);
+        let (input, tag) = rest.split_at(rest.len() - TAG_SIZE);
+        let mut cipher = AesGcm::new(KeySize::KeySize256, key, nonce, &[]);
+        let mut output = vec![0; input.len()];
+        if !cipher.decrypt(input, &mut output, tag) {
+            return Err(CoreError::AccountStore("Wrong encryption key or corrupted account file".to_owned()));
+        }
+        String::from_utf8(output).map_err(|err| CoreError::AccountStore(err.to_string()))
+    }
+}
+
+/// 是否是网络不可用或者服务器暂时出错
+fn is_network_error(err: &CoreError) -> bool {
+    matches!(err, CoreError::Download { .. }) || matches!(err, CoreError::HttpStatus { status, .. } if *status >= 500)
+}
+
+/// 创建只有当前用户可以读写的文件
+#[cfg(unix)]
+fn create_private_file(path: &str) -> std::io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)
+}
+
+/// Windows下用户目录默认只有当前用户可以访问
+#[cfg(not(unix))]
+fn create_private_file(path: &str) -> std::io::Result<fs::File> {
+    fs::File::create(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use crate::tools::test_tools::temp_dir;
+
+    use super::*;
+
+    const KEY: &[u8] = b"launcher secret";
+
+    fn microsoft_account() -> UserResult {
+        UserResult::Microsoft {
+            username: "Notch".to_owned(),
+            uuid: "069a79f444e94726a5befca90e38aaf5".to_owned(),
+            access_token: "minecraft-access-token".to_owned(),
+            refresh_token: "microsoft-refresh-token".to_owned(),
+            xuid: "2535400000000000".to_owned(),
+            expires_at: Utc::now() + Duration::hours(1),
+        }
+    }
+
+    fn save_account(path: &str) -> AccountStore {
+        let mut store = AccountStore::load(path, Some(KEY)).unwrap();
+        store.add_account(microsoft_account());
+        store.save().unwrap();
+        store
+    }
+
+    #[test]
+    fn round_trips_encrypted_accounts() {
+        let path = format!("{}/accounts.json", temp_dir("accounts"));
+        save_account(&path);
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("minecraft-access-token"));
+        assert!(!contents.contains("microsoft-refresh-token"));
+        assert_eq!(contents.matches(ENCRYPTED_PREFIX).count(), 2);
+
+        let store = AccountStore::load(&path, Some(KEY)).unwrap();
+        assert_eq!(store.selected.as_deref(), Some("microsoft:069a79f444e94726a5befca90e38aaf5"));
+        match store.selected_account() {
+            Some(UserResult::Microsoft { access_token, refresh_token, .. }) => {
+                assert_eq!(access_token, "minecraft-access-token");
+                assert_eq!(refresh_token, "microsoft-refresh-token");
+            }
+            other => panic!("unexpected account: {other:?}"),
+        }
+        assert!(AccountStore::load(&path, Some(b"wrong key")).is_err());
+        assert!(AccountStore::load(&path, None).is_err());
+    }
+
+    #[test]
+    fn uses_a_new_nonce_for_each_value() {
+        let store = AccountStore::load(&format!("{}/accounts.json", temp_dir("nonce")), Some(KEY)).unwrap();
+        let first = store.encrypt("token").unwrap();
+        let second = store.encrypt("token").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(store.decrypt(&first).unwrap(), "token");
+        assert_eq!(store.decrypt(&second).unwrap(), "token");
+    }
+
+    #[test]
+    fn rejects_tampered_file() {
+        let path = format!("{}/accounts.json", temp_dir("accounts-tampered"));
+        save_account(&path);
+
+        let mut json: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let encoded = json["accounts"][0]["access_token"].as_str().unwrap().strip_prefix(ENCRYPTED_PREFIX).unwrap().to_owned();
+        let mut data = STANDARD.decode(encoded).unwrap();
+        data[NONCE_SIZE] ^= 1;
+        json["accounts"][0]["access_token"] = Value::String(format!("{ENCRYPTED_PREFIX}{}", STANDARD.encode(data)));
+        fs::write(&path, json.to_string()).unwrap();
+
+        assert!(matches!(AccountStore::load(&path, Some(KEY)), Err(CoreError::AccountStore(_))));
+    }
+
+    #[test]
+    fn keeps_custom_auth_token_offline() {
+        // 绑定后立刻释放端口，连接会被拒绝
+        let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let user = UserResult::CustomAuth {
+            username: "Steve".to_owned(),
+            uuid: "8667ba71b85a4004af54457a9734eed7".to_owned(),
+            access_token: "cached-token".to_owned(),
+            client_token: "client-token".to_owned(),
+            server_url: format!("http://{address}/api/yggdrasil"),
+            prefetched_metadata: String::new(),
+        };
+        let store = AccountStore::load(&format!("{}/accounts.json", temp_dir("offline")), None).unwrap();
+        assert!(store.refresh_if_needed(&user).unwrap().is_none());
+    }
+}
@@ -0,0 +1,464 @@
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::download::download_task::DownloadTask;
+use crate::download::downloader::Downloader;
+use crate::error::{CoreError, Result};
+use crate::tools::hash_tools::sha256_file;
+use crate::tools::http_tools::{self, http_error, read_json};
+
+use super::user_types::UserResult;
+
+/// authlib-injector最新版本的信息
+pub const AUTHLIB_INJECTOR_LATEST_URL: &str = "https://authlib-injector.yushi.moe/artifact/latest.json";
+
+/// 外置登录账户下的一个角色
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct YggdrasilProfile {
+    pub id: String,
+    pub name: String,
+}
+
+/// 登录成功后的会话
+///
+/// 账户有多个角色并且服务器没有自动选择时，需要调用`select_profile`选择一个角色
+#[derive(Debug, Clone)]
+pub struct YggdrasilSession {
+    pub access_token: String,
+    pub client_token: String,
+    pub available_profiles: Vec<YggdrasilProfile>,
+    pub selected_profile: Option<YggdrasilProfile>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthResponse {
+    access_token: String,
+    client_token: String,
+    #[serde(default)]
+    available_profiles: Vec<YggdrasilProfile>,
+    #[serde(default)]
+    selected_profile: Option<YggdrasilProfile>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct YggdrasilError {
+    #[serde(default)]
+    error: String,
+    #[serde(default)]
+    error_message: String,
+}
+
+#[derive(Deserialize)]
+struct AuthlibInjectorArtifact {
+    download_url: String,
+    checksums: AuthlibInjectorChecksums,
+}
+
+#[derive(Deserialize)]
+struct AuthlibInjectorChecksums {
+    sha256: String,
+}
+
+/// 外置登录（Yggdrasil协议，配合authlib-injector使用）
+pub struct CustomAuthUserType {
+    /// 用户填写的验证服务器地址
+    pub server_url: String,
+    agent: ureq::Agent,
+}
+
+impl CustomAuthUserType {
+    pub fn new(server_url: &str) -> Self {
+        let server_url = if server_url.contains("://") {
+            server_url.to_owned()
+        } else {
+            format!("https://{server_url}")
+        };
+        Self { server_url, agent: http_tools::agent() }
+    }
+
+    /// 获取Yggdrasil API的根地址
+    ///
+    /// 服务器返回了`X-Authlib-Injector-API-Location`时使用它指向的地址
+    pub fn api_root(&self) -> Result<String> {
+        let response = self.agent.get(&self.server_url).call().map_err(|err| http_error(&self.server_url, err))?;
+        let api_root = match response.header("X-Authlib-Injector-API-Location") {
+            Some(location) if location.contains("://") => location.to_owned(),
+            Some(location) if location.starts_with('/') => {
+                let host_end = self.server_url.find("://")
+                    .and_then(|index| self.server_url[index + 3..].find('/').map(|start| index + 3 + start))
+                    .unwrap_or(self.server_url.len());
+                format!("{}{location}", &self.server_url[..host_end])
+            }
+            Some(location) => format!("{}/{location}", self.server_url.trim_end_matches('/')),
+            None => self.server_url.clone(),
+        };
+        Ok(api_root.trim_end_matches('/').to_owned())
+    }
+
+    /// 获取API元数据并进行base64编码，用于`-Dauthlibinjector.yggdrasil.prefetched`
+    pub fn fetch_metadata(&self, api_root: &str) -> Result<String> {
+        let response = self.agent.get(api_root).call().map_err(|err| http_error(api_root, err))?;
+        let body = response.into_string().map_err(|err| CoreError::Download { url: api_root.to_owned(), message: err.to_string() })?;
+        Ok(STANDARD.encode(body))
+    }
+
+    /// 登录，账户有多个角色时由`choose_profile`返回选择的角色序号
+    pub fn login(&self, username: &str, password: &str, choose_profile: impl FnOnce(&[YggdrasilProfile]) -> Option<usize>) -> Result<UserResult> {
+        let api_root = self.api_root()?;
+        let session = self.authenticate(&api_root, username, password)?;
+        match &session.selected_profile {
+            Some(profile) => self.to_user(&api_root, &session.access_token, &session.client_token, profile),
+            None => {
+                let profile = match session.available_profiles.len() {
+                    0 => return Err(CoreError::NoMinecraftProfile),
+                    1 => &session.available_profiles[0],
+                    _ => choose_profile(&session.available_profiles)
+                        .and_then(|index| session.available_profiles.get(index))
+                        .ok_or_else(|| CoreError::Auth("No profile selected".to_owned()))?,
+                };
+                self.select_profile(&api_root, &session, profile)
+            }
+        }
+    }
+
+    /// 使用用户名和密码登录
+    pub fn authenticate(&self, api_root: &str, username: &str, password: &str) -> Result<YggdrasilSession> {
+        let response: AuthResponse = self.post(&format!("{api_root}/authserver/authenticate"), json!({
+            "agent": { "name": "Minecraft", "version": 1 },
+            "username": username,
+            "password": password,
+            "clientToken": uuid::Uuid::new_v4().simple().to_string(),
+            "requestUser": true
+        }))?;
+        Ok(YggdrasilSession {
+            access_token: response.access_token,
+            client_token: response.client_token,
+            available_profiles: response.available_profiles,
+            selected_profile: response.selected_profile,
+        })
+    }
+
+    /// 为会话选择一个角色
+    pub fn select_profile(&self, api_root: &str, session: &YggdrasilSession, profile: &YggdrasilProfile) -> Result<UserResult> {
+        let response: AuthResponse = self.post(&format!("{api_root}/authserver/refresh"), json!({
+            "accessToken": session.access_token,
+            "clientToken": session.client_token,
+            "selectedProfile": profile,
+            "requestUser": true
+        }))?;
+        let profile = response.selected_profile.unwrap_or_else(|| profile.clone());
+        self.to_user(api_root, &response.access_token, &response.client_token, &profile)
+    }
+
+    /// 刷新令牌，返回新的账户信息
+    pub fn refresh(&self, api_root: &str, access_token: &str, client_token: &str) -> Result<UserResult> {
+        let response: AuthResponse = self.post(&format!("{api_root}/authserver/refresh"), json!({
+            "accessToken": access_token,
+            "clientToken": client_token,
+            "requestUser": true
+        }))?;
+        let profile = response.selected_profile.ok_or(CoreError::NoMinecraftProfile)?;
+        self.to_user(api_root, &response.access_token, &response.client_token, &profile)
+    }
+
+    /// 检查令牌是否仍然有效
+    pub fn validate(&self, api_root: &str, access_token: &str, client_token: &str) -> Result<bool> {
+        let url = format!("{api_root}/authserver/validate");
+        let body = json!({ "accessToken": access_token, "clientToken": client_token });
+        match self.request(&url).send_string(&body.to_string()) {
+            Ok(_) => Ok(true),
+            Err(ureq::Error::Status(403, _)) => Ok(false),
+            Err(err) => Err(yggdrasil_error(&url, err)),
+        }
+    }
+
+    /// 注销令牌
+    pub fn invalidate(&self, api_root: &str, access_token: &str, client_token: &str) -> Result<()> {
+        let url = format!("{api_root}/authserver/invalidate");
+        let body = json!({ "accessToken": access_token, "clientToken": client_token });
+        self.request(&url).send_string(&body.to_string())
+            .map(|_| ())
+            .map_err(|err| yggdrasil_error(&url, err))
+    }
+
+    fn to_user(&self, api_root: &str, access_token: &str, client_token: &str, profile: &YggdrasilProfile) -> Result<UserResult> {
+        Ok(UserResult::CustomAuth {
+            username: profile.name.clone(),
+            uuid: profile.id.clone(),
+            access_token: access_token.to_owned(),
+            client_token: client_token.to_owned(),
+            server_url: api_root.to_owned(),
+            prefetched_metadata: self.fetch_metadata(api_root)?,
+        })
+    }
+
+    fn request(&self, url: &str) -> ureq::Request {
+        self.agent.post(url).set("Content-Type", "application/json")
+    }
+
+    fn post<T: DeserializeOwned>(&self, url: &str, body: Value) -> Result<T> {
+        let response = self.request(url).send_string(&body.to_string()).map_err(|err| yggdrasil_error(url, err))?;
+        read_json(url, response)
+    }
+}
+
+/// Yggdrasil接口出错时会返回`error`和`errorMessage`，返回的内容不是json时只报告状态码
+fn yggdrasil_error(url: &str, err: ureq::Error) -> CoreError {
+    match err {
+        ureq::Error::Status(status, response) => match read_json::<YggdrasilError>(url, response) {
+            Ok(error) if !error.error_message.is_empty() => CoreError::Auth(error.error_message),
+            Ok(error) => CoreError::Auth(format!("{url} returned {status} {}", error.error)),
+            Err(_) => CoreError::HttpStatus { url: url.to_owned(), status },
+        },
+        err => http_error(url, err),
+    }
+}
+
+/// 下载最新的authlib-injector到`path`，本地文件已经是最新版本时跳过
+pub fn download_authlib_injector(downloader: &Downloader, path: &str) -> Result<()> {
+    let artifact: AuthlibInjectorArtifact = downloader.get_json(AUTHLIB_INJECTOR_LATEST_URL)?;
+    let expected = artifact.checksums.sha256.to_lowercase();
+    if Path::new(path).exists() && sha256_file(path).is_ok_and(|actual| actual == expected) {
+        return Ok(());
+    }
+    let _ = std::fs::remove_file(path);
+    downloader.download(&DownloadTask::new(&artifact.download_url, path))?;
+    let actual = sha256_file(path).map_err(|err| CoreError::io(path, err))?;
+    if actual != expected {
+        let _ = std::fs::remove_file(path);
+        return Err(CoreError::HashMismatch { path: path.to_owned(), expected, actual });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::tools::test_tools::{temp_dir, write_file, MockRequest, MockResponse, MockServer};
+
+    use super::*;
+
+    const METADATA: &str = r#"{"meta":{"serverName":"Test"},"skinDomains":[]}"#;
+
+    /// 模拟的Yggdrasil验证服务器，API位于`/api/yggdrasil`
+    struct MockYggdrasil {
+        profiles: Vec<Value>,
+        /// 服务器自动选择的角色
+        selected_profile: Option<Value>,
+    }
+
+    impl MockYggdrasil {
+        fn new() -> Self {
+            Self {
+                profiles: vec![
+                    json!({ "id": "8667ba71b85a4004af54457a9734eed7", "name": "Steve" }),
+                    json!({ "id": "ec561538f3fd461daff5086b22154bce", "name": "Alex" }),
+                ],
+                selected_profile: None,
+            }
+        }
+
+        fn respond(&self, request: &MockRequest) -> MockResponse {
+            let body: Value = serde_json::from_str(&request.body).unwrap_or(Value::Null);
+            let forbidden = |message: &str| MockResponse::json(403, &json!({ "error": "ForbiddenOperationException", "errorMessage": message }));
+            match request.path.as_str() {
+                "/" => MockResponse::new(200, "").with_header("X-Authlib-Injector-API-Location", "/api/yggdrasil/"),
+                "/api/yggdrasil" => MockResponse::new(200, METADATA),
+                "/api/yggdrasil/authserver/authenticate" => {
+                    if body["password"] != "password" {
+                        return forbidden("Invalid credentials. Invalid username or password.");
+                    }
+                    assert_eq!(body["agent"]["name"], "Minecraft");
+                    MockResponse::json(200, &json!({
+                        "accessToken": "access-token",
+                        "clientToken": body["clientToken"],
+                        "availableProfiles": self.profiles,
+                        "selectedProfile": self.selected_profile
+                    }))
+                }
+                "/api/yggdrasil/authserver/refresh" => {
+                    if body["accessToken"] != "access-token" {
+                        return forbidden("Invalid token.");
+                    }
+                    let selected_profile = if body["selectedProfile"].is_null() { self.selected_profile.clone() } else { Some(body["selectedProfile"].clone()) };
+                    MockResponse::json(200, &json!({
+                        "accessToken": "refreshed-token",
+                        "clientToken": body["clientToken"],
+                        "selectedProfile": selected_profile
+                    }))
+                }
+                "/api/yggdrasil/authserver/validate" if body["accessToken"] == "access-token" => MockResponse::new(204, ""),
+                "/api/yggdrasil/authserver/validate" => forbidden("Invalid token."),
+                "/api/yggdrasil/authserver/invalidate" => MockResponse::json(500, &json!({ "error": "InternalError" })),
+                _ => MockResponse::new(404, ""),
+            }
+        }
+
+        fn start(self) -> (MockServer, CustomAuthUserType) {
+            let server = MockServer::start(move |request| self.respond(request));
+            let user_type = CustomAuthUserType::new(&server.url);
+            (server, user_type)
+        }
+    }
+
+    #[test]
+    fn discovers_api_location() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/absolute" => MockResponse::new(200, "").with_header("X-Authlib-Injector-API-Location", "https://auth.example.com/api/"),
+            "/root" => MockResponse::new(200, "").with_header("X-Authlib-Injector-API-Location", "/api/yggdrasil/"),
+            "/relative" => MockResponse::new(200, "").with_header("X-Authlib-Injector-API-Location", "api"),
+            _ => MockResponse::new(200, ""),
+        });
+        let api_root = |path: &str| CustomAuthUserType::new(&format!("{}{path}", server.url)).api_root().unwrap();
+        assert_eq!(api_root("/absolute"), "https://auth.example.com/api");
+        assert_eq!(api_root("/root"), format!("{}/api/yggdrasil", server.url));
+        assert_eq!(api_root("/relative"), format!("{}/relative/api", server.url));
+        assert_eq!(api_root("/none/"), format!("{}/none", server.url));
+        assert_eq!(CustomAuthUserType::new("littleskin.cn/api/yggdrasil").server_url, "https://littleskin.cn/api/yggdrasil");
+    }
+
+    #[test]
+    fn logs_in_with_chosen_profile() {
+        let (server, user_type) = MockYggdrasil::new().start();
+        let offered = Arc::new(Mutex::new(vec![]));
+        let result = user_type.login("steve@example.com", "password", |profiles| {
+            offered.lock().unwrap().extend(profiles.iter().map(|profile| profile.name.clone()));
+            Some(1)
+        }).unwrap();
+        assert_eq!(*offered.lock().unwrap(), ["Steve", "Alex"]);
+        let UserResult::CustomAuth { username, uuid, access_token, client_token, server_url, prefetched_metadata } = result else {
+            panic!("expected a custom auth account");
+        };
+        assert_eq!(username, "Alex");
+        assert_eq!(uuid, "ec561538f3fd461daff5086b22154bce");
+        assert_eq!(access_token, "refreshed-token");
+        assert_eq!(client_token.len(), 32);
+        assert_eq!(server_url, format!("{}/api/yggdrasil", server.url));
+        assert_eq!(STANDARD.decode(prefetched_metadata).unwrap(), METADATA.as_bytes());
+
+        let requests = server.requests();
+        let authenticate: Value = serde_json::from_str(&requests.iter().find(|request| request.path.ends_with("/authenticate")).unwrap().body).unwrap();
+        assert_eq!(authenticate["username"], "steve@example.com");
+        let refresh = requests.iter().find(|request| request.path.ends_with("/refresh")).unwrap();
+        assert_eq!(refresh.header("Content-Type"), Some("application/json"));
+        let refresh: Value = serde_json::from_str(&refresh.body).unwrap();
+        assert_eq!(refresh["selectedProfile"]["name"], "Alex");
+        assert_eq!(refresh["clientToken"], client_token);
+    }
+
+    #[test]
+    fn logs_in_with_selected_or_single_profile() {
+        let steve = json!({ "id": "8667ba71b85a4004af54457a9734eed7", "name": "Steve" });
+        let choose = |_: &[YggdrasilProfile]| -> Option<usize> { panic!("should not ask for a profile") };
+
+        let (server, user_type) = MockYggdrasil { selected_profile: Some(steve.clone()), ..MockYggdrasil::new() }.start();
+        let UserResult::CustomAuth { username, access_token, .. } = user_type.login("steve", "password", choose).unwrap() else {
+            panic!("expected a custom auth account");
+        };
+        assert_eq!((username.as_str(), access_token.as_str()), ("Steve", "access-token"));
+        assert!(server.requests().iter().all(|request| !request.path.ends_with("/refresh")));
+
+        let (_server, user_type) = MockYggdrasil { profiles: vec![steve], selected_profile: None }.start();
+        let UserResult::CustomAuth { username, access_token, .. } = user_type.login("steve", "password", choose).unwrap() else {
+            panic!("expected a custom auth account");
+        };
+        assert_eq!((username.as_str(), access_token.as_str()), ("Steve", "refreshed-token"));
+    }
+
+    #[test]
+    fn reports_login_failures() {
+        let (_server, user_type) = MockYggdrasil::new().start();
+        match user_type.login("steve", "wrong", |_| None) {
+            Err(CoreError::Auth(message)) => assert_eq!(message, "Invalid credentials. Invalid username or password."),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        match user_type.login("steve", "password", |_| None) {
+            Err(CoreError::Auth(message)) => assert_eq!(message, "No profile selected"),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        let (_server, user_type) = MockYggdrasil { profiles: vec![], selected_profile: None }.start();
+        assert!(matches!(user_type.login("steve", "password", |_| None), Err(CoreError::NoMinecraftProfile)));
+    }
+
+    #[test]
+    fn refreshes_tokens() {
+        let selected = json!({ "id": "ec561538f3fd461daff5086b22154bce", "name": "Alex" });
+        let (server, user_type) = MockYggdrasil { selected_profile: Some(selected), ..MockYggdrasil::new() }.start();
+        let api_root = format!("{}/api/yggdrasil", server.url);
+        let UserResult::CustomAuth { username, access_token, client_token, .. } = user_type.refresh(&api_root, "access-token", "client-token").unwrap() else {
+            panic!("expected a custom auth account");
+        };
+        assert_eq!((username.as_str(), access_token.as_str(), client_token.as_str()), ("Alex", "refreshed-token", "client-token"));
+
+        match user_type.refresh(&api_root, "expired-token", "client-token") {
+            Err(CoreError::Auth(message)) => assert_eq!(message, "Invalid token."),
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        // 刷新后没有角色
+        let (server, user_type) = MockYggdrasil::new().start();
+        let api_root = format!("{}/api/yggdrasil", server.url);
+        assert!(matches!(user_type.refresh(&api_root, "access-token", "client-token"), Err(CoreError::NoMinecraftProfile)));
+    }
+
+    #[test]
+    fn validates_and_invalidates_tokens() {
+        let (server, user_type) = MockYggdrasil::new().start();
+        let api_root = format!("{}/api/yggdrasil", server.url);
+        assert!(user_type.validate(&api_root, "access-token", "client-token").unwrap());
+        assert!(!user_type.validate(&api_root, "expired-token", "client-token").unwrap());
+        match user_type.invalidate(&api_root, "access-token", "client-token") {
+            Err(CoreError::Auth(message)) => assert_eq!(message, format!("{api_root}/authserver/invalidate returned 500 InternalError")),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert!(matches!(user_type.validate(&format!("{}/missing", server.url), "access-token", "client-token"), Err(CoreError::HttpStatus { status: 404, .. })));
+    }
+
+    #[test]
+    fn downloads_authlib_injector() {
+        const JAR: &[u8] = b"authlib-injector";
+        let dir = temp_dir("authlib-injector");
+        let reference = format!("{dir}/reference.jar");
+        write_file(&reference, JAR);
+        let sha256 = Arc::new(Mutex::new(sha256_file(&reference).unwrap().to_uppercase()));
+        let server = {
+            let sha256 = sha256.clone();
+            MockServer::start(move |request| match request.path.as_str() {
+                "/artifact/latest.json" => MockResponse::json(200, &json!({
+                    "build_number": 55,
+                    "version": "1.2.5",
+                    "download_url": "https://authlib-injector.yushi.moe/artifact/55/authlib-injector-1.2.5.jar",
+                    "checksums": { "sha256": *sha256.lock().unwrap() }
+                })),
+                "/artifact/55/authlib-injector-1.2.5.jar" => MockResponse::new(200, JAR),
+                _ => MockResponse::new(404, ""),
+            })
+        };
+        let downloader = Downloader::new().with_retries(0).with_base_url(&server.url);
+        let path = format!("{dir}/authlib-injector.jar");
+        let jar_requests = || server.requests().iter().filter(|request| request.path.ends_with(".jar")).count();
+
+        download_authlib_injector(&downloader, &path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), JAR);
+        assert_eq!(jar_requests(), 1);
+
+        // 已经是最新版本
+        download_authlib_injector(&downloader, &path).unwrap();
+        assert_eq!(jar_requests(), 1);
+
+        *sha256.lock().unwrap() = "0".repeat(64);
+        match download_authlib_injector(&downloader, &path) {
+            Err(CoreError::HashMismatch { expected, .. }) => assert_eq!(expected, "0".repeat(64)),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert_eq!(jar_requests(), 2);
+        assert!(!Path::new(&path).exists());
+    }
+}
@@ -7,7 +7,7 @@ pub struct DeveloperUserType {
 }
 
 impl DeveloperUserType {
-    pub fn login(mut username: &str) -> UserResult {
+    pub fn login(username: &str) -> UserResult {
         UserResult::Developer { username: username.to_owned(), uuid: uuid_from_name(username.to_owned()) }
     }
 }
\ No newline at end of file
@@ -0,0 +1,472 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::{CoreError, Result};
+use crate::tools::http_tools::{self, http_error, read_json};
+
+use super::user_types::UserResult;
+
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+const OAUTH_SCOPE: &str = "XboxLive.signin offline_access";
+const MINECRAFT_RELYING_PARTY: &str = "rp://api.minecraftservices.com/";
+const XBOX_RELYING_PARTY: &str = "http://xboxlive.com";
+
+/// Microsoft登录用到的接口地址
+///
+/// 默认为官方地址，测试时可以全部替换为本地服务器
+#[derive(Debug, Clone)]
+pub struct MicrosoftEndpoints {
+    pub device_code_url: String,
+    pub token_url: String,
+    pub xbox_user_authenticate_url: String,
+    pub xsts_authorize_url: String,
+    pub minecraft_login_url: String,
+    pub entitlements_url: String,
+    pub profile_url: String,
+}
+
+impl Default for MicrosoftEndpoints {
+    fn default() -> Self {
+        Self {
+            device_code_url: "https://login.microsoftonline.com/consumers/oauth2/v2.0/devicecode".to_owned(),
+            token_url: "https://login.microsoftonline.com/consumers/oauth2/v2.0/token".to_owned(),
+            xbox_user_authenticate_url: "https://user.auth.xboxlive.com/user/authenticate".to_owned(),
+            xsts_authorize_url: "https://xsts.auth.xboxlive.com/xsts/authorize".to_owned(),
+            minecraft_login_url: "https://api.minecraftservices.com/authentication/login_with_xbox".to_owned(),
+            entitlements_url: "https://api.minecraftservices.com/entitlements/mcstore".to_owned(),
+            profile_url: "https://api.minecraftservices.com/minecraft/profile".to_owned(),
+        }
+    }
+}
+
+/// 设备代码登录时需要展示给用户的信息
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceCode {
+    pub device_code: String,
+    /// 用户需要输入的验证码
+    pub user_code: String,
+    /// 用户需要打开的网址
+    pub verification_uri: String,
+    /// 验证码的有效时间（秒）
+    pub expires_in: u64,
+    /// 轮询的间隔（秒）
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    #[serde(default)]
+    pub message: String,
+}
+
+/// Microsoft OAuth的令牌
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthToken {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: String,
+    pub expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct OAuthError {
+    error: String,
+    #[serde(default)]
+    error_description: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct XboxToken {
+    token: String,
+    display_claims: XboxDisplayClaims,
+}
+
+#[derive(Deserialize)]
+struct XboxDisplayClaims {
+    #[serde(default)]
+    xui: Vec<XboxUserInfo>,
+}
+
+#[derive(Deserialize)]
+struct XboxUserInfo {
+    #[serde(default)]
+    uhs: String,
+    #[serde(default)]
+    xid: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct XboxError {
+    #[serde(rename = "XErr", default)]
+    x_err: u64,
+    #[serde(rename = "Message", default)]
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct MinecraftToken {
+    access_token: String,
+    expires_in: i64,
+}
+
+#[derive(Deserialize)]
+struct Entitlements {
+    #[serde(default)]
+    items: Vec<EntitlementItem>,
+}
+
+#[derive(Deserialize)]
+struct EntitlementItem {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct MinecraftProfile {
+    id: String,
+    name: String,
+}
+
+/// 轮询OAuth令牌的结果
+enum TokenPoll {
+    Token(OAuthToken),
+    Pending,
+    SlowDown,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+/// Microsoft账户登录
+///
+/// 流程为：设备代码登录 -> Xbox Live -> XSTS -> Minecraft -> 检查是否购买 -> 获取角色
+pub struct MicrosoftUserType {
+    /// 启动器在Azure注册的应用ID
+    pub client_id: String,
+    pub endpoints: MicrosoftEndpoints,
+    agent: ureq::Agent,
+}
+
+impl MicrosoftUserType {
+    pub fn new(client_id: &str) -> Self {
+        Self { client_id: client_id.to_owned(), endpoints: MicrosoftEndpoints::default(), agent: http_tools::agent() }
+    }
+
+    pub fn with_endpoints(mut self, endpoints: MicrosoftEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// 使用设备代码登录
+    ///
+    /// `on_device_code`会收到需要展示给用户的验证码和网址，之后会一直等待，直到用户完成登录或者验证码过期
+    pub fn login(&self, on_device_code: impl FnOnce(&DeviceCode)) -> Result<UserResult> {
+        let device_code = self.request_device_code()?;
+        on_device_code(&device_code);
+        let token = self.poll_device_token(&device_code)?;
+        self.login_with_oauth_token(&token)
+    }
+
+    /// 申请设备代码
+    pub fn request_device_code(&self) -> Result<DeviceCode> {
+        let url = &self.endpoints.device_code_url;
+        let response = self.agent.post(url)
+            .send_form(&[("client_id", self.client_id.as_str()), ("scope", OAUTH_SCOPE)])
+            .map_err(|err| oauth_error(url, err))?;
+        read_json(url, response)
+    }
+
+    /// 轮询直到用户完成登录，返回OAuth令牌
+    pub fn poll_device_token(&self, device_code: &DeviceCode) -> Result<OAuthToken> {
+        let deadline = Instant::now() + Duration::from_secs(device_code.expires_in);
+        let mut interval = device_code.interval;
+        loop {
+            if Instant::now() >= deadline {
+                return Err(CoreError::Auth("Device code expired".to_owned()));
+            }
+            thread::sleep(Duration::from_secs(interval));
+            let params = [
+                ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                ("client_id", self.client_id.as_str()),
+                ("device_code", device_code.device_code.as_str()),
+            ];
+            match self.request_token(&params)? {
+                TokenPoll::Token(token) => return Ok(token),
+                TokenPoll::Pending => { }
+                TokenPoll::SlowDown => interval += 5,
+            }
+        }
+    }
+
+    /// 使用refresh token重新登录，不需要用户再次输入验证码
+    pub fn refresh(&self, refresh_token: &str) -> Result<UserResult> {
+        let params = [
+            ("grant_type", "refresh_token"),
+            ("client_id", self.client_id.as_str()),
+            ("refresh_token", refresh_token),
+            ("scope", OAUTH_SCOPE),
+        ];
+        match self.request_token(&params)? {
+            TokenPoll::Token(mut token) => {
+                // 服务器没有返回新的refresh token时继续使用旧的
+                if token.refresh_token.is_empty() {
+                    token.refresh_token = refresh_token.to_owned();
+                }
+                self.login_with_oauth_token(&token)
+            }
+            TokenPoll::Pending | TokenPoll::SlowDown => Err(CoreError::Auth("Failed to refresh Microsoft token".to_owned())),
+        }
+    }
+
+    /// 请求OAuth令牌
+    fn request_token(&self, params: &[(&str, &str)]) -> Result<TokenPoll> {
+        let url = &self.endpoints.token_url;
+        match self.agent.post(url).send_form(params) {
+            Ok(response) => Ok(TokenPoll::Token(read_json(url, response)?)),
+            Err(ureq::Error::Status(_, response)) => {
+                let error: OAuthError = read_json(url, response)?;
+                match error.error.as_str() {
+                    "authorization_pending" => Ok(TokenPoll::Pending),
+                    "slow_down" => Ok(TokenPoll::SlowDown),
+                    _ => Err(CoreError::Auth(format!("{}: {}", error.error, error.error_description))),
+                }
+            }
+            Err(err) => Err(http_error(url, err)),
+        }
+    }
+
+    /// 使用已有的OAuth令牌完成剩下的登录流程
+    pub fn login_with_oauth_token(&self, token: &OAuthToken) -> Result<UserResult> {
+        let xbox_token = self.xbox_user_authenticate(&token.access_token)?;
+        let xsts_token = self.xsts_authorize(&xbox_token.token, MINECRAFT_RELYING_PARTY)?;
+        let user_hash = xsts_token.display_claims.xui.first()
+            .map(|info| info.uhs.clone())
+            .ok_or_else(|| CoreError::Auth("XSTS response has no user hash".to_owned()))?;
+        let minecraft_token = self.login_with_xbox(&user_hash, &xsts_token.token)?;
+        self.check_entitlements(&minecraft_token.access_token)?;
+        let profile = self.get_profile(&minecraft_token.access_token)?;
+        // xuid只用于遥测，获取失败不影响登录
+        let xuid = self.xsts_authorize(&xbox_token.token, XBOX_RELYING_PARTY).ok()
+            .and_then(|token| token.display_claims.xui.into_iter().next())
+            .and_then(|info| info.xid)
+            .unwrap_or_default();
+
+        Ok(UserResult::Microsoft {
+            username: profile.name,
+            uuid: profile.id,
+            access_token: minecraft_token.access_token,
+            refresh_token: token.refresh_token.clone(),
+            xuid,
+            expires_at: Utc::now() + chrono::Duration::seconds(minecraft_token.expires_in),
+        })
+    }
+
+    fn xbox_user_authenticate(&self, access_token: &str) -> Result<XboxToken> {
+        self.post_xbox(&self.endpoints.xbox_user_authenticate_url, json!({
+            "Properties": {
+                "AuthMethod": "RPS",
+                "SiteName": "user.auth.xboxlive.com",
+                "RpsTicket": format!("d={access_token}")
+            },
+            "RelyingParty": "http://auth.xboxlive.com",
+            "TokenType": "JWT"
+        }))
+    }
+
+    fn xsts_authorize(&self, xbox_token: &str, relying_party: &str) -> Result<XboxToken> {
+        self.post_xbox(&self.endpoints.xsts_authorize_url, json!({
+            "Properties": {
+                "SandboxId": "RETAIL",
+                "UserTokens": [xbox_token]
+            },
+            "RelyingParty": relying_party,
+            "TokenType": "JWT"
+        }))
+    }
+
+    fn login_with_xbox(&self, user_hash: &str, xsts_token: &str) -> Result<MinecraftToken> {
+        self.post_json(&self.endpoints.minecraft_login_url, json!({
+            "identityToken": format!("XBL3.0 x={user_hash};{xsts_token}")
+        }))
+    }
+
+    fn check_entitlements(&self, access_token: &str) -> Result<()> {
+        let url = &self.endpoints.entitlements_url;
+        let response = self.agent.get(url)
+            .set("Authorization", &format!("Bearer {access_token}"))
+            .call()
+            .map_err(|err| http_error(url, err))?;
+        let entitlements: Entitlements = read_json(url, response)?;
+        if entitlements.items.iter().any(|item| item.name == "product_minecraft" || item.name == "game_minecraft") {
+            Ok(())
+        } else {
+            Err(CoreError::GameNotOwned)
+        }
+    }
+
+    fn get_profile(&self, access_token: &str) -> Result<MinecraftProfile> {
+        let url = &self.endpoints.profile_url;
+        match self.agent.get(url).set("Authorization", &format!("Bearer {access_token}")).call() {
+            Ok(response) => read_json(url, response),
+            Err(ureq::Error::Status(404, _)) => Err(CoreError::NoMinecraftProfile),
+            Err(err) => Err(http_error(url, err)),
+        }
+    }
+
+    fn post_json<T: DeserializeOwned>(&self, url: &str, body: Value) -> Result<T> {
+        let response = self.agent.post(url)
+            .set("Content-Type", "application/json")
+            .set("Accept", "application/json")
+            .send_string(&body.to_string())
+            .map_err(|err| http_error(url, err))?;
+        read_json(url, response)
+    }
+
+    /// Xbox的接口出错时会在`XErr`中给出原因
+    fn post_xbox(&self, url: &str, body: Value) -> Result<XboxToken> {
+        let result = self.agent.post(url)
+            .set("Content-Type", "application/json")
+            .set("Accept", "application/json")
+            .send_string(&body.to_string());
+        match result {
+            Ok(response) => read_json(url, response),
+            Err(ureq::Error::Status(status, response)) => {
+                let error: Option<XboxError> = response.into_string().ok().and_then(|body| serde_json::from_str(&body).ok());
+                let message = match error.as_ref().map(|error| error.x_err) {
+                    Some(2148916233) => "This Microsoft account has no Xbox account".to_owned(),
+                    Some(2148916235) => "Xbox Live is not available in this country".to_owned(),
+                    Some(2148916236) | Some(2148916237) => "This account needs adult verification on the Xbox website".to_owned(),
+                    Some(2148916238) => "This is a child account and must be added to a family by an adult".to_owned(),
+                    _ => format!("{url} returned {status} {}", error.map(|error| error.message).unwrap_or_default()),
+                };
+                Err(CoreError::Auth(message))
+            }
+            Err(err) => Err(http_error(url, err)),
+        }
+    }
+}
+
+/// 设备代码接口出错时返回的是OAuth错误
+fn oauth_error(url: &str, err: ureq::Error) -> CoreError {
+    match err {
+        ureq::Error::Status(_, response) => match read_json::<OAuthError>(url, response) {
+            Ok(error) => CoreError::Auth(format!("{}: {}", error.error, error.error_description)),
+            Err(err) => err,
+        },
+        err => http_error(url, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tools::test_tools::{MockRequest, MockResponse, MockServer};
+
+    use super::*;
+
+    /// 模拟的Microsoft登录服务器
+    struct MockMicrosoft {
+        /// XSTS返回的`XErr`
+        xsts_error: Option<u64>,
+        entitlements: Vec<&'static str>,
+        profile_status: u16,
+    }
+
+    impl MockMicrosoft {
+        fn new() -> Self {
+            Self { xsts_error: None, entitlements: vec!["product_minecraft", "game_minecraft"], profile_status: 200 }
+        }
+
+        fn respond(&self, request: &MockRequest) -> MockResponse {
+            let body: Value = serde_json::from_str(&request.body).unwrap_or(Value::Null);
+            match request.path.as_str() {
+                "/token" => MockResponse::json(200, &json!({ "access_token": "msa-token", "expires_in": 3600 })),
+                "/user/authenticate" => {
+                    assert_eq!(body["Properties"]["RpsTicket"], "d=msa-token");
+                    MockResponse::json(200, &json!({ "Token": "xbl-token", "DisplayClaims": { "xui": [{ "uhs": "user-hash" }] } }))
+                }
+                "/xsts/authorize" => match self.xsts_error {
+                    Some(x_err) => MockResponse::json(401, &json!({ "XErr": x_err, "Message": "" })),
+                    None if body["RelyingParty"] == XBOX_RELYING_PARTY => {
+                        MockResponse::json(200, &json!({ "Token": "xbox-xsts", "DisplayClaims": { "xui": [{ "uhs": "user-hash", "xid": "2535400000000000" }] } }))
+                    }
+                    None => MockResponse::json(200, &json!({ "Token": "xsts-token", "DisplayClaims": { "xui": [{ "uhs": "user-hash" }] } })),
+                },
+                "/login_with_xbox" => {
+                    assert_eq!(body["identityToken"], "XBL3.0 x=user-hash;xsts-token");
+                    MockResponse::json(200, &json!({ "access_token": "minecraft-token", "expires_in": 86400 }))
+                }
+                "/entitlements" => {
+                    let items: Vec<Value> = self.entitlements.iter().map(|name| json!({ "name": name })).collect();
+                    MockResponse::json(200, &json!({ "items": items }))
+                }
+                "/profile" if self.profile_status == 200 => {
+                    MockResponse::json(200, &json!({ "id": "069a79f444e94726a5befca90e38aaf5", "name": "Notch" }))
+                }
+                "/profile" => MockResponse::json(self.profile_status, &json!({ "error": "NOT_FOUND" })),
+                _ => MockResponse::new(404, ""),
+            }
+        }
+
+        fn start(self) -> (MockServer, MicrosoftUserType) {
+            let server = MockServer::start(move |request| self.respond(request));
+            let endpoints = MicrosoftEndpoints {
+                device_code_url: format!("{}/devicecode", server.url),
+                token_url: format!("{}/token", server.url),
+                xbox_user_authenticate_url: format!("{}/user/authenticate", server.url),
+                xsts_authorize_url: format!("{}/xsts/authorize", server.url),
+                minecraft_login_url: format!("{}/login_with_xbox", server.url),
+                entitlements_url: format!("{}/entitlements", server.url),
+                profile_url: format!("{}/profile", server.url),
+            };
+            let user_type = MicrosoftUserType::new("client-id").with_endpoints(endpoints);
+            (server, user_type)
+        }
+    }
+
+    #[test]
+    fn logs_in_through_xbox_and_minecraft() {
+        let (server, user_type) = MockMicrosoft::new().start();
+        let result = user_type.refresh("old-refresh-token").unwrap();
+        let UserResult::Microsoft { username, uuid, access_token, refresh_token, xuid, expires_at } = result else {
+            panic!("expected a Microsoft account");
+        };
+        assert_eq!(username, "Notch");
+        assert_eq!(uuid, "069a79f444e94726a5befca90e38aaf5");
+        assert_eq!(access_token, "minecraft-token");
+        // 服务器没有返回新的refresh token
+        assert_eq!(refresh_token, "old-refresh-token");
+        assert_eq!(xuid, "2535400000000000");
+        assert!(expires_at > Utc::now());
+
+        let requests = server.requests();
+        assert!(requests[0].body.contains("grant_type=refresh_token"));
+        let profile = requests.iter().find(|request| request.path == "/profile").unwrap();
+        assert_eq!(profile.header("Authorization"), Some("Bearer minecraft-token"));
+    }
+
+    #[test]
+    fn reports_xsts_errors() {
+        for (x_err, message) in [(2148916233, "no Xbox account"), (2148916238, "child account")] {
+            let (_server, user_type) = MockMicrosoft { xsts_error: Some(x_err), ..MockMicrosoft::new() }.start();
+            match user_type.refresh("refresh-token") {
+                Err(CoreError::Auth(error)) => assert!(error.contains(message), "{error}"),
+                other => panic!("unexpected result for {x_err}: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn reports_game_not_owned() {
+        let (_server, user_type) = MockMicrosoft { entitlements: vec![], ..MockMicrosoft::new() }.start();
+        assert!(matches!(user_type.refresh("refresh-token"), Err(CoreError::GameNotOwned)));
+    }
+
+    #[test]
+    fn reports_missing_profile() {
+        let (_server, user_type) = MockMicrosoft { profile_status: 404, ..MockMicrosoft::new() }.start();
+        assert!(matches!(user_type.refresh("refresh-token"), Err(CoreError::NoMinecraftProfile)));
+    }
+}
@@ -1,2 +1,5 @@
 pub mod user_types;
-pub mod developer_user_type;
\ No newline at end of file
+pub mod developer_user_type;
+pub mod microsoft_user_type;
+pub mod custom_auth_user_type;
+pub mod account_store;
\ No newline at end of file
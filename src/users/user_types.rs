@@ -1,13 +1,58 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
 pub enum UserResult {
     Developer {
         username: String,
         uuid: String
     },
     Microsoft {
-
+        username: String,
+        uuid: String,
+        access_token: String,
+        /// 用于刷新登录的Microsoft OAuth refresh token
+        refresh_token: String,
+        xuid: String,
+        /// Minecraft access token的过期时间
+        expires_at: DateTime<Utc>
     },
     CustomAuth {
+        username: String,
+        uuid: String,
+        access_token: String,
+        client_token: String,
+        /// Yggdrasil API的根地址
+        server_url: String,
+        /// base64编码的API元数据，启动时传给authlib-injector，省去一次请求
+        prefetched_metadata: String
+    }
+}
+
+impl UserResult {
+    pub fn username(&self) -> &str {
+        match self {
+            UserResult::Developer { username, .. }
+            | UserResult::Microsoft { username, .. }
+            | UserResult::CustomAuth { username, .. } => username,
+        }
+    }
+
+    pub fn uuid(&self) -> &str {
+        match self {
+            UserResult::Developer { uuid, .. }
+            | UserResult::Microsoft { uuid, .. }
+            | UserResult::CustomAuth { uuid, .. } => uuid,
+        }
+    }
 
+    /// 账户的唯一标识，同一个角色在不同的登录方式下是不同的账户
+    pub fn account_key(&self) -> String {
+        match self {
+            UserResult::Developer { uuid, .. } => format!("developer:{uuid}"),
+            UserResult::Microsoft { uuid, .. } => format!("microsoft:{uuid}"),
+            UserResult::CustomAuth { uuid, server_url, .. } => format!("custom:{server_url}:{uuid}"),
+        }
     }
-}
\ No newline at end of file
+}